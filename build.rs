@@ -0,0 +1,79 @@
+fn main() {
+    #[cfg(feature = "node")]
+    napi_build::setup();
+    #[cfg(feature = "baked-specs")]
+    baked::write();
+}
+
+// The spec generation of the crate, which only depends on the field traits
+#[cfg(feature = "baked-specs")]
+#[allow(dead_code)]
+#[path = "src/grain.rs"]
+mod grain;
+#[cfg(feature = "baked-specs")]
+#[allow(dead_code)]
+#[path = "src/optimize.rs"]
+mod optimize;
+
+/// Tables of `src/baked.rs`, in the format of `PrecomputedSpec::to_bytes`
+#[cfg(feature = "baked-specs")]
+mod baked {
+    use std::{env, fmt::Write, fs, path::Path};
+
+    use halo2curves::{bn256, group::ff::PrimeField, pasta};
+
+    use crate::{grain, optimize};
+
+    fn table<F: PrimeField, const T: usize, const RATE: usize>(r_f: usize, r_p: usize) -> Vec<u8> {
+        let (constants, mds) = grain::parameters::<F, T>(r_f, r_p);
+        let optimized = optimize::optimize::<F, T, RATE>(r_f, &constants, &mds).unwrap();
+
+        let mut bytes = b"PSPC".to_vec();
+        bytes.push(1);
+        for n in [T, RATE, r_f, r_p, F::MODULUS.len()] {
+            bytes.extend((n as u32).to_be_bytes());
+        }
+        bytes.extend(F::MODULUS.as_bytes());
+        let elements = optimized
+            .start
+            .iter()
+            .flatten()
+            .chain(&optimized.partial)
+            .chain(optimized.end.iter().flatten())
+            .chain(mds.iter().flatten())
+            .chain(optimized.pre_sparse_mds.iter().flatten())
+            .chain(
+                optimized
+                    .sparse
+                    .iter()
+                    .flat_map(|(row, col)| row.iter().chain(col)),
+            );
+        for element in elements {
+            let mut repr = element.to_repr().as_ref().to_vec();
+            repr.reverse();
+            bytes.extend(repr);
+        }
+        bytes
+    }
+
+    pub fn write() {
+        let out = env::var("OUT_DIR").unwrap();
+        let tables = [
+            ("bn256_3_2_8_56", table::<bn256::Fr, 3, 2>(8, 56)),
+            ("bn256_4_3_8_56", table::<bn256::Fr, 4, 3>(8, 56)),
+            ("pallas_3_2_8_56", table::<pasta::Fp, 3, 2>(8, 56)),
+            ("vesta_3_2_8_56", table::<pasta::Fq, 3, 2>(8, 56)),
+        ];
+        let mut list = String::from("&[\n");
+        for (name, bytes) in tables {
+            let path = Path::new(&out).join(format!("{}.bin", name));
+            fs::write(&path, bytes).unwrap();
+            writeln!(list, "    include_bytes!({:?}),", path).unwrap();
+        }
+        list.push(']');
+        fs::write(Path::new(&out).join("baked_specs.rs"), list).unwrap();
+        println!("cargo:rerun-if-changed=build.rs");
+        println!("cargo:rerun-if-changed=src/grain.rs");
+        println!("cargo:rerun-if-changed=src/optimize.rs");
+    }
+}
@@ -0,0 +1,82 @@
+//! `#[derive(Absorb)]`, implementing `poseidon_circuit::absorb::AbsorbInRO`
+//! for a struct by absorbing its number of fields, then its fields in
+//! declaration order. `#[absorb(domain = n)]` absorbs the integer `n` first.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Index, LitInt};
+
+#[proc_macro_derive(Absorb, attributes(absorb))]
+pub fn derive_absorb(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Absorb can only be derived for structs",
+        ));
+    };
+
+    let mut domain = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("absorb"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("domain") {
+                domain = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u64>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `domain = <integer>`"))
+            }
+        })?;
+    }
+
+    let absorb = quote!(::poseidon_circuit::absorb::AbsorbInRO::<__C>::absorb_in_ro);
+    let domain = domain.map(|domain| quote!(#absorb(&#domain, ro);));
+    let len = data.fields.len() as u64;
+    let members = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        });
+
+    let mut generics = input.generics.clone();
+    generics
+        .params
+        .push(parse_quote!(__C: ::poseidon_circuit::halo2_proofs::arithmetic::CurveAffine));
+    let where_clause = generics.make_where_clause();
+    for field in &data.fields {
+        let ty = &field.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::poseidon_circuit::absorb::AbsorbInRO<__C>));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let name = &input.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::poseidon_circuit::absorb::AbsorbInRO<__C> for #name #ty_generics
+            #where_clause
+        {
+            fn absorb_in_ro<__R: ::poseidon_circuit::ro_types::ROTrait<__C>>(&self, ro: &mut __R) {
+                #domain
+                #absorb(&#len, ro);
+                #( #absorb(&self.#members, ro); )*
+            }
+        }
+    })
+}
@@ -0,0 +1,135 @@
+//! One folding step of a Nova-style IVC scheme, end to end.
+//!
+//! The prover folds two committed instances into one, deriving the folding
+//! challenge from the commitments with the crate's random oracle. The verifier
+//! recomputes the challenge natively and, with the Poseidon chip, in a circuit
+//! as the augmented circuit of an IVC scheme would.
+//!
+//! Instances are Pedersen commitments on Pallas to witness vectors over its
+//! scalar field `Fq`. Their coordinates live in `Fp`, the scalar field of
+//! Vesta, so the RO runs over `Fp` and its output is truncated to a 128-bit
+//! challenge in `Fq`, like [`NovaRO`] does. The generators are multiples of
+//! the Pallas generator with known discrete logarithms, so the commitments
+//! are not binding: this only illustrates the data flow.
+//!
+//! ```text
+//! cargo run --example ivc_folding
+//! ```
+use halo2_proofs::{arithmetic::CurveAffine, dev::MockProver};
+use halo2curves::{
+    group::{ff::PrimeField, prime::PrimeCurveAffine, Curve},
+    pasta::{EpAffine, EqAffine, Fp, Fq},
+};
+use poseidon_circuit::{
+    nova::{NovaRO, NUM_CHALLENGE_BITS},
+    poseidon_hash::PoseidonHash,
+    ro_types::ROTrait,
+    test_circuit::TestCircuit,
+};
+
+// the parameters of `TestCircuit`
+const T: usize = 4;
+const RATE: usize = 3;
+const R_F: usize = 8;
+const R_P: usize = 56;
+const K: u32 = 11;
+
+type RO = PoseidonHash<EqAffine, Fp, T, RATE>;
+type Constants = <RO as ROTrait<EqAffine>>::Constants;
+
+/// A committed instance: `commitment = sum_i w_i * G_i` with scale `u`
+#[derive(Clone, Debug)]
+struct Instance {
+    commitment: EpAffine,
+    u: Fq,
+}
+
+fn generators(n: usize) -> Vec<EpAffine> {
+    (0..n)
+        .map(|i| (EpAffine::generator() * Fq::from(i as u64 + 2)).to_affine())
+        .collect()
+}
+
+fn commit(generators: &[EpAffine], witness: &[Fq]) -> EpAffine {
+    generators
+        .iter()
+        .zip(witness)
+        .map(|(g, w)| *g * w)
+        .reduce(|acc, term| acc + term)
+        .unwrap()
+        .to_affine()
+}
+
+/// RO inputs for the challenge: `(x, y, is_infinity)` of both commitments,
+/// in the order [`NovaRO::absorb_point`] absorbs them
+fn ro_inputs(left: &Instance, right: &Instance) -> Vec<Fp> {
+    [left.commitment, right.commitment]
+        .iter()
+        .flat_map(|point| {
+            let coordinates = point.coordinates().unwrap();
+            [*coordinates.x(), *coordinates.y(), Fp::from(0)]
+        })
+        .collect()
+}
+
+/// The prover's challenge, through the Nova RO conventions
+fn challenge(left: &Instance, right: &Instance) -> Fq {
+    let mut ro = NovaRO::<EqAffine, Fp, T, RATE>::new(Constants::new(R_F, R_P), 6);
+    ro.absorb_point(&left.commitment);
+    ro.absorb_point(&right.commitment);
+    ro.squeeze(NUM_CHALLENGE_BITS)
+}
+
+/// Low 128 bits of an RO output as a challenge
+fn truncate(digest: &Fp) -> Fq {
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    repr.as_mut()[..NUM_CHALLENGE_BITS / 8]
+        .copy_from_slice(&digest.to_repr().as_ref()[..NUM_CHALLENGE_BITS / 8]);
+    Fq::from_repr(repr).unwrap()
+}
+
+fn fold(left: &Instance, right: &Instance, r: Fq) -> Instance {
+    Instance {
+        commitment: (left.commitment.to_curve() + right.commitment * r).to_affine(),
+        u: left.u + r * right.u,
+    }
+}
+
+fn main() {
+    let generators = generators(4);
+    let witnesses = [[1, 2, 3, 4].map(Fq::from), [5, 6, 7, 8].map(Fq::from)];
+    let [left, right] = witnesses.map(|witness| Instance {
+        commitment: commit(&generators, &witness),
+        u: Fq::from(1),
+    });
+
+    // prover: derive the challenge and fold instances and witnesses
+    let r = challenge(&left, &right);
+    let folded = fold(&left, &right, r);
+    let folded_witness = witnesses[0]
+        .iter()
+        .zip(&witnesses[1])
+        .map(|(w1, w2)| *w1 + r * w2)
+        .collect::<Vec<_>>();
+    assert_eq!(folded.commitment, commit(&generators, &folded_witness));
+    assert_eq!(folded.u, Fq::from(1) + r);
+    println!("folded with challenge {:?}", r);
+
+    // verifier: recompute the RO output natively...
+    let inputs = ro_inputs(&left, &right);
+    let mut ro = RO::new(Constants::new(R_F, R_P));
+    ro.update(&inputs);
+    let digest = ro.squeeze();
+    assert_eq!(truncate(&digest), r);
+
+    // ...and in the circuit, which exposes it as its public input. An IVC
+    // scheme would go on to decompose it and fold in-circuit.
+    let prover = MockProver::run(K, &TestCircuit::new(inputs), vec![vec![digest]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    println!("circuit recomputed RO output {:?}", digest);
+}
+
+#[test]
+fn test_ivc_folding() {
+    main();
+}
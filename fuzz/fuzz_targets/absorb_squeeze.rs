@@ -0,0 +1,53 @@
+//! Absorbing the same elements and bytes in one call or one at a time must
+//! give the same digests, whatever the interleaving with squeezes.
+#![no_main]
+
+use std::sync::OnceLock;
+
+use libfuzzer_sys::{arbitrary::Arbitrary, fuzz_target};
+use poseidon_circuit::{
+    halo2curves::bn256::{Fr, G1Affine},
+    poseidon_hash::PoseidonHash,
+    precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+};
+
+type Hasher = PoseidonHash<G1Affine, Fr, 4, 3>;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Update(Vec<u64>),
+    UpdateBytes(Vec<u8>),
+    Squeeze,
+}
+
+fn spec() -> &'static PrecomputedSpec<Fr, 4, 3> {
+    static SPEC: OnceLock<PrecomputedSpec<Fr, 4, 3>> = OnceLock::new();
+    SPEC.get_or_init(|| PrecomputedSpec::new(8, 56))
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut whole = Hasher::new(spec().clone());
+    let mut split = Hasher::new(spec().clone());
+    for op in ops {
+        match op {
+            Op::Update(values) => {
+                let elements = values.into_iter().map(Fr::from).collect::<Vec<_>>();
+                whole.update(&elements);
+                for element in elements {
+                    split.update(&[element]);
+                }
+            }
+            Op::UpdateBytes(bytes) => {
+                whole.update_bytes(&bytes);
+                // an empty call still starts a byte string
+                split.update_bytes(&[]);
+                for byte in bytes {
+                    split.update_bytes(&[byte]);
+                }
+            }
+            Op::Squeeze => assert_eq!(whole.squeeze(), split.squeeze()),
+        }
+    }
+    assert_eq!(whole.squeeze(), split.squeeze());
+});
@@ -0,0 +1,26 @@
+//! The strict decoders never panic and accept exactly the canonical
+//! encodings; the reducing decoder accepts everything.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use poseidon_circuit::{encoding, halo2curves::bn256::Fr};
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(value) = encoding::from_be_bytes::<Fr>(data) {
+        assert_eq!(encoding::to_be_bytes(&value), data);
+        assert_eq!(encoding::from_be_bytes_reduced::<Fr>(data), value);
+    }
+    let _ = encoding::from_be_bytes_reduced::<Fr>(data);
+
+    if let Ok(text) = std::str::from_utf8(data) {
+        if let Some(value) = encoding::from_hex::<Fr>(text) {
+            assert_eq!(encoding::from_hex(&encoding::to_hex(&value)), Some(value));
+        }
+        if let Some(value) = encoding::from_decimal::<Fr>(text) {
+            assert_eq!(
+                encoding::from_decimal(&encoding::to_decimal(&value)),
+                Some(value)
+            );
+        }
+    }
+});
@@ -0,0 +1,49 @@
+//! The native hash and the PSE `poseidon` sponge must return the same
+//! digests for any session of updates and squeezes.
+#![no_main]
+
+use std::sync::OnceLock;
+
+use libfuzzer_sys::{arbitrary::Arbitrary, fuzz_target};
+use poseidon_circuit::{
+    diff_fuzz::Differential,
+    encoding,
+    halo2curves::bn256::{Fr, G1Affine},
+};
+
+type Sponges = Differential<G1Affine, Fr, 4, 3>;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    /// Elements reduced from 32 big-endian bytes each
+    Update(Vec<[u8; 32]>),
+    Squeeze,
+}
+
+fn prototype() -> &'static Sponges {
+    static SPONGES: OnceLock<Sponges> = OnceLock::new();
+    SPONGES.get_or_init(|| Sponges::new(8, 56))
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut sponges = prototype().clone();
+    for op in ops {
+        match op {
+            Op::Update(values) => {
+                let elements = values
+                    .iter()
+                    .map(|bytes| encoding::from_be_bytes_reduced::<Fr>(bytes))
+                    .collect::<Vec<_>>();
+                sponges.update(&elements);
+            }
+            Op::Squeeze => {
+                if let Err(mismatch) = sponges.squeeze() {
+                    panic!("{}", mismatch);
+                }
+            }
+        }
+    }
+    if let Err(mismatch) = sponges.squeeze() {
+        panic!("{}", mismatch);
+    }
+});
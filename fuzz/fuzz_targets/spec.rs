@@ -0,0 +1,21 @@
+//! Deserializing a spec either fails cleanly or yields a spec that
+//! serializes back to the same round numbers.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use poseidon_circuit::{halo2curves::bn256::Fr, precomputed::PrecomputedSpec};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Config {
+    #[serde(with = "poseidon_circuit::encoding::spec")]
+    spec: PrecomputedSpec<Fr, 4, 3>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(config) = serde_json::from_slice::<Config>(data) {
+        let json = serde_json::to_string(&config).unwrap();
+        let decoded: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
+});
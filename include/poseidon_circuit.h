@@ -0,0 +1,74 @@
+#ifndef POSEIDON_CIRCUIT_H
+#define POSEIDON_CIRCUIT_H
+
+/* Generated by cbindgen from src/bindings/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define POSEIDON_OK 0
+
+#define POSEIDON_ERR_NULL -1
+
+#define POSEIDON_ERR_INVALID_INPUT -2
+
+#define POSEIDON_ELEMENT_BYTES 32
+
+/**
+ * Opaque sponge handle
+ */
+typedef struct PoseidonHasher PoseidonHasher;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Allocates a sponge; release it with [`poseidon_hasher_free`]
+ */
+PoseidonHasher *poseidon_hasher_new(void);
+
+/**
+ * # Safety
+ *
+ * `hasher` must be null or returned by [`poseidon_hasher_new`] and not yet
+ * freed
+ */
+void poseidon_hasher_free(PoseidonHasher *hasher);
+
+/**
+ * Absorbs `len / 32` field elements from `bytes`
+ *
+ * # Safety
+ *
+ * `hasher` must be a live handle and `bytes` valid for reads of `len` bytes
+ */
+int poseidon_hasher_absorb(PoseidonHasher *hasher, const uint8_t *bytes, size_t len);
+
+/**
+ * Writes the next 32-byte challenge to `out`
+ *
+ * # Safety
+ *
+ * `hasher` must be a live handle and `out` valid for writes of 32 bytes
+ */
+int poseidon_hasher_squeeze(PoseidonHasher *hasher, uint8_t *out);
+
+/**
+ * One-shot hash of `len / 32` field elements
+ *
+ * # Safety
+ *
+ * `bytes` must be valid for reads of `len` bytes and `out` for writes of 32
+ * bytes
+ */
+int poseidon_hash(const uint8_t *bytes, size_t len, uint8_t *out);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* POSEIDON_CIRCUIT_H */
@@ -0,0 +1,23 @@
+// Writes the circomlibjs vectors of src/circom.rs to stdout: the decimal
+// poseidon([1, .., n]) for n = 1..16, with the circomlibjs version that
+// computed them.
+//
+//   npm install
+//   npm run circom-vectors
+import { readFileSync } from "node:fs";
+
+import { buildPoseidon } from "circomlibjs";
+
+const { version } = JSON.parse(
+  readFileSync("node_modules/circomlibjs/package.json", "utf8"),
+);
+const poseidon = await buildPoseidon();
+const vectors = [];
+for (let n = 1; n <= 16; n++) {
+  const inputs = Array.from({ length: n }, (_, i) => BigInt(i + 1));
+  vectors.push({
+    inputs: inputs.map(String),
+    output: poseidon.F.toString(poseidon(inputs)),
+  });
+}
+console.log(JSON.stringify({ circomlibjs: version, vectors }, null, 2));
@@ -0,0 +1,214 @@
+//! Absorption of structured values into any [`ROTrait`] implementation.
+//!
+//! [`AbsorbInRO`] absorbs a value as scalars of the curve through
+//! [`ROTrait::absorb`]:
+//!
+//! - a scalar as itself and an integer as the scalar of its value
+//! - a point the way [`PoseidonHash::absorb_point`] absorbs it with its
+//!   default limbs: the [`BITS`]-bit limbs of both coordinates, then `0`, or
+//!   all-zero limbs then `1` for the identity
+//! - a tuple or an array element by element, and a vector prefixed by its
+//!   length
+//!
+//! With the `derive` feature, `#[derive(Absorb)]` implements it for a
+//! struct: the number of fields is absorbed first, then the fields in
+//! declaration order. A struct attribute `#[absorb(domain = n)]` absorbs the
+//! integer `n` before the number of fields, so that types with the same
+//! layout absorb differently.
+//!
+//! Scalars and points are implemented for the bn256 and pasta curves only: a
+//! blanket implementation over `C::Scalar` would conflict with the ones over
+//! integers and containers.
+//!
+//! [`PoseidonHash::absorb_point`]: crate::poseidon_hash::PoseidonHash::absorb_point
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::{
+    bn256,
+    group::ff::{Field, PrimeField},
+    pasta, Coordinates,
+};
+#[cfg(feature = "derive")]
+pub use poseidon_circuit_derive::Absorb;
+
+use crate::{encoding, ro_types::ROTrait, transcript::BITS};
+
+pub trait AbsorbInRO<C: CurveAffine> {
+    /// Absorbs `self` into `ro`
+    fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R);
+}
+
+fn absorb_point<C: CurveAffine, R: ROTrait<C>>(point: &C, ro: &mut R) {
+    let limbs = (C::Base::NUM_BITS as usize).div_ceil(BITS);
+    match Option::<Coordinates<C>>::from(point.coordinates()) {
+        Some(coordinates) => {
+            for coordinate in [coordinates.x(), coordinates.y()] {
+                for limb in encoding::to_limbs::<_, C::Scalar>(coordinate, BITS) {
+                    ro.absorb(limb);
+                }
+            }
+            ro.absorb(C::Scalar::ZERO);
+        }
+        None => {
+            for _ in 0..2 * limbs {
+                ro.absorb(C::Scalar::ZERO);
+            }
+            ro.absorb(C::Scalar::ONE);
+        }
+    }
+}
+
+macro_rules! absorb_curve {
+    ($($curve:ty => $scalar:ty),*) => {
+        $(
+            impl AbsorbInRO<$curve> for $scalar {
+                fn absorb_in_ro<R: ROTrait<$curve>>(&self, ro: &mut R) {
+                    ro.absorb(*self);
+                }
+            }
+
+            impl AbsorbInRO<$curve> for $curve {
+                fn absorb_in_ro<R: ROTrait<$curve>>(&self, ro: &mut R) {
+                    absorb_point(self, ro);
+                }
+            }
+        )*
+    };
+}
+
+absorb_curve!(
+    bn256::G1Affine => bn256::Fr,
+    pasta::EpAffine => pasta::Fq,
+    pasta::EqAffine => pasta::Fp
+);
+
+macro_rules! absorb_integer {
+    ($($int:ty),*) => {
+        $(
+            impl<C: CurveAffine> AbsorbInRO<C> for $int {
+                fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R) {
+                    ro.absorb(C::Scalar::from(*self as u64));
+                }
+            }
+        )*
+    };
+}
+
+absorb_integer!(bool, u8, u16, u32, u64, usize);
+
+impl<C: CurveAffine> AbsorbInRO<C> for u128 {
+    fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R) {
+        ro.absorb(C::Scalar::from_u128(*self));
+    }
+}
+
+impl<C: CurveAffine, A: AbsorbInRO<C> + ?Sized> AbsorbInRO<C> for &A {
+    fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R) {
+        (**self).absorb_in_ro(ro);
+    }
+}
+
+impl<C: CurveAffine, A: AbsorbInRO<C>, const N: usize> AbsorbInRO<C> for [A; N] {
+    fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R) {
+        for element in self {
+            element.absorb_in_ro(ro);
+        }
+    }
+}
+
+impl<C: CurveAffine, A: AbsorbInRO<C>> AbsorbInRO<C> for Vec<A> {
+    fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R) {
+        self.len().absorb_in_ro(ro);
+        for element in self {
+            element.absorb_in_ro(ro);
+        }
+    }
+}
+
+impl<C: CurveAffine, A: AbsorbInRO<C>, B: AbsorbInRO<C>> AbsorbInRO<C> for (A, B) {
+    fn absorb_in_ro<R: ROTrait<C>>(&self, ro: &mut R) {
+        self.0.absorb_in_ro(ro);
+        self.1.absorb_in_ro(ro);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::{prime::PrimeCurveAffine, Curve},
+    };
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec};
+
+    type Hasher = PoseidonHash<G1Affine, Fr, 3, 2>;
+
+    fn hasher() -> Hasher {
+        PoseidonHash::new(PrecomputedSpec::new(8, 56))
+    }
+
+    fn digest(value: &impl AbsorbInRO<G1Affine>) -> Fr {
+        let mut ro = hasher();
+        value.absorb_in_ro(&mut ro);
+        ro.squeeze()
+    }
+
+    #[test]
+    fn test_matches_hasher() {
+        let point = (G1Affine::generator() * Fr::from(3)).to_affine();
+        let value = (
+            vec![Fr::from(5), Fr::from(6)],
+            [point, G1Affine::identity()],
+        );
+
+        let mut expected = hasher();
+        expected.update(&[Fr::from(2), Fr::from(5), Fr::from(6)]);
+        expected.absorb_point(&point);
+        expected.absorb_point(&G1Affine::identity());
+        assert_eq!(digest(&value), expected.squeeze());
+    }
+
+    #[test]
+    fn test_length_prefix() {
+        let split = (vec![Fr::from(1)], vec![Fr::from(2), Fr::from(3)]);
+        let other = (vec![Fr::from(1), Fr::from(2)], vec![Fr::from(3)]);
+        assert_ne!(digest(&split), digest(&other));
+        assert_eq!(digest(&7u8), digest(&Fr::from(7)));
+
+        let mut expected = hasher();
+        expected.absorb_u128(u128::MAX);
+        assert_eq!(digest(&u128::MAX), expected.squeeze());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive() {
+        #[derive(Absorb)]
+        struct Instance<C: CurveAffine> {
+            commitment: C,
+            u: C::Scalar,
+            steps: u64,
+        }
+
+        #[derive(Absorb)]
+        #[absorb(domain = 9)]
+        struct Tagged(Fr, u64);
+
+        let point = (G1Affine::generator() * Fr::from(3)).to_affine();
+        let instance = Instance {
+            commitment: point,
+            u: Fr::from(4),
+            steps: 5,
+        };
+        let mut expected = hasher();
+        expected.update(&[Fr::from(3)]);
+        expected.absorb_point(&point);
+        expected.update(&[Fr::from(4), Fr::from(5)]);
+        assert_eq!(digest(&instance), expected.squeeze());
+
+        assert_eq!(
+            digest(&Tagged(Fr::from(4), 5)),
+            digest(&(9u64, (2u64, (Fr::from(4), 5u64))))
+        );
+    }
+}
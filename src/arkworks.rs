@@ -0,0 +1,193 @@
+//! arkworks' sponge API on top of [`PoseidonHash`].
+//!
+//! [`ArkworksSponge`] lets Fiat-Shamir code written against
+//! `ark_crypto_primitives::sponge` run with this crate's parameters. Absorbed
+//! values are first turned into elements of the arkworks field `A` by their
+//! `Absorb` implementation and then converted to `F` through their canonical
+//! bytes, so `A` and `F` must be the same field, e.g. `ark_bn254::Fr` and
+//! `halo2curves::bn256::Fr`. Squeezed bits are the low `F::CAPACITY` bits of
+//! each squeezed element and squeezed bytes are its low
+//! [`PoseidonHash::BYTES_PER_ELEMENT`] bytes, both in little-endian order.
+use std::marker::PhantomData;
+
+use ark_crypto_primitives::sponge::{
+    Absorb, CryptographicSponge, FieldBasedCryptographicSponge, FieldElementSize,
+};
+use ark_ff::{BigInteger, PrimeField as ArkPrimeField};
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+#[derive(Clone, Debug)]
+pub struct ArkworksSponge<C, F, A, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    A: ArkPrimeField,
+{
+    hasher: PoseidonHash<C, F, T, RATE>,
+    _marker: PhantomData<A>,
+}
+
+impl<C, F, A, const T: usize, const RATE: usize> ArkworksSponge<C, F, A, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    A: ArkPrimeField,
+{
+    fn squeeze_element(&mut self) -> F {
+        self.hasher.squeeze()
+    }
+
+    /// Low `F::CAPACITY` bits of successive squeezed elements
+    fn squeeze_low_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        let mut bits = Vec::with_capacity(num_bits);
+        while bits.len() < num_bits {
+            let repr = self.squeeze_element().to_repr();
+            let element_bits = repr
+                .as_ref()
+                .iter()
+                .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+                .take(F::CAPACITY as usize);
+            bits.extend(element_bits.take(num_bits - bits.len()));
+        }
+        bits
+    }
+}
+
+/// # Panics
+///
+/// If `value` is not a canonical element of `F`
+fn to_native<A: ArkPrimeField, F: PrimeField>(value: &A) -> F {
+    encoding::from_be_bytes(&value.into_bigint().to_bytes_be())
+        .expect("arkworks field differs from the sponge field")
+}
+
+fn from_native<F: PrimeField, A: ArkPrimeField>(value: &F) -> A {
+    A::from_be_bytes_mod_order(&encoding::to_be_bytes(value))
+}
+
+impl<C, F, A, const T: usize, const RATE: usize> CryptographicSponge
+    for ArkworksSponge<C, F, A, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    A: ArkPrimeField,
+{
+    type Config = PrecomputedSpec<F, T, RATE>;
+
+    fn new(spec: &Self::Config) -> Self {
+        Self {
+            hasher: PoseidonHash::new(spec.clone()),
+            _marker: PhantomData,
+        }
+    }
+
+    fn absorb(&mut self, input: &impl Absorb) {
+        let mut elements = Vec::<A>::new();
+        input.to_sponge_field_elements(&mut elements);
+        let elements = elements.iter().map(to_native).collect::<Vec<F>>();
+        self.hasher.update(&elements);
+    }
+
+    fn squeeze_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
+        let per_element = PoseidonHash::<C, F, T, RATE>::BYTES_PER_ELEMENT;
+        let mut bytes = Vec::with_capacity(num_bytes);
+        while bytes.len() < num_bytes {
+            let repr = self.squeeze_element().to_repr();
+            let take = per_element.min(num_bytes - bytes.len());
+            bytes.extend_from_slice(&repr.as_ref()[..take]);
+        }
+        bytes
+    }
+
+    fn squeeze_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        self.squeeze_low_bits(num_bits)
+    }
+
+    fn squeeze_field_elements<G: ArkPrimeField>(&mut self, num_elements: usize) -> Vec<G> {
+        (0..num_elements)
+            .map(|_| from_native(&self.squeeze_element()))
+            .collect()
+    }
+}
+
+impl<C, F, A, const T: usize, const RATE: usize> FieldBasedCryptographicSponge<A>
+    for ArkworksSponge<C, F, A, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    A: ArkPrimeField,
+{
+    fn squeeze_native_field_elements(&mut self, num_elements: usize) -> Vec<A> {
+        self.squeeze_field_elements(num_elements)
+    }
+
+    /// Squeezes one element per size; truncated sizes keep its low bits
+    fn squeeze_native_field_elements_with_sizes(&mut self, sizes: &[FieldElementSize]) -> Vec<A> {
+        sizes
+            .iter()
+            .map(|size| match size {
+                FieldElementSize::Full => from_native(&self.squeeze_element()),
+                FieldElementSize::Truncated(num_bits) => {
+                    let bits = self.squeeze_low_bits(*num_bits);
+                    A::from_bigint(A::BigInt::from_bits_le(&bits)).unwrap()
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+
+    const T: usize = 4;
+    const RATE: usize = 3;
+    const R_F: usize = 8;
+    const R_P: usize = 56;
+
+    type Sponge = ArkworksSponge<G1Affine, Fr, ark_bn254::Fr, T, RATE>;
+
+    fn spec() -> PrecomputedSpec<Fr, T, RATE> {
+        PrecomputedSpec::new(R_F, R_P)
+    }
+
+    #[test]
+    fn test_matches_native_vector() {
+        let mut sponge = Sponge::new(&spec());
+        let inputs = (0..5u64).map(ark_bn254::Fr::from).collect::<Vec<_>>();
+        sponge.absorb(&inputs);
+        let output: Vec<ark_bn254::Fr> = sponge.squeeze_native_field_elements(1);
+        assert_eq!(
+            encoding::to_hex(&to_native::<_, Fr>(&output[0])),
+            "0x2ce4016298e9e5fcaa94ccb686413e16add1bb813def8a3a0628aed46ea07749"
+        );
+    }
+
+    #[test]
+    fn test_squeeze_bits_and_bytes() {
+        let mut sponge = Sponge::new(&spec());
+        sponge.absorb(&ark_bn254::Fr::from(7u64));
+        let mut native = PoseidonHash::<G1Affine, Fr, T, RATE>::new(spec());
+        native.update(&[Fr::from(7)]);
+
+        let first = native.squeeze().to_repr();
+        let second = native.squeeze().to_repr();
+        let bytes = sponge.clone().squeeze_bytes(40);
+        assert_eq!(&bytes[..31], &first.as_ref()[..31]);
+        assert_eq!(&bytes[31..], &second.as_ref()[..9]);
+
+        let bits = sponge.squeeze_bits(8);
+        let low_byte = bits
+            .iter()
+            .rev()
+            .fold(0u8, |acc, bit| (acc << 1) | *bit as u8);
+        assert_eq!(low_byte, first.as_ref()[0]);
+    }
+}
@@ -0,0 +1,312 @@
+//! Audit reports of generated parameters, for signing off on a configuration.
+//!
+//! An [`Audit`] collects what a security review of a spec checks: the field
+//! and the Grain LFSR seed the constants were generated from, the chosen round
+//! numbers next to those of [`round_numbers`], the margin of every attack of
+//! [`Margins`] and the MDS checks of [`MdsCheck`]. It prints as a text report
+//! and, with the `serde` feature, serializes to JSON; `poseidon params
+//! --audit` writes either alongside the spec.
+//!
+//! The MDS checks cover the MDS property of the full-round matrix, every
+//! square submatrix being invertible, and the invertibility of the matrices
+//! the optimized permutation uses instead of it. Subspace trails of the
+//! partial rounds are not checked.
+use std::fmt;
+
+use halo2curves::group::ff::{Field, PrimeField};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+pub use crate::grain::seed;
+use crate::{
+    encoding,
+    precomputed::PrecomputedSpec,
+    rounds::{round_numbers, Margins},
+    spec::PoseidonSpec,
+};
+
+/// Determinant by Gaussian elimination
+pub(crate) fn determinant<F: Field>(mut matrix: Vec<Vec<F>>) -> F {
+    let n = matrix.len();
+    let mut det = F::ONE;
+    for col in 0..n {
+        let pivot = match (col..n).find(|row| matrix[*row][col] != F::ZERO) {
+            Some(pivot) => pivot,
+            None => return F::ZERO,
+        };
+        if pivot != col {
+            matrix.swap(pivot, col);
+            det = -det;
+        }
+        det *= matrix[col][col];
+        let inv = matrix[col][col].invert().unwrap();
+        let pivot_row = matrix[col].clone();
+        for row in matrix.iter_mut().skip(col + 1) {
+            let factor = row[col] * inv;
+            for (entry, pivot) in row.iter_mut().zip(&pivot_row).skip(col) {
+                *entry -= factor * pivot;
+            }
+        }
+    }
+    det
+}
+
+/// Results of the MDS checks of a spec
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MdsCheck {
+    /// Square submatrices of the MDS matrix checked, `C(2T, T) - 1`
+    pub minors: usize,
+    /// Those of them that are singular; the matrix is MDS if there are none
+    pub singular_minors: usize,
+    /// Whether the matrix replacing the MDS in the last first-half full round
+    /// is invertible
+    pub pre_sparse_invertible: bool,
+    /// Partial rounds whose sparse matrix is singular
+    pub singular_sparse: Vec<usize>,
+}
+
+impl MdsCheck {
+    pub fn new<F: PrimeField, const T: usize, const RATE: usize>(
+        spec: &PrecomputedSpec<F, T, RATE>,
+    ) -> Self {
+        let mds = spec.mds();
+        let (mut minors, mut singular_minors) = (0, 0);
+        for rows in 1..1usize << T {
+            for cols in (1..1usize << T).filter(|cols| cols.count_ones() == rows.count_ones()) {
+                let submatrix = (0..T)
+                    .filter(|i| (rows >> i) & 1 == 1)
+                    .map(|i| {
+                        (0..T)
+                            .filter(|j| (cols >> j) & 1 == 1)
+                            .map(|j| mds[i][j])
+                            .collect()
+                    })
+                    .collect();
+                minors += 1;
+                if determinant(submatrix) == F::ZERO {
+                    singular_minors += 1;
+                }
+            }
+        }
+
+        let pre_sparse = spec
+            .pre_sparse_mds()
+            .iter()
+            .map(|row| row.to_vec())
+            .collect();
+        let singular_sparse = (0..spec.partial_rounds())
+            .filter(|round| {
+                let (row, col_hat) = spec.sparse_mds(*round);
+                // [row; col_hat | I]
+                let matrix = (0..T)
+                    .map(|i| match i {
+                        0 => row.to_vec(),
+                        i => (0..T)
+                            .map(|j| match j {
+                                0 => col_hat[i - 1],
+                                j if j == i => F::ONE,
+                                _ => F::ZERO,
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                determinant(matrix) == F::ZERO
+            })
+            .collect();
+        Self {
+            minors,
+            singular_minors,
+            pre_sparse_invertible: determinant(pre_sparse) != F::ZERO,
+            singular_sparse,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.singular_minors == 0 && self.pre_sparse_invertible && self.singular_sparse.is_empty()
+    }
+}
+
+/// Audit report of a spec at a security level
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Audit {
+    /// Modulus of the field, as halo2curves prints it
+    pub modulus: String,
+    pub field_bits: u32,
+    pub t: usize,
+    pub rate: usize,
+    /// Grain LFSR seed, as 20 hex digits
+    pub seed: String,
+    pub r_f: usize,
+    pub r_p: usize,
+    pub security: u32,
+    /// Round numbers [`round_numbers`] picks for this width and security
+    pub recommended_r_f: usize,
+    pub recommended_r_p: usize,
+    /// Margins of `r_f` and `r_p` against each attack
+    pub margins: Margins,
+    pub mds: MdsCheck,
+}
+
+impl Audit {
+    pub fn new<F: PrimeField, const T: usize, const RATE: usize>(
+        spec: &PrecomputedSpec<F, T, RATE>,
+        security: u32,
+    ) -> Self {
+        let (r_f, r_p) = (spec.full_rounds(), spec.partial_rounds());
+        let (recommended_r_f, recommended_r_p) = round_numbers(F::NUM_BITS, T, security);
+        let seed = seed::<F>(T, r_f, r_p)
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, bit| acc << 1 | *bit as u8))
+            .collect::<Vec<_>>();
+        Self {
+            modulus: F::MODULUS.to_string(),
+            field_bits: F::NUM_BITS,
+            t: T,
+            rate: RATE,
+            seed: encoding::bytes_to_hex(&seed),
+            r_f,
+            r_p,
+            security,
+            recommended_r_f,
+            recommended_r_p,
+            margins: Margins::new(F::NUM_BITS, T, r_f, r_p, security),
+            mds: MdsCheck::new(spec),
+        }
+    }
+
+    /// Whether the rounds resist every attack and the matrices pass every
+    /// check
+    pub fn passes(&self) -> bool {
+        self.margins.is_secure() && self.mds.is_valid()
+    }
+}
+
+impl fmt::Display for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let check = |ok: bool| if ok { "ok" } else { "FAILED" };
+        writeln!(f, "Poseidon parameter audit")?;
+        writeln!(f, "field modulus      {}", self.modulus)?;
+        writeln!(f, "field bits         {}", self.field_bits)?;
+        writeln!(f, "width              t = {}, rate = {}", self.t, self.rate)?;
+        writeln!(f, "s-box              x^5")?;
+        writeln!(f, "grain seed         {}", self.seed)?;
+        writeln!(
+            f,
+            "rounds             r_f = {}, r_p = {} (recommended {}, {})",
+            self.r_f, self.r_p, self.recommended_r_f, self.recommended_r_p
+        )?;
+        writeln!(f, "security           {} bits", self.security)?;
+        writeln!(f)?;
+        writeln!(f, "attack margins of the chosen rounds")?;
+        let margins = &self.margins;
+        for (attack, margin, unit) in [
+            ("statistical", margins.statistical, "full rounds"),
+            ("interpolation", margins.interpolation, "partial rounds"),
+            ("groebner basis 1", margins.groebner_1, "partial rounds"),
+            ("groebner basis 2", margins.groebner_2, "partial rounds"),
+            ("groebner basis 3", margins.groebner_3, "bits"),
+        ] {
+            writeln!(
+                f,
+                "  {:<17}{:>+8.2} {:<15}{}",
+                attack,
+                margin,
+                unit,
+                check(margin >= 0.0)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "mds checks")?;
+        let mds = &self.mds;
+        writeln!(
+            f,
+            "  mds property     {} of {} minors singular  {}",
+            mds.singular_minors,
+            mds.minors,
+            check(mds.singular_minors == 0)
+        )?;
+        writeln!(f, "  pre-sparse       {}", check(mds.pre_sparse_invertible))?;
+        writeln!(
+            f,
+            "  sparse           {} singular  {}",
+            mds.singular_sparse.len(),
+            check(mds.singular_sparse.is_empty())
+        )?;
+        writeln!(f)?;
+        writeln!(f, "result             {}", check(self.passes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{bn256::Fr, pasta::Fp};
+
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        let bits = seed::<Fr>(3, 8, 57);
+        assert_eq!(bits.len(), 80);
+        // field and s-box flags, then the 254 bits of the field
+        assert_eq!(&bits[..6], &[false, true, false, false, false, false]);
+        assert_eq!(&bits[6..18], &seed::<Fr>(5, 8, 60)[6..18]);
+        assert!(bits[50..].iter().all(|bit| *bit));
+    }
+
+    #[test]
+    fn test_determinant() {
+        let matrix = |rows: [[u64; 3]; 3]| {
+            rows.iter()
+                .map(|row| row.iter().map(|v| Fr::from(*v)).collect())
+                .collect()
+        };
+        assert_eq!(
+            determinant(matrix([[2, 0, 1], [1, 3, 2], [1, 1, 2]])),
+            Fr::from(6)
+        );
+        // a row swap negates
+        assert_eq!(
+            determinant(matrix([[0, 1, 0], [1, 0, 0], [0, 0, 1]])),
+            -Fr::from(1)
+        );
+        assert_eq!(
+            determinant(matrix([[1, 2, 3], [2, 4, 6], [1, 1, 1]])),
+            Fr::ZERO
+        );
+    }
+
+    #[test]
+    fn test_audit() {
+        let audit = Audit::new(&PrecomputedSpec::<Fr, 3, 2>::new(8, 57), 128);
+        assert!(audit.passes());
+        assert_eq!((audit.t, audit.rate, audit.field_bits), (3, 2, 254));
+        assert_eq!(audit.seed.len(), 2 + 20);
+        assert_eq!((audit.recommended_r_f, audit.recommended_r_p), (8, 56));
+        // C(6, 3) - 1 square submatrices
+        assert_eq!(audit.mds.minors, 19);
+        assert!(audit.mds.is_valid());
+
+        let text = audit.to_string();
+        assert!(text.contains("rounds             r_f = 8, r_p = 57 (recommended 8, 56)"));
+        assert!(text.ends_with("result             ok\n"));
+
+        // too few partial rounds fail the interpolation attack
+        let audit = Audit::new(&PrecomputedSpec::<Fp, 5, 4>::new(8, 20), 128);
+        assert!(!audit.passes());
+        assert!(audit.mds.is_valid());
+        assert!(audit.to_string().ends_with("result             FAILED\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_audit_json() {
+        let audit = Audit::new(&PrecomputedSpec::<Fr, 4, 3>::new(8, 56), 128);
+        let json: serde_json::Value = serde_json::to_value(&audit).unwrap();
+        assert_eq!(json["r_p"], 56);
+        assert_eq!(json["margins"]["statistical"], 2.0);
+        assert_eq!(json["mds"]["singular_minors"], 0);
+        assert_eq!(json["seed"], audit.seed.as_str());
+    }
+}
@@ -0,0 +1,194 @@
+//! Interoperability with the Baby Jubjub/Poseidon stack of iden3 and
+//! Semaphore (EIP-2494).
+//!
+//! Baby Jubjub is the twisted Edwards curve `a x^2 + y^2 = 1 + d x^2 y^2`
+//! over the bn256 scalar field with `a = 168700` and `d = 168696`. Points are
+//! packed as in circomlibjs: 32 little-endian bytes of `y`, with the top bit
+//! set when `x` is greater than `(p - 1) / 2`. Hashes use
+//! [`CircomPoseidon`], so digests match circomlib circuits.
+use halo2curves::{
+    bn256::Fr,
+    group::ff::{Field, PrimeField},
+};
+
+use crate::{circom::CircomPoseidon, encoding};
+
+pub const A: u64 = 168700;
+pub const D: u64 = 168696;
+
+/// Order of the prime subgroup generated by [`base8`]
+pub const SUBORDER: &str =
+    "2736030358979909402780800718157159386076813972158567259200215660948447373041";
+
+/// Point in affine twisted Edwards coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point {
+    pub x: Fr,
+    pub y: Fr,
+}
+
+fn constant(decimal: &str) -> Fr {
+    encoding::from_decimal(decimal).unwrap()
+}
+
+/// circomlib's `Generator`, of order `8 * SUBORDER`
+pub fn generator() -> Point {
+    Point {
+        x: constant("995203441582195749578291179787384436505546430278305826713579947235728471134"),
+        y: constant("5472060717959818805561601436314318772137091100104008585924551046643952123905"),
+    }
+}
+
+/// circomlib's `Base8 = 8 * Generator`, used for public keys
+pub fn base8() -> Point {
+    Point {
+        x: constant("5299619240641551281634865583518297030282874472190772894086521144482721001553"),
+        y: constant(
+            "16950150798460657717958625567821834550301663161624707787222815936182638968203",
+        ),
+    }
+}
+
+impl Point {
+    pub fn identity() -> Self {
+        Self {
+            x: Fr::ZERO,
+            y: Fr::ONE,
+        }
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        let (x2, y2) = (self.x.square(), self.y.square());
+        Fr::from(A) * x2 + y2 == Fr::ONE + Fr::from(D) * x2 * y2
+    }
+
+    /// Complete twisted Edwards addition
+    pub fn add(&self, other: &Self) -> Self {
+        let t = Fr::from(D) * self.x * other.x * self.y * other.y;
+        Self {
+            x: (self.x * other.y + self.y * other.x) * (Fr::ONE + t).invert().unwrap(),
+            y: (self.y * other.y - Fr::from(A) * self.x * other.x)
+                * (Fr::ONE - t).invert().unwrap(),
+        }
+    }
+
+    /// `scalar * self`, where `scalar` is read as an integer below the field
+    /// modulus
+    pub fn mul_scalar(&self, scalar: &Fr) -> Self {
+        let repr = scalar.to_repr();
+        repr.as_ref()
+            .iter()
+            .rev()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+            .fold(Self::identity(), |acc, bit| {
+                let acc = acc.add(&acc);
+                if bit {
+                    acc.add(self)
+                } else {
+                    acc
+                }
+            })
+    }
+
+    /// circomlibjs `packPoint`
+    pub fn compress(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(self.y.to_repr().as_ref());
+        if is_negative(&self.x) {
+            bytes[31] |= 0x80;
+        }
+        bytes
+    }
+
+    /// circomlibjs `unpackPoint`; returns `None` if `y` is not canonical or
+    /// no point has this `y`
+    pub fn decompress(bytes: &[u8; 32]) -> Option<Self> {
+        let negative = bytes[31] & 0x80 != 0;
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut().copy_from_slice(bytes);
+        repr.as_mut()[31] &= 0x7f;
+        let y = Option::<Fr>::from(Fr::from_repr(repr))?;
+
+        let y2 = y.square();
+        let x2 = (Fr::ONE - y2) * Option::<Fr>::from((Fr::from(A) - Fr::from(D) * y2).invert())?;
+        let x = Option::<Fr>::from(x2.sqrt())?;
+        let x = if is_negative(&x) == negative { x } else { -x };
+        Some(Self { x, y })
+    }
+}
+
+/// Whether `value` is greater than `(p - 1) / 2`
+fn is_negative(value: &Fr) -> bool {
+    let half = -Fr::ONE * Fr::from(2).invert().unwrap();
+    encoding::to_be_bytes(value) > encoding::to_be_bytes(&half)
+}
+
+/// Public key of `secret`: `secret * Base8`
+pub fn public_key(secret: &Fr) -> Point {
+    base8().mul_scalar(secret)
+}
+
+/// Semaphore's identity commitment, `Poseidon(pk.x, pk.y)`
+pub fn identity_commitment(public_key: &Point) -> Fr {
+    CircomPoseidon::<Fr, 3, 2>::new().hash(&[public_key.x, public_key.y])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_curve_constants() {
+        assert!(generator().is_on_curve());
+        assert!(base8().is_on_curve());
+        assert_eq!(generator().mul_scalar(&Fr::from(8)), base8());
+        assert_eq!(base8().mul_scalar(&constant(SUBORDER)), Point::identity());
+    }
+
+    // computed with an independent Python model of the curve and of
+    // circomlibjs' packing
+    #[test]
+    fn test_vectors() {
+        let public_key = public_key(&Fr::from(12345));
+        assert_eq!(
+            public_key,
+            Point {
+                x: constant(
+                    "19099552327547260981542886231210125691902505931204088720746463491300185142606"
+                ),
+                y: constant(
+                    "13276557205153692030187527501273228448057533426731746626187331221465573305487"
+                ),
+            }
+        );
+        assert_eq!(
+            hex(&public_key.compress()),
+            "8f2cc7d0d267c587c57178e44c2137484dd3a492cc21e5cc9304fe73dc435a9d"
+        );
+        assert_eq!(
+            hex(&base8().compress()),
+            "8b7d2d877a253c4b7733e1b91f05e0fcedf96bd11c2e572549b2a0f703727925"
+        );
+        assert_eq!(
+            encoding::to_decimal(&identity_commitment(&public_key)),
+            "17008644521684354376829226507058000166858428675089116546351357073338598635844"
+        );
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        for point in [
+            base8(),
+            generator(),
+            public_key(&Fr::from(7)),
+            Point::identity(),
+        ] {
+            assert_eq!(Point::decompress(&point.compress()), Some(point));
+        }
+        assert_eq!(Point::decompress(&[0xff; 32]), None);
+    }
+}
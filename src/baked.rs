@@ -0,0 +1,58 @@
+//! Specs generated at build time.
+//!
+//! With the `baked-specs` feature, the build script generates the specs
+//! below with the Grain LFSR and constant folding of this crate, whose
+//! modules it includes, and embeds them in the binary as static tables in
+//! the format of [`PrecomputedSpec::to_bytes`]. [`spec`] decodes one, which
+//! only converts its elements from bytes: no Grain LFSR or matrix inversion
+//! runs in the final binary, which suits embedded and WASM targets.
+//!
+//! | field           | `T` | `RATE` | `r_f` | `r_p` |
+//! |-----------------|-----|--------|-------|-------|
+//! | bn256 scalar    | 3   | 2      | 8     | 56    |
+//! | bn256 scalar    | 4   | 3      | 8     | 56    |
+//! | pasta `Fp`      | 3   | 2      | 8     | 56    |
+//! | pasta `Fq`      | 3   | 2      | 8     | 56    |
+//!
+//! A baked spec is a [`PrecomputedSpec`] like any other, for the native
+//! sponge and the circuit chip alike.
+use halo2curves::group::ff::PrimeField;
+
+use crate::precomputed::PrecomputedSpec;
+
+static TABLES: &[&[u8]] = include!(concat!(env!("OUT_DIR"), "/baked_specs.rs"));
+
+/// The baked spec of the field `F` with these parameters, `None` if it was
+/// not generated at build time
+pub fn spec<F: PrimeField, const T: usize, const RATE: usize>(
+    r_f: usize,
+    r_p: usize,
+) -> Option<PrecomputedSpec<F, T, RATE>> {
+    TABLES.iter().find_map(|table| {
+        PrecomputedSpec::from_bytes(table).filter(|spec| (spec.r_f(), spec.r_p()) == (r_f, r_p))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::Fr,
+        pasta::{Fp, Fq},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_matches_generated() {
+        assert_eq!(spec::<Fr, 3, 2>(8, 56), Some(PrecomputedSpec::new(8, 56)));
+        assert_eq!(spec::<Fr, 4, 3>(8, 56), Some(PrecomputedSpec::new(8, 56)));
+        assert_eq!(spec::<Fp, 3, 2>(8, 56), Some(PrecomputedSpec::new(8, 56)));
+        assert_eq!(spec::<Fq, 3, 2>(8, 56), Some(PrecomputedSpec::new(8, 56)));
+    }
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(spec::<Fr, 3, 2>(8, 57), None);
+        assert_eq!(spec::<Fr, 5, 4>(8, 56), None);
+    }
+}
@@ -0,0 +1,347 @@
+//! Command-line access to the native hash for generating fixtures and
+//! debugging mismatches.
+//!
+//! ```text
+//! poseidon hash --curve bn256 --t 3 0x01 2
+//! poseidon merkle root leaves.json
+//! poseidon params --t 5 --security 128 --out spec.json --audit audit.txt
+//! poseidon kat --out kat
+//! poseidon cost --absorbs 12 --point-absorbs 4 --squeezes 2 --merkle-depth 20
+//! ```
+//!
+//! Field elements are read as `0x`-prefixed hex or decimal and printed as
+//! hex, using the encodings of [`poseidon_circuit::encoding`].
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::{
+    bn256::{Fr, G1Affine},
+    group::ff::{FromUniformBytes, PrimeField},
+    pasta::{EpAffine, EqAffine, Fp, Fq},
+};
+use poseidon_circuit::{
+    audit::Audit,
+    cost::{self, Protocol},
+    encoding, kat,
+    merkle::PoseidonMerkleTree,
+    poseidon_hash::PoseidonHash,
+    precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+    rounds::{round_numbers, validate_security},
+};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(name = "poseidon", about = "Poseidon hashing over halo2curves fields")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Hashes field elements with the sponge
+    Hash {
+        #[command(flatten)]
+        params: Params,
+        inputs: Vec<String>,
+    },
+    /// Merkle tree commands; parents are `H(left, right)` with `t = 3`
+    Merkle {
+        #[command(subcommand)]
+        command: MerkleCommand,
+    },
+    /// Prints the round numbers, MDS matrix and round constants of a spec
+    Params {
+        #[command(flatten)]
+        params: Params,
+        /// Writes JSON to this file instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Also writes an audit report of the spec to this file, as JSON if
+        /// its extension is `.json` and as text otherwise
+        #[arg(long)]
+        audit: Option<PathBuf>,
+    },
+    /// Writes the known-answer vectors of every curve to `<out>/v<version>/`
+    Kat {
+        #[arg(long, default_value = "kat")]
+        out: PathBuf,
+    },
+    /// Estimates permutations, chip rows and native time of a protocol's
+    /// hashing at every width
+    Cost {
+        #[arg(long, value_enum, default_value_t = Curve::Bn256)]
+        curve: Curve,
+        #[arg(long, default_value_t = 128)]
+        security: u32,
+        /// Field elements absorbed into the transcript
+        #[arg(long, default_value_t = 0)]
+        absorbs: usize,
+        /// Curve points absorbed into the transcript, three elements each
+        #[arg(long, default_value_t = 0)]
+        point_absorbs: usize,
+        /// Challenges squeezed from the transcript
+        #[arg(long, default_value_t = 1)]
+        squeezes: usize,
+        /// Merkle levels verified, summed over all paths
+        #[arg(long, default_value_t = 0)]
+        merkle_depth: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum MerkleCommand {
+    /// Root of the tree over a JSON array of leaves, padded with zero leaves
+    /// to a power of two
+    Root {
+        leaves: PathBuf,
+        #[arg(long, value_enum, default_value_t = Curve::Bn256)]
+        curve: Curve,
+        #[arg(long, default_value_t = 128)]
+        security: u32,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Curve {
+    /// bn256 scalar field
+    Bn256,
+    /// Pallas scalar field
+    Pallas,
+    /// Vesta scalar field
+    Vesta,
+}
+
+#[derive(clap::Args)]
+struct Params {
+    #[arg(long, value_enum, default_value_t = Curve::Bn256)]
+    curve: Curve,
+    /// State width, from 2 to 8
+    #[arg(long, default_value_t = 3)]
+    t: usize,
+    /// Security level used to pick the round numbers
+    #[arg(long, default_value_t = 128)]
+    security: u32,
+    /// Overrides the number of full rounds
+    #[arg(long)]
+    r_f: Option<usize>,
+    /// Overrides the number of partial rounds
+    #[arg(long)]
+    r_p: Option<usize>,
+    /// Accepts overrides that do not reach the security level
+    #[arg(long)]
+    allow_insecure: bool,
+}
+
+#[derive(Serialize)]
+struct SpecJson {
+    t: usize,
+    r_f: usize,
+    r_p: usize,
+    mds: Vec<Vec<String>>,
+    start_constants: Vec<Vec<String>>,
+    partial_constants: Vec<String>,
+    end_constants: Vec<Vec<String>>,
+}
+
+type Result<T> = std::result::Result<T, String>;
+
+fn parse<F: PrimeField>(value: &str) -> Result<F> {
+    let parsed = if value.starts_with("0x") {
+        encoding::from_hex(value)
+    } else {
+        encoding::from_decimal(value)
+    };
+    parsed.ok_or_else(|| format!("invalid field element: {}", value))
+}
+
+fn rounds<F: PrimeField>(params: &Params) -> Result<(usize, usize)> {
+    let (r_f, r_p) = round_numbers(F::NUM_BITS, params.t, params.security);
+    let (r_f, r_p) = (params.r_f.unwrap_or(r_f), params.r_p.unwrap_or(r_p));
+    if !params.allow_insecure {
+        validate_security(
+            params.t,
+            params.t - 1,
+            r_f,
+            r_p,
+            F::NUM_BITS,
+            params.security,
+        )
+        .map_err(|err| format!("{} (--allow-insecure to proceed)", err))?;
+    }
+    Ok((r_f, r_p))
+}
+
+fn hash<C, F, const T: usize, const RATE: usize>(
+    params: &Params,
+    inputs: &[String],
+) -> Result<String>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let (r_f, r_p) = rounds::<F>(params)?;
+    let inputs = inputs
+        .iter()
+        .map(|input| parse(input))
+        .collect::<Result<Vec<F>>>()?;
+    let mut hasher = PoseidonHash::<C, F, T, RATE>::new(PrecomputedSpec::new(r_f, r_p));
+    hasher.update(&inputs);
+    Ok(encoding::to_hex(&hasher.squeeze()))
+}
+
+// `C` only keeps the signature uniform for `dispatch!`
+#[allow(clippy::extra_unused_type_parameters)]
+fn params<C, F, const T: usize, const RATE: usize>(params: &Params) -> Result<String>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let (r_f, r_p) = rounds::<F>(params)?;
+    let spec = PrecomputedSpec::<F, T, RATE>::new(r_f, r_p);
+    let row = |row: &[F]| row.iter().map(encoding::to_hex).collect::<Vec<_>>();
+    let json = SpecJson {
+        t: T,
+        r_f,
+        r_p,
+        mds: spec.mds().iter().map(|r| row(r)).collect(),
+        start_constants: spec.start_constants().iter().map(|r| row(r)).collect(),
+        partial_constants: row(spec.partial_constants()),
+        end_constants: spec.end_constants().iter().map(|r| row(r)).collect(),
+    };
+    serde_json::to_string_pretty(&json).map_err(|err| err.to_string())
+}
+
+#[allow(clippy::extra_unused_type_parameters)]
+fn audit<C, F, const T: usize, const RATE: usize>(params: &Params, json: bool) -> Result<String>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let (r_f, r_p) = rounds::<F>(params)?;
+    let audit = Audit::new(
+        &PrecomputedSpec::<F, T, RATE>::new(r_f, r_p),
+        params.security,
+    );
+    if json {
+        serde_json::to_string_pretty(&audit).map_err(|err| err.to_string())
+    } else {
+        Ok(audit.to_string())
+    }
+}
+
+fn merkle_root<F>(leaves: &[String], security: u32) -> Result<String>
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let (r_f, r_p) = round_numbers(F::NUM_BITS, 3, security);
+    let leaves = leaves
+        .iter()
+        .map(|leaf| parse(leaf))
+        .collect::<Result<Vec<F>>>()?;
+    let tree = PoseidonMerkleTree::<F>::new(PrecomputedSpec::new(r_f, r_p), leaves);
+    Ok(encoding::to_hex(&tree.root()))
+}
+
+/// Instantiates `$f::<$c, $fld, T, T - 1>` for the width of `$params`
+macro_rules! widths {
+    ($f:ident, $c:ty, $fld:ty, $params:expr $(, $arg:expr)*) => {
+        match $params.t {
+            2 => $f::<$c, $fld, 2, 1>($params $(, $arg)*),
+            3 => $f::<$c, $fld, 3, 2>($params $(, $arg)*),
+            4 => $f::<$c, $fld, 4, 3>($params $(, $arg)*),
+            5 => $f::<$c, $fld, 5, 4>($params $(, $arg)*),
+            6 => $f::<$c, $fld, 6, 5>($params $(, $arg)*),
+            7 => $f::<$c, $fld, 7, 6>($params $(, $arg)*),
+            8 => $f::<$c, $fld, 8, 7>($params $(, $arg)*),
+            t => Err(format!("unsupported width t = {}", t)),
+        }
+    };
+}
+
+/// Instantiates `$f` for the curve and width of `$params`
+macro_rules! dispatch {
+    ($f:ident, $params:expr $(, $arg:expr)*) => {
+        match $params.curve {
+            Curve::Bn256 => widths!($f, G1Affine, Fr, $params $(, $arg)*),
+            Curve::Pallas => widths!($f, EpAffine, Fq, $params $(, $arg)*),
+            Curve::Vesta => widths!($f, EqAffine, Fp, $params $(, $arg)*),
+        }
+    };
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Hash { params, inputs } => {
+            println!("{}", dispatch!(hash, &params, &inputs)?);
+        }
+        Command::Merkle {
+            command:
+                MerkleCommand::Root {
+                    leaves,
+                    curve,
+                    security,
+                },
+        } => {
+            let json = fs::read_to_string(&leaves).map_err(|err| err.to_string())?;
+            let leaves: Vec<String> = serde_json::from_str(&json).map_err(|err| err.to_string())?;
+            let root = match curve {
+                Curve::Bn256 => merkle_root::<Fr>(&leaves, security),
+                Curve::Pallas => merkle_root::<Fq>(&leaves, security),
+                Curve::Vesta => merkle_root::<Fp>(&leaves, security),
+            }?;
+            println!("{}", root);
+        }
+        Command::Params {
+            params: p,
+            out,
+            audit: audit_path,
+        } => {
+            let json = dispatch!(params, &p)?;
+            match out {
+                Some(path) => fs::write(path, json).map_err(|err| err.to_string())?,
+                None => println!("{}", json),
+            }
+            if let Some(path) = audit_path {
+                let as_json = path.extension().is_some_and(|ext| ext == "json");
+                let report = dispatch!(audit, &p, as_json)?;
+                fs::write(path, report).map_err(|err| err.to_string())?;
+            }
+        }
+        Command::Kat { out } => kat::write_all(&out).map_err(|err| err.to_string())?,
+        Command::Cost {
+            curve,
+            security,
+            absorbs,
+            point_absorbs,
+            squeezes,
+            merkle_depth,
+        } => {
+            let protocol = Protocol {
+                absorbs,
+                point_absorbs,
+                squeezes,
+                merkle_depth,
+            };
+            let costs = match curve {
+                Curve::Bn256 => cost::report::<Fr>(&protocol, security),
+                Curve::Pallas => cost::report::<Fq>(&protocol, security),
+                Curve::Vesta => cost::report::<Fp>(&protocol, security),
+            };
+            print!("{}", cost::table(&costs));
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
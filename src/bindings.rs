@@ -0,0 +1,167 @@
+//! Foreign-language bindings.
+//!
+//! All bindings share one preset, the bn256 scalar field with `T = 4`,
+//! `RATE = 3`, `R_F = 8` and `R_P = 56` as in [`crate::test_circuit`], so
+//! digests computed from other languages match the ones the circuit proves.
+//! Field elements cross the boundary as 32 big-endian bytes.
+#![allow(dead_code)]
+use halo2curves::bn256::{Fr, G1Affine};
+
+use crate::{
+    cached::CachedSpec,
+    encoding,
+    merkle::{MerkleProof, PoseidonMerkleTree},
+    poseidon_hash::PoseidonHash,
+    precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+};
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "uniffi")]
+pub mod uniffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub(crate) const T: usize = 4;
+pub(crate) const RATE: usize = 3;
+pub(crate) const R_F: usize = 8;
+pub(crate) const R_P: usize = 56;
+
+pub(crate) type Hasher = PoseidonHash<G1Affine, Fr, T, RATE>;
+
+pub(crate) fn spec() -> &'static PrecomputedSpec<Fr, T, RATE> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+pub(crate) fn hasher() -> Hasher {
+    Hasher::new(spec().clone())
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Option<Fr> {
+    encoding::from_be_bytes(bytes)
+}
+
+pub(crate) fn encode(value: &Fr) -> Vec<u8> {
+    encoding::to_be_bytes(value)
+}
+
+pub(crate) fn hash(inputs: &[Fr]) -> Fr {
+    let mut hasher = hasher();
+    hasher.update(inputs);
+    hasher.squeeze()
+}
+
+/// Nullifier of `secret` within `scope`: `H(secret, scope)`. The same secret
+/// gives unlinkable nullifiers in different scopes.
+pub(crate) fn nullifier(secret: Fr, scope: Fr) -> Fr {
+    hash(&[secret, scope])
+}
+
+/// Checks a Merkle path from `leaf` to `root` in a tree of `depth`, where
+/// the `i`-th bit of `index` tells whether the node at height `i` is a right
+/// child and every parent is `H(left, right)`. A path of another depth is
+/// rejected.
+pub(crate) fn verify_merkle_proof(
+    leaf: Fr,
+    index: u64,
+    depth: usize,
+    siblings: &[Fr],
+    root: Fr,
+) -> bool {
+    let proof = MerkleProof {
+        index,
+        siblings: siblings.to_vec(),
+    };
+    proof.verify(spec(), depth, root, leaf)
+}
+
+/// Binary Merkle tree over `H(left, right)`, padded with zero leaves to a
+/// power of two
+pub(crate) struct MerkleTree(PoseidonMerkleTree<Fr, T, RATE>);
+
+impl MerkleTree {
+    pub(crate) fn new(leaves: Vec<Fr>) -> Self {
+        Self(PoseidonMerkleTree::new(spec().clone(), leaves))
+    }
+
+    pub(crate) fn root(&self) -> Fr {
+        self.0.root()
+    }
+
+    /// Siblings from the leaf up, as expected by [`verify_merkle_proof`]
+    pub(crate) fn proof(&self, index: usize) -> Option<Vec<Fr>> {
+        self.0.prove(index).map(|proof| proof.siblings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::group::ff::PrimeField;
+
+    use super::*;
+
+    #[test]
+    fn test_hash_matches_preset() {
+        let inputs = (0..5).map(Fr::from).collect::<Vec<_>>();
+        let expected = Fr::from_str_vartime(
+            "20304616028358001435806807494046171997958789835068077254356069730773893150537",
+        )
+        .unwrap();
+        assert_eq!(hash(&inputs), expected);
+        assert_eq!(decode(&encode(&expected)), Some(expected));
+    }
+
+    #[test]
+    fn test_nullifier() {
+        let secret = Fr::from(42);
+        assert_eq!(nullifier(secret, Fr::from(1)), hash(&[secret, Fr::from(1)]));
+        assert_ne!(
+            nullifier(secret, Fr::from(1)),
+            nullifier(secret, Fr::from(2))
+        );
+    }
+
+    #[test]
+    fn test_verify_merkle_proof() {
+        let leaves = (0..4).map(Fr::from).collect::<Vec<_>>();
+        let left = hash(&[leaves[0], leaves[1]]);
+        let right = hash(&[leaves[2], leaves[3]]);
+        let root = hash(&[left, right]);
+
+        assert!(verify_merkle_proof(
+            leaves[2],
+            2,
+            2,
+            &[leaves[3], left],
+            root
+        ));
+        assert!(verify_merkle_proof(
+            leaves[1],
+            1,
+            2,
+            &[leaves[0], right],
+            root
+        ));
+        assert!(!verify_merkle_proof(
+            leaves[1],
+            2,
+            2,
+            &[leaves[0], right],
+            root
+        ));
+        assert!(!verify_merkle_proof(
+            leaves[1],
+            5,
+            2,
+            &[leaves[0], right],
+            root
+        ));
+        // a node presented as a leaf of a shorter path
+        assert!(!verify_merkle_proof(left, 0, 2, &[right], root));
+    }
+}
@@ -0,0 +1,182 @@
+//! C bindings. The header is generated with
+//! `cbindgen --config cbindgen.toml --output include/poseidon_circuit.h`.
+//!
+//! Functions returning `c_int` return [`POSEIDON_OK`] on success and a
+//! negative status otherwise. Byte buffers hold consecutive 32-byte
+//! big-endian field elements.
+use std::{os::raw::c_int, slice};
+
+use halo2curves::bn256::Fr;
+
+use super::Hasher;
+use crate::ro_types::ROTrait;
+
+pub const POSEIDON_OK: c_int = 0;
+pub const POSEIDON_ERR_NULL: c_int = -1;
+pub const POSEIDON_ERR_INVALID_INPUT: c_int = -2;
+
+pub const POSEIDON_ELEMENT_BYTES: usize = 32;
+
+/// Opaque sponge handle
+pub struct PoseidonHasher(Hasher);
+
+fn elements(bytes: &[u8]) -> Option<Vec<Fr>> {
+    if bytes.len() % POSEIDON_ELEMENT_BYTES != 0 {
+        return None;
+    }
+    bytes
+        .chunks(POSEIDON_ELEMENT_BYTES)
+        .map(super::decode)
+        .collect()
+}
+
+/// # Safety
+///
+/// `ptr` must be null or valid for reads of `len` bytes
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// # Safety
+///
+/// `out` must be null or valid for writes of 32 bytes
+unsafe fn write(value: &Fr, out: *mut u8) {
+    let bytes = super::encode(value);
+    slice::from_raw_parts_mut(out, POSEIDON_ELEMENT_BYTES).copy_from_slice(&bytes);
+}
+
+/// Allocates a sponge; release it with [`poseidon_hasher_free`]
+#[no_mangle]
+pub extern "C" fn poseidon_hasher_new() -> *mut PoseidonHasher {
+    Box::into_raw(Box::new(PoseidonHasher(super::hasher())))
+}
+
+/// # Safety
+///
+/// `hasher` must be null or returned by [`poseidon_hasher_new`] and not yet
+/// freed
+#[no_mangle]
+pub unsafe extern "C" fn poseidon_hasher_free(hasher: *mut PoseidonHasher) {
+    if !hasher.is_null() {
+        drop(Box::from_raw(hasher));
+    }
+}
+
+/// Absorbs `len / 32` field elements from `bytes`
+///
+/// # Safety
+///
+/// `hasher` must be a live handle and `bytes` valid for reads of `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn poseidon_hasher_absorb(
+    hasher: *mut PoseidonHasher,
+    bytes: *const u8,
+    len: usize,
+) -> c_int {
+    let Some(hasher) = hasher.as_mut() else {
+        return POSEIDON_ERR_NULL;
+    };
+    let Some(bytes) = input(bytes, len) else {
+        return POSEIDON_ERR_NULL;
+    };
+    match elements(bytes) {
+        Some(elements) => {
+            hasher.0.update(&elements);
+            POSEIDON_OK
+        }
+        None => POSEIDON_ERR_INVALID_INPUT,
+    }
+}
+
+/// Writes the next 32-byte challenge to `out`
+///
+/// # Safety
+///
+/// `hasher` must be a live handle and `out` valid for writes of 32 bytes
+#[no_mangle]
+pub unsafe extern "C" fn poseidon_hasher_squeeze(
+    hasher: *mut PoseidonHasher,
+    out: *mut u8,
+) -> c_int {
+    let Some(hasher) = hasher.as_mut() else {
+        return POSEIDON_ERR_NULL;
+    };
+    if out.is_null() {
+        return POSEIDON_ERR_NULL;
+    }
+    write(&hasher.0.squeeze(), out);
+    POSEIDON_OK
+}
+
+/// One-shot hash of `len / 32` field elements
+///
+/// # Safety
+///
+/// `bytes` must be valid for reads of `len` bytes and `out` for writes of 32
+/// bytes
+#[no_mangle]
+pub unsafe extern "C" fn poseidon_hash(bytes: *const u8, len: usize, out: *mut u8) -> c_int {
+    let Some(bytes) = input(bytes, len) else {
+        return POSEIDON_ERR_NULL;
+    };
+    if out.is_null() {
+        return POSEIDON_ERR_NULL;
+    }
+    match elements(bytes) {
+        Some(elements) => {
+            write(&super::hash(&elements), out);
+            POSEIDON_OK
+        }
+        None => POSEIDON_ERR_INVALID_INPUT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        let bytes = (0..5u64)
+            .flat_map(|i| super::super::encode(&Fr::from(i)))
+            .collect::<Vec<_>>();
+
+        let mut expected = [0u8; POSEIDON_ELEMENT_BYTES];
+        let mut actual = [0u8; POSEIDON_ELEMENT_BYTES];
+        unsafe {
+            assert_eq!(
+                poseidon_hash(bytes.as_ptr(), bytes.len(), expected.as_mut_ptr()),
+                POSEIDON_OK
+            );
+
+            let hasher = poseidon_hasher_new();
+            assert_eq!(
+                poseidon_hasher_absorb(hasher, bytes.as_ptr(), bytes.len()),
+                POSEIDON_OK
+            );
+            assert_eq!(
+                poseidon_hasher_absorb(hasher, bytes.as_ptr(), 31),
+                POSEIDON_ERR_INVALID_INPUT
+            );
+            assert_eq!(
+                poseidon_hasher_squeeze(hasher, actual.as_mut_ptr()),
+                POSEIDON_OK
+            );
+            assert_eq!(
+                poseidon_hasher_squeeze(hasher, ptr::null_mut()),
+                POSEIDON_ERR_NULL
+            );
+            poseidon_hasher_free(hasher);
+        }
+        assert_eq!(actual, expected);
+        assert_eq!(expected[0], 0x2c);
+    }
+}
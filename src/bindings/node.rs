@@ -0,0 +1,83 @@
+//! Node.js bindings, built with `napi build --platform --release --features node`
+use halo2curves::bn256::Fr;
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+
+use super::Hasher;
+use crate::ro_types::ROTrait;
+
+fn field(bytes: &[u8]) -> napi::Result<Fr> {
+    super::decode(bytes).ok_or_else(|| napi::Error::from_reason("invalid field element"))
+}
+
+fn fields(values: &[Buffer]) -> napi::Result<Vec<Fr>> {
+    values.iter().map(|value| field(value)).collect()
+}
+
+fn buffer(value: &Fr) -> Buffer {
+    super::encode(value).into()
+}
+
+/// Hashes 32-byte big-endian field elements
+#[napi]
+pub fn poseidon_hash(inputs: Vec<Buffer>) -> napi::Result<Buffer> {
+    Ok(buffer(&super::hash(&fields(&inputs)?)))
+}
+
+#[napi]
+pub fn merkle_root(leaves: Vec<Buffer>) -> napi::Result<Buffer> {
+    Ok(buffer(&super::MerkleTree::new(fields(&leaves)?).root()))
+}
+
+#[napi]
+pub fn merkle_proof(leaves: Vec<Buffer>, index: u32) -> napi::Result<Vec<Buffer>> {
+    let siblings = super::MerkleTree::new(fields(&leaves)?)
+        .proof(index as usize)
+        .ok_or_else(|| napi::Error::from_reason("leaf index out of range"))?;
+    Ok(siblings.iter().map(buffer).collect())
+}
+
+#[napi]
+pub fn verify_merkle_proof(
+    leaf: Buffer,
+    index: u32,
+    depth: u32,
+    siblings: Vec<Buffer>,
+    root: Buffer,
+) -> napi::Result<bool> {
+    Ok(super::verify_merkle_proof(
+        field(&leaf)?,
+        index.into(),
+        depth as usize,
+        &fields(&siblings)?,
+        field(&root)?,
+    ))
+}
+
+/// Fiat-Shamir transcript on top of the native sponge
+#[napi]
+pub struct Transcript {
+    hasher: Hasher,
+}
+
+#[napi]
+impl Transcript {
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            hasher: super::hasher(),
+        }
+    }
+
+    #[napi]
+    pub fn absorb(&mut self, element: Buffer) -> napi::Result<()> {
+        self.hasher.update(&[field(&element)?]);
+        Ok(())
+    }
+
+    #[napi]
+    pub fn squeeze(&mut self) -> Buffer {
+        buffer(&self.hasher.squeeze())
+    }
+}
@@ -0,0 +1,136 @@
+//! Python bindings, built with `maturin develop` (see `pyproject.toml`).
+//!
+//! Field elements are passed as 32-byte big-endian `bytes`.
+use std::borrow::Cow;
+
+use halo2curves::bn256::Fr;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use super::{Hasher, RATE, R_F, R_P, T};
+use crate::{encoding::to_hex, ro_types::ROTrait};
+
+fn field(bytes: &[u8]) -> PyResult<Fr> {
+    super::decode(bytes).ok_or_else(|| PyValueError::new_err("invalid field element"))
+}
+
+fn fields(values: &[Vec<u8>]) -> PyResult<Vec<Fr>> {
+    values.iter().map(|value| field(value)).collect()
+}
+
+fn bytes(value: &Fr) -> Cow<'static, [u8]> {
+    Cow::Owned(super::encode(value))
+}
+
+#[pyclass(name = "PoseidonHash")]
+struct PyPoseidonHash {
+    hasher: Hasher,
+}
+
+#[pymethods]
+impl PyPoseidonHash {
+    #[new]
+    fn new() -> Self {
+        Self {
+            hasher: super::hasher(),
+        }
+    }
+
+    fn update(&mut self, elements: Vec<Vec<u8>>) -> PyResult<()> {
+        self.hasher.update(&fields(&elements)?);
+        Ok(())
+    }
+
+    fn squeeze(&mut self) -> Cow<'static, [u8]> {
+        bytes(&self.hasher.squeeze())
+    }
+}
+
+#[pyclass(name = "MerkleTree")]
+struct PyMerkleTree {
+    tree: super::MerkleTree,
+}
+
+#[pymethods]
+impl PyMerkleTree {
+    #[new]
+    fn new(leaves: Vec<Vec<u8>>) -> PyResult<Self> {
+        Ok(Self {
+            tree: super::MerkleTree::new(fields(&leaves)?),
+        })
+    }
+
+    fn root(&self) -> Cow<'static, [u8]> {
+        bytes(&self.tree.root())
+    }
+
+    fn proof(&self, index: usize) -> PyResult<Vec<Cow<'static, [u8]>>> {
+        let siblings = self
+            .tree
+            .proof(index)
+            .ok_or_else(|| PyValueError::new_err("leaf index out of range"))?;
+        Ok(siblings.iter().map(bytes).collect())
+    }
+
+    #[staticmethod]
+    fn verify(
+        leaf: &[u8],
+        index: u64,
+        depth: usize,
+        siblings: Vec<Vec<u8>>,
+        root: &[u8],
+    ) -> PyResult<bool> {
+        Ok(super::verify_merkle_proof(
+            field(leaf)?,
+            index,
+            depth,
+            &fields(&siblings)?,
+            field(root)?,
+        ))
+    }
+}
+
+/// Parameters of the preset, with field elements as `0x` hex. The round
+/// constants are the optimized ones consumed by the sparse-matrix
+/// permutation, not the raw Grain output.
+#[pyclass(get_all)]
+struct Parameters {
+    t: usize,
+    rate: usize,
+    r_f: usize,
+    r_p: usize,
+    mds: Vec<Vec<String>>,
+    start_constants: Vec<Vec<String>>,
+    partial_constants: Vec<String>,
+    end_constants: Vec<Vec<String>>,
+}
+
+#[pyfunction]
+fn parameters() -> Parameters {
+    let spec = super::spec();
+    let row = |row: &[Fr]| row.iter().map(to_hex).collect::<Vec<_>>();
+    Parameters {
+        t: T,
+        rate: RATE,
+        r_f: R_F,
+        r_p: R_P,
+        mds: spec.mds().iter().map(|r| row(r)).collect(),
+        start_constants: spec.start_constants().iter().map(|r| row(r)).collect(),
+        partial_constants: row(spec.partial_constants()),
+        end_constants: spec.end_constants().iter().map(|r| row(r)).collect(),
+    }
+}
+
+#[pyfunction]
+fn poseidon_hash(inputs: Vec<Vec<u8>>) -> PyResult<Cow<'static, [u8]>> {
+    Ok(bytes(&super::hash(&fields(&inputs)?)))
+}
+
+#[pymodule]
+fn poseidon_circuit(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyPoseidonHash>()?;
+    m.add_class::<PyMerkleTree>()?;
+    m.add_class::<Parameters>()?;
+    m.add_function(wrap_pyfunction!(poseidon_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(parameters, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Swift and Kotlin bindings. Build the library with `--features uniffi`,
+//! then generate the foreign sources from it with
+//! `cargo run --features uniffi --bin uniffi-bindgen -- generate --library
+//! target/release/libposeidon_circuit.so --language swift --out-dir out`
+//! (or `--language kotlin`).
+use std::{fmt, sync::Arc};
+
+use halo2curves::bn256::Fr;
+
+#[derive(Debug, uniffi::Error)]
+pub enum PoseidonError {
+    InvalidFieldElement,
+    IndexOutOfRange,
+}
+
+impl fmt::Display for PoseidonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFieldElement => f.write_str("invalid field element"),
+            Self::IndexOutOfRange => f.write_str("leaf index out of range"),
+        }
+    }
+}
+
+impl std::error::Error for PoseidonError {}
+
+fn field(bytes: &[u8]) -> Result<Fr, PoseidonError> {
+    super::decode(bytes).ok_or(PoseidonError::InvalidFieldElement)
+}
+
+fn fields(values: &[Vec<u8>]) -> Result<Vec<Fr>, PoseidonError> {
+    values.iter().map(|value| field(value)).collect()
+}
+
+/// Hashes 32-byte big-endian field elements
+#[uniffi::export]
+pub fn poseidon_hash(inputs: Vec<Vec<u8>>) -> Result<Vec<u8>, PoseidonError> {
+    Ok(super::encode(&super::hash(&fields(&inputs)?)))
+}
+
+/// `H(secret, scope)`, see [`super::nullifier`]
+#[uniffi::export]
+pub fn derive_nullifier(secret: Vec<u8>, scope: Vec<u8>) -> Result<Vec<u8>, PoseidonError> {
+    Ok(super::encode(&super::nullifier(
+        field(&secret)?,
+        field(&scope)?,
+    )))
+}
+
+#[uniffi::export]
+pub fn verify_merkle_proof(
+    leaf: Vec<u8>,
+    index: u64,
+    depth: u32,
+    siblings: Vec<Vec<u8>>,
+    root: Vec<u8>,
+) -> Result<bool, PoseidonError> {
+    Ok(super::verify_merkle_proof(
+        field(&leaf)?,
+        index,
+        depth as usize,
+        &fields(&siblings)?,
+        field(&root)?,
+    ))
+}
+
+#[derive(uniffi::Object)]
+pub struct MerkleTree(super::MerkleTree);
+
+#[uniffi::export]
+impl MerkleTree {
+    #[uniffi::constructor]
+    pub fn new(leaves: Vec<Vec<u8>>) -> Result<Arc<Self>, PoseidonError> {
+        Ok(Arc::new(Self(super::MerkleTree::new(fields(&leaves)?))))
+    }
+
+    pub fn root(&self) -> Vec<u8> {
+        super::encode(&self.0.root())
+    }
+
+    pub fn proof(&self, index: u64) -> Result<Vec<Vec<u8>>, PoseidonError> {
+        let siblings = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.0.proof(index))
+            .ok_or(PoseidonError::IndexOutOfRange)?;
+        Ok(siblings.iter().map(super::encode).collect())
+    }
+}
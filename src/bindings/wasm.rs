@@ -0,0 +1,68 @@
+//! JavaScript bindings, built with `wasm-pack build --features wasm`
+use halo2curves::bn256::Fr;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use super::Hasher;
+use crate::ro_types::ROTrait;
+
+fn field(bytes: &[u8]) -> Result<Fr, JsError> {
+    super::decode(bytes).ok_or_else(|| JsError::new("invalid field element"))
+}
+
+fn fields(values: &Array) -> Result<Vec<Fr>, JsError> {
+    values
+        .iter()
+        .map(|value| field(&Uint8Array::new(&value).to_vec()))
+        .collect()
+}
+
+/// Hashes an array of 32-byte big-endian field elements
+#[wasm_bindgen]
+pub fn poseidon_hash(inputs: Array) -> Result<Vec<u8>, JsError> {
+    Ok(super::encode(&super::hash(&fields(&inputs)?)))
+}
+
+/// See [`super::verify_merkle_proof`]
+#[wasm_bindgen]
+pub fn verify_merkle_proof(
+    leaf: &[u8],
+    index: u32,
+    depth: u32,
+    siblings: Array,
+    root: &[u8],
+) -> Result<bool, JsError> {
+    Ok(super::verify_merkle_proof(
+        field(leaf)?,
+        index.into(),
+        depth as usize,
+        &fields(&siblings)?,
+        field(root)?,
+    ))
+}
+
+/// Fiat-Shamir transcript on top of the native sponge
+#[wasm_bindgen]
+pub struct Transcript {
+    hasher: Hasher,
+}
+
+#[wasm_bindgen]
+impl Transcript {
+    #[wasm_bindgen(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            hasher: super::hasher(),
+        }
+    }
+
+    pub fn absorb(&mut self, element: &[u8]) -> Result<(), JsError> {
+        self.hasher.update(&[field(element)?]);
+        Ok(())
+    }
+
+    pub fn squeeze(&mut self) -> Vec<u8> {
+        super::encode(&self.hasher.squeeze())
+    }
+}
@@ -0,0 +1,147 @@
+//! Process-wide specs, generated once per set of parameters.
+//!
+//! Generating the constants of a [`PrecomputedSpec`] dominates creating a
+//! short-lived hasher. [`CachedSpec::cached`] generates the spec of a field,
+//! width and pair of round numbers on first use and hands out the same
+//! `&'static` reference afterwards, from any thread:
+//!
+//! ```
+//! use halo2curves::bn256::Fr;
+//! use poseidon_circuit::{cached::CachedSpec, precomputed::PrecomputedSpec};
+//!
+//! let spec: &'static PrecomputedSpec<Fr, 3, 2> = PrecomputedSpec::cached(8, 56);
+//! assert!(std::ptr::eq(spec, PrecomputedSpec::cached(8, 56)));
+//! ```
+//!
+//! A cached spec is never freed, so the parameters should come from a small
+//! fixed set rather than from untrusted input.
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::precomputed::PrecomputedSpec;
+
+type Cache = HashMap<(TypeId, usize, usize), &'static (dyn Any + Send + Sync)>;
+
+fn cache() -> &'static Mutex<Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+pub trait CachedSpec: Sized {
+    /// The spec with `r_f` full and `r_p` partial rounds, generated on the
+    /// first call with these parameters
+    fn cached(r_f: usize, r_p: usize) -> &'static Self;
+}
+
+impl<F, const T: usize, const RATE: usize> CachedSpec for PrecomputedSpec<F, T, RATE>
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn cached(r_f: usize, r_p: usize) -> &'static Self {
+        let key = (TypeId::of::<Self>(), r_f, r_p);
+        // only the empty cell is created under the lock, so that generating
+        // one spec does not block other parameters; racing threads share
+        // the cell and exactly one of them generates its spec
+        let cell: &'static OnceLock<Self> = cache()
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| Box::leak(Box::<OnceLock<Self>>::default()))
+            .downcast_ref()
+            .unwrap();
+        cell.get_or_init(|| PrecomputedSpec::new(r_f, r_p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        pasta::Fp,
+    };
+
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, ro_types::ROTrait};
+
+    #[test]
+    fn test_cached() {
+        let spec = PrecomputedSpec::<Fr, 3, 2>::cached(8, 56);
+        assert!(std::ptr::eq(spec, PrecomputedSpec::cached(8, 56)));
+        assert!(!std::ptr::eq(spec, PrecomputedSpec::cached(8, 57)));
+        assert_eq!(PrecomputedSpec::<Fp, 3, 2>::cached(8, 56).r_f(), 8);
+
+        let inputs = [Fr::from(1), Fr::from(2)];
+        let mut cached = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+        let mut fresh = PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 56));
+        cached.update(&inputs);
+        fresh.update(&inputs);
+        assert_eq!(cached.squeeze(), fresh.squeeze());
+    }
+
+    #[test]
+    fn test_threads() {
+        let specs = std::thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| {
+                    scope.spawn(|| PrecomputedSpec::<Fr, 4, 3>::cached(8, 56) as *const _ as usize)
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(specs.iter().all(|spec| *spec == specs[0]));
+    }
+
+    /// Many threads released together race on a handful of fresh keys, each
+    /// in a different order: every key gives one spec, the generated one,
+    /// however the calls interleave
+    #[test]
+    fn test_stress() {
+        const THREADS: usize = 16;
+        const KEYS: usize = 4;
+        // round numbers no other test caches, so every key starts empty
+        let key = |i: usize| (8, 100 + i);
+        let barrier = std::sync::Barrier::new(THREADS);
+        let specs = std::thread::scope(|scope| {
+            let handles = (0..THREADS)
+                .map(|thread| {
+                    let barrier = &barrier;
+                    scope.spawn(move || {
+                        barrier.wait();
+                        (0..KEYS)
+                            .map(|i| {
+                                let (r_f, r_p) = key((i + thread) % KEYS);
+                                let spec = PrecomputedSpec::<Fp, 3, 2>::cached(r_f, r_p);
+                                (r_p, spec as *const _ as usize)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<HashSet<_>>()
+        });
+        // one pointer per key, and a different one for each key
+        assert_eq!(specs.len(), KEYS);
+        let pointers = specs.iter().map(|(_, spec)| spec).collect::<HashSet<_>>();
+        assert_eq!(pointers.len(), KEYS);
+        for i in 0..KEYS {
+            let (r_f, r_p) = key(i);
+            assert_eq!(
+                *PrecomputedSpec::<Fp, 3, 2>::cached(r_f, r_p),
+                PrecomputedSpec::new(r_f, r_p)
+            );
+        }
+    }
+}
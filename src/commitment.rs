@@ -0,0 +1,76 @@
+//! Hiding commitments to a sequence of field elements.
+//!
+//! [`commit`] is the digest of a sponge of width 3 with the round numbers of
+//! [`round_numbers`] at 128 bits, from the initial state of
+//! [`PoseidonHash`], over the label `poseidon-commitment` packed with
+//! [`pack_bytes`], `msg.len()`, the blinding factor, then `msg`. The length
+//! keeps messages that differ by trailing zeros apart, and the label keeps
+//! commitments apart from the other digests of the same sponge, such as the
+//! ones of [`kdf`](crate::kdf).
+//!
+//! The commitment hides `msg` as long as the blinding factor is uniformly
+//! random and used once, and binds it by collision resistance. A circuit
+//! opens it by absorbing the same sequence in
+//! [`PoseidonChip`](crate::poseidon_circuit::PoseidonChip).
+//!
+//! [`round_numbers`]: crate::rounds::round_numbers
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{encoding::pack_bytes, poseidon_hash::digest_128};
+
+const LABEL: &[u8] = b"poseidon-commitment";
+
+/// The commitment to `msg` with the blinding factor `blinding`
+pub fn commit<F: PrimeField + FromUniformBytes<64>>(msg: &[F], blinding: F) -> F {
+    let mut elements = pack_bytes(LABEL);
+    elements.extend([F::from(msg.len() as u64), blinding]);
+    elements.extend_from_slice(msg);
+    digest_128(&elements)
+}
+
+/// Whether `commitment` opens to `msg` with the blinding factor `blinding`
+pub fn verify_commitment<F: PrimeField + FromUniformBytes<64>>(
+    commitment: &F,
+    msg: &[F],
+    blinding: F,
+) -> bool {
+    commit(msg, blinding).ct_eq(commitment).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::ff::Field,
+    };
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait};
+
+    #[test]
+    fn test_matches_hasher() {
+        let (msg, blinding) = ([Fr::from(1), Fr::from(2)], Fr::from(99));
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 56));
+        hasher.update_bytes(LABEL);
+        hasher.update(&[Fr::from(2), blinding]);
+        hasher.update(&msg);
+        assert_eq!(commit(&msg, blinding), hasher.squeeze());
+    }
+
+    #[test]
+    fn test_opening() {
+        let (msg, blinding) = ([Fr::from(1), Fr::from(2)], Fr::from(99));
+        let commitment = commit(&msg, blinding);
+        assert!(verify_commitment(&commitment, &msg, blinding));
+        assert!(!verify_commitment(&commitment, &msg, blinding + Fr::ONE));
+        assert!(!verify_commitment(&commitment, &msg[..1], blinding));
+        assert!(!verify_commitment(
+            &commitment,
+            &[Fr::from(1), Fr::from(2), Fr::ZERO],
+            blinding
+        ));
+        // the blinding factor is not an extra message element
+        assert_ne!(commit(&[], Fr::from(1)), commit(&[Fr::from(1)], Fr::ZERO));
+    }
+}
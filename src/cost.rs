@@ -0,0 +1,208 @@
+//! Cost estimates of a protocol's hashing at every width.
+//!
+//! A [`Protocol`] counts what a protocol hashes: field elements and curve
+//! points absorbed into its transcript, the challenges squeezed from it and
+//! the depth of the Merkle paths it verifies. [`report`] turns that into
+//! permutations, [`PoseidonChip`] rows and native time for `T` from 2 to 8,
+//! with the round numbers of [`round_numbers`] at the given security, to
+//! compare widths before fixing parameters.
+//!
+//! The counts are upper bounds for the sponge: a squeeze absorbs its buffer
+//! `RATE` elements per permutation plus one permutation for the padding, so
+//! `E` elements over `S` squeezes take at most `E / RATE + S` permutations,
+//! with equality when every element is absorbed before the first squeeze.
+//! Points are absorbed like [`NovaRO::absorb_point`], as three elements, and
+//! every Merkle level hashes two elements with a fresh sponge.
+//! The native time is measured on the current machine.
+//!
+//! [`PoseidonChip`]: crate::poseidon_circuit::PoseidonChip
+//! [`NovaRO::absorb_point`]: crate::nova::NovaRO::absorb_point
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{poseidon_hash::State, precomputed::PrecomputedSpec, rounds::round_numbers};
+
+/// Permutations timed to estimate the native cost of one
+const SAMPLES: u32 = 64;
+
+/// What a protocol hashes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Protocol {
+    /// Field elements absorbed into the transcript
+    pub absorbs: usize,
+    /// Curve points absorbed into the transcript
+    pub point_absorbs: usize,
+    /// Challenges squeezed from the transcript
+    pub squeezes: usize,
+    /// Levels of the Merkle paths verified, summed over all paths
+    pub merkle_depth: usize,
+}
+
+impl Protocol {
+    /// Field elements absorbed, points included
+    pub fn elements(&self) -> usize {
+        self.absorbs + 3 * self.point_absorbs
+    }
+
+    /// Permutations at the given rate. Nothing is hashed without a squeeze.
+    pub fn permutations(&self, rate: usize) -> usize {
+        let transcript = if self.squeezes == 0 {
+            0
+        } else {
+            self.elements() / rate + self.squeezes
+        };
+        transcript + self.merkle_depth * (2 / rate + 1)
+    }
+}
+
+/// Cost of a [`Protocol`] at one width
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cost {
+    pub t: usize,
+    pub r_f: usize,
+    pub r_p: usize,
+    pub permutations: usize,
+    /// Rows of the [`PoseidonChip`](crate::poseidon_circuit::PoseidonChip)
+    /// regions, `T * (1 + r_f + r_p)` per permutation
+    pub rows: usize,
+    /// Native time of the permutations
+    pub native: Duration,
+}
+
+/// Mean time of one permutation of `spec`
+pub fn permutation_time<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+) -> Duration
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut state = State::<F, T, RATE>::new([F::ZERO; T]);
+    let start = Instant::now();
+    for _ in 0..SAMPLES {
+        state.permutation(spec, &[]);
+    }
+    start.elapsed() / SAMPLES
+}
+
+/// Cost of `protocol` at width `T` and the given security level
+pub fn cost<F, const T: usize, const RATE: usize>(protocol: &Protocol, security: u32) -> Cost
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let (r_f, r_p) = round_numbers(F::NUM_BITS, T, security);
+    let permutations = protocol.permutations(RATE);
+    let native =
+        permutation_time(&PrecomputedSpec::<F, T, RATE>::new(r_f, r_p)) * permutations as u32;
+    Cost {
+        t: T,
+        r_f,
+        r_p,
+        permutations,
+        rows: permutations * T * (1 + r_f + r_p),
+        native,
+    }
+}
+
+/// Costs of `protocol` over `F` for `T` from 2 to 8
+pub fn report<F>(protocol: &Protocol, security: u32) -> Vec<Cost>
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    vec![
+        cost::<F, 2, 1>(protocol, security),
+        cost::<F, 3, 2>(protocol, security),
+        cost::<F, 4, 3>(protocol, security),
+        cost::<F, 5, 4>(protocol, security),
+        cost::<F, 6, 5>(protocol, security),
+        cost::<F, 7, 6>(protocol, security),
+        cost::<F, 8, 7>(protocol, security),
+    ]
+}
+
+/// One line per width, with a header
+pub fn table(costs: &[Cost]) -> String {
+    let mut table = String::from("  t  r_f  r_p  permutations       rows      native\n");
+    for cost in costs {
+        writeln!(
+            table,
+            "{:>3}  {:>3}  {:>3}  {:>12}  {:>9}  {:>10.3?}",
+            cost.t, cost.r_f, cost.r_p, cost.permutations, cost.rows, cost.native
+        )
+        .unwrap();
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, ro_types::ROTrait};
+
+    #[test]
+    fn test_permutations() {
+        let protocol = Protocol {
+            absorbs: 10,
+            point_absorbs: 2,
+            squeezes: 3,
+            merkle_depth: 4,
+        };
+        assert_eq!(protocol.elements(), 16);
+        // 16 / 1 + 3 and 4 levels of 3
+        assert_eq!(protocol.permutations(1), 19 + 12);
+        // 16 / 2 + 3 and 4 levels of 2
+        assert_eq!(protocol.permutations(2), 11 + 8);
+        // 16 / 3 + 3 and 4 levels of 1
+        assert_eq!(protocol.permutations(3), 8 + 4);
+        assert_eq!(Protocol::default().permutations(2), 0);
+    }
+
+    /// The bound is exact when everything is absorbed before one squeeze
+    #[test]
+    fn test_permutations_match_sponge() {
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        for absorbs in 0..7 {
+            let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+            hasher.update(&vec![Fr::from(1); absorbs]);
+            hasher.squeeze();
+            let protocol = Protocol {
+                absorbs,
+                squeezes: 1,
+                ..Default::default()
+            };
+            let permutations = hasher.state.trace.len() / (8 + 56);
+            assert_eq!(protocol.permutations(2), permutations);
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let protocol = Protocol {
+            absorbs: 5,
+            squeezes: 1,
+            ..Default::default()
+        };
+        let costs = report::<Fr>(&protocol, 128);
+        assert_eq!(
+            costs.iter().map(|cost| cost.t).collect::<Vec<_>>(),
+            (2..=8).collect::<Vec<_>>()
+        );
+        assert_eq!((costs[1].r_f, costs[1].r_p), (8, 56));
+        assert_eq!(costs[1].permutations, 3);
+        assert_eq!(costs[1].rows, 3 * 3 * (1 + 8 + 56));
+        assert!(costs.iter().all(|cost| cost.native > Duration::ZERO));
+
+        let table = table(&costs);
+        assert_eq!(table.lines().count(), 1 + 7);
+        assert!(table
+            .lines()
+            .nth(2)
+            .unwrap()
+            .starts_with("  3    8   56             3        585"));
+    }
+}
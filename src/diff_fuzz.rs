@@ -0,0 +1,159 @@
+//! Differential checks of the native hash against the PSE `poseidon` sponge.
+//!
+//! [`PoseidonHash`] runs its own permutation over the folded constants and
+//! sparse MDS matrices of a [`PrecomputedSpec`] generated by this crate, so
+//! that the chip can mirror it round by round. `poseidon::Poseidon` generates
+//! its spec with the upstream Grain LFSR, runs the upstream permutation and
+//! pads the same way. Both must agree on every squeeze, so a divergence in
+//! either the generated constants or the sparse MDS adaptation shows up.
+//! With the `diff-fuzz` feature, [`Differential`] drives both with the same
+//! inputs and reports the first squeeze at which they disagree; the
+//! `diff_pse` fuzz target feeds it random sessions.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use std::fmt;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+use poseidon::Poseidon;
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+/// Squeeze at which the two sponges returned different outputs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch<F: PrimeField> {
+    /// Index of the squeeze in the session, from 0
+    pub squeeze: usize,
+    pub local: F,
+    pub reference: F,
+}
+
+impl<F: PrimeField> fmt::Display for Mismatch<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "squeeze {} differs: local {}, reference {}",
+            self.squeeze,
+            encoding::to_hex(&self.local),
+            encoding::to_hex(&self.reference)
+        )
+    }
+}
+
+/// [`PoseidonHash`] and `poseidon::Poseidon` absorbing the same inputs.
+/// Both sponges derive their specs from scratch, so clone a prototype rather
+/// than creating one per session.
+#[derive(Clone, Debug)]
+pub struct Differential<C, F, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    local: PoseidonHash<C, F, T, RATE>,
+    reference: Poseidon<F, T, RATE>,
+    squeezes: usize,
+}
+
+impl<C, F, const T: usize, const RATE: usize> Differential<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(r_f: usize, r_p: usize) -> Self {
+        Self {
+            local: PoseidonHash::new(PrecomputedSpec::new(r_f, r_p)),
+            reference: Poseidon::new(r_f, r_p),
+            squeezes: 0,
+        }
+    }
+
+    pub fn update(&mut self, elements: &[F]) {
+        self.local.update(elements);
+        self.reference.update(elements);
+    }
+
+    /// Squeezes both sponges, returning the common output
+    pub fn squeeze(&mut self) -> Result<F, Mismatch<F>> {
+        let (local, reference) = (self.local.squeeze(), self.reference.squeeze());
+        let squeeze = self.squeezes;
+        self.squeezes += 1;
+        if local == reference {
+            Ok(local)
+        } else {
+            Err(Mismatch {
+                squeeze,
+                local,
+                reference,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        pasta::{EqAffine, Fp},
+    };
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Runs `blocks` of elements through both sponges, squeezing after each
+    /// block and once more at the end
+    fn session<C, F, const T: usize, const RATE: usize>(
+        r_f: usize,
+        r_p: usize,
+        bytes: &[u8],
+        blocks: &[usize],
+    ) -> Result<(), Mismatch<F>>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        F: PrimeField + FromUniformBytes<64>,
+    {
+        let mut elements = bytes.chunks(32).map(encoding::from_be_bytes_reduced::<F>);
+        let mut differential = Differential::<C, F, T, RATE>::new(r_f, r_p);
+        for len in blocks {
+            differential.update(&elements.by_ref().take(*len).collect::<Vec<_>>());
+            differential.squeeze()?;
+        }
+        differential.squeeze().map(|_| ())
+    }
+
+    macro_rules! differential {
+        ($name:ident, $curve:ty, $field:ty, $t:expr, $rate:expr) => {
+            proptest! {
+                #![proptest_config(ProptestConfig::with_cases(16))]
+                #[test]
+                fn $name(
+                    bytes in prop::collection::vec(any::<u8>(), 32 * 4 * ($rate + 1)),
+                    blocks in prop::collection::vec(0usize..=$rate + 1, 0..4),
+                    half_r_f in 1usize..5,
+                    r_p in 1usize..64,
+                ) {
+                    let r_f = 2 * half_r_f;
+                    let result = session::<$curve, $field, $t, $rate>(r_f, r_p, &bytes, &blocks);
+                    prop_assert_eq!(result, Ok(()));
+                }
+            }
+        };
+    }
+
+    differential!(test_differential_bn256_t3, G1Affine, Fr, 3, 2);
+    differential!(test_differential_bn256_t5, G1Affine, Fr, 5, 4);
+    differential!(test_differential_vesta_t4, EqAffine, Fp, 4, 3);
+
+    #[test]
+    fn test_mismatch_display() {
+        let mismatch = Mismatch {
+            squeeze: 2,
+            local: Fr::from(1),
+            reference: Fr::from(2),
+        };
+        assert!(mismatch
+            .to_string()
+            .starts_with("squeeze 2 differs: local 0x"));
+    }
+}
@@ -0,0 +1,679 @@
+//! Canonical encodings of field elements and parameters.
+//!
+//! These are the encodings every serialization path and foreign binding in
+//! the crate uses:
+//!
+//! - bytes: the canonical integer in big-endian order, exactly as many bytes
+//!   as the field's `Repr` (32 for the bn256 and pasta fields). Decoding
+//!   rejects other lengths and values not below the modulus.
+//! - hex: `0x`-prefixed, zero-padded lowercase hex of those bytes. Decoding
+//!   rejects anything else, so each value has exactly one encoding.
+//! - decimal: plain base-10 integers without sign, leading `+` or leading
+//!   zeros, also required to be below the modulus.
+//! - oversized inputs, such as 32- or 64-byte outputs of other hashes, are
+//!   never silently truncated. [`from_be_bytes_reduced`] maps big-endian
+//!   bytes of any length to the integer they encode modulo the field order.
+//!
+//! Absorbing bytes is the one exception to big-endian order: [`pack_bytes`],
+//! and with it `PoseidonHash::update_bytes`, packs each chunk of input bytes
+//! into an element little-endian, the first byte being the least
+//! significant. Changing it would change every byte-string digest, so it is
+//! kept as an absorption format, not a serialization of field elements.
+//!
+//! Domain labels follow the same format: every labelled construction of the
+//! crate, such as `commitment`, `kdf`, `hash_to_curve` and `vrf`, absorbs
+//! `pack_bytes(label)` before anything else. A label is thus terminated like
+//! any byte string and absorbs as `update_bytes(label)` would, and chips
+//! assign the same elements as constants.
+//!
+//! This assumes the little-endian `Repr` used by the halo2curves fields.
+use std::fmt;
+
+use halo2curves::group::ff::PrimeField;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Big-endian bytes of the canonical representation of `value`
+pub fn to_be_bytes<F: PrimeField>(value: &F) -> Vec<u8> {
+    let mut bytes = value.to_repr().as_ref().to_vec();
+    bytes.reverse();
+    bytes
+}
+
+/// Inverse of [`to_be_bytes`]; returns `None` if `bytes` has the wrong length
+/// or encodes a non-canonical value
+pub fn from_be_bytes<F: PrimeField>(bytes: &[u8]) -> Option<F> {
+    let mut repr = F::Repr::default();
+    if bytes.len() != repr.as_ref().len() {
+        return None;
+    }
+    repr.as_mut()
+        .iter_mut()
+        .zip(bytes.iter().rev())
+        .for_each(|(dst, src)| *dst = *src);
+    Option::from(F::from_repr(repr))
+}
+
+/// Big-endian `bytes` of any length reduced modulo the field order
+pub fn from_be_bytes_reduced<F: PrimeField>(bytes: &[u8]) -> F {
+    let radix = F::from(256);
+    bytes
+        .iter()
+        .fold(F::ZERO, |acc, byte| acc * radix + F::from(*byte as u64))
+}
+
+/// Splits `value` into little-endian limbs of `bits` bits, as many as its
+/// field needs, each embedded into `F`
+///
+/// # Panics
+///
+/// If `bits` is zero or a limb does not fit below the modulus of `F`
+pub fn to_limbs<B: PrimeField, F: PrimeField>(value: &B, bits: usize) -> Vec<F> {
+    assert!(
+        bits > 0 && bits <= F::CAPACITY as usize,
+        "invalid limb width"
+    );
+    let repr = value.to_repr();
+    let value_bits = repr
+        .as_ref()
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .take(B::NUM_BITS as usize)
+        .collect::<Vec<_>>();
+    value_bits
+        .chunks(bits)
+        .map(|limb| {
+            limb.iter()
+                .rev()
+                .fold(F::ZERO, |acc, bit| acc.double() + F::from(*bit as u64))
+        })
+        .collect()
+}
+
+/// The integer of the low `bits` bits of `value` as an element of `S`
+pub fn truncate<F: PrimeField, S: PrimeField>(value: &F, bits: usize) -> S {
+    let repr = value.to_repr();
+    let bits = repr
+        .as_ref()
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .take(bits)
+        .collect::<Vec<_>>();
+    bits.iter()
+        .rev()
+        .fold(S::ZERO, |acc, bit| acc.double() + S::from(*bit as u64))
+}
+
+/// The elements [`PoseidonHash::update_bytes`] absorbs for the whole byte
+/// string `bytes`: chunks of `CAPACITY / 8` little-endian bytes, the last
+/// one, possibly empty, followed by a `0x01` byte
+///
+/// [`PoseidonHash::update_bytes`]: crate::poseidon_hash::PoseidonHash::update_bytes
+pub fn pack_bytes<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+    let width = F::CAPACITY as usize / 8;
+    let pack = |chunk: &[u8]| {
+        let mut repr = F::Repr::default();
+        repr.as_mut()[..chunk.len()].copy_from_slice(chunk);
+        F::from_repr(repr).unwrap()
+    };
+    let mut tail = bytes[bytes.len() / width * width..].to_vec();
+    tail.push(1);
+    bytes
+        .chunks_exact(width)
+        .chain([&tail[..]])
+        .map(pack)
+        .collect()
+}
+
+/// Encodes `value` as `0x`-prefixed big-endian hex
+pub fn to_hex<F: PrimeField>(value: &F) -> String {
+    let hex = to_be_bytes(value)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("0x{}", hex)
+}
+
+/// Bytes of an even number of lowercase hex digits. Every character must be a
+/// digit: `u8::from_str_radix` alone also accepts a leading `+` and uppercase
+/// digits, which would give a value several encodings.
+fn hex_digits(hex: &str) -> Option<Vec<u8>> {
+    let digit = |byte: u8| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte);
+    if !hex.bytes().all(digit) || hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Decodes the output of [`to_hex`]; returns `None` on malformed input, such
+/// as a missing `0x` prefix or uppercase digits, or a non-canonical value
+pub fn from_hex<F: PrimeField>(hex: &str) -> Option<F> {
+    from_be_bytes(&hex_digits(hex.strip_prefix("0x")?)?)
+}
+
+/// Encodes a byte string as `0x`-prefixed lowercase hex, in order
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("0x{}", hex)
+}
+
+/// Decodes the output of [`bytes_to_hex`]; the `0x` prefix is required
+pub fn bytes_from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex_digits(hex.strip_prefix("0x")?)
+}
+
+/// Encodes `value` as a base-10 integer
+pub fn to_decimal<F: PrimeField>(value: &F) -> String {
+    let mut bytes = to_be_bytes(value);
+    let mut digits = Vec::new();
+    while bytes.iter().any(|byte| *byte != 0) {
+        let mut remainder = 0u32;
+        for byte in bytes.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 10) as u8;
+            remainder = acc % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Decodes the output of [`to_decimal`]; unlike
+/// `PrimeField::from_str_vartime`, values not below the modulus are rejected
+/// instead of being reduced, and so are leading zeros other than `"0"` itself
+pub fn from_decimal<F: PrimeField>(decimal: &str) -> Option<F> {
+    if decimal.is_empty() || (decimal.len() > 1 && decimal.starts_with('0')) {
+        return None;
+    }
+    let mut bytes = vec![0u8; F::Repr::default().as_ref().len()];
+    for digit in decimal.chars() {
+        let mut carry = digit.to_digit(10)?;
+        for byte in bytes.iter_mut().rev() {
+            let acc = *byte as u32 * 10 + carry;
+            *byte = acc as u8;
+            carry = acc >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    from_be_bytes(&bytes)
+}
+
+/// A hash output with a fixed textual representation; [`fmt::Display`]
+/// prints [`Digest::to_hex`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct Digest<F: PrimeField>(
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field"))] pub F,
+);
+
+impl<F: PrimeField> Digest<F> {
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        from_hex(hex).map(Self)
+    }
+
+    pub fn to_decimal(&self) -> String {
+        to_decimal(&self.0)
+    }
+
+    pub fn from_decimal(decimal: &str) -> Option<Self> {
+        from_decimal(decimal).map(Self)
+    }
+
+    pub fn to_be_bytes(&self) -> Vec<u8> {
+        to_be_bytes(&self.0)
+    }
+
+    pub fn from_be_bytes(bytes: &[u8]) -> Option<Self> {
+        from_be_bytes(bytes).map(Self)
+    }
+}
+
+impl<F: PrimeField> From<F> for Digest<F> {
+    fn from(value: F) -> Self {
+        Self(value)
+    }
+}
+
+impl<F: PrimeField> fmt::Display for Digest<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// `#[serde(with = "crate::encoding::field")]` for a single field element.
+///
+/// Human-readable formats get [`to_hex`] strings, binary formats such as
+/// bincode get the raw [`to_be_bytes`]
+#[cfg(feature = "serde")]
+pub mod field {
+    use halo2curves::group::ff::PrimeField;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<F: PrimeField, S: Serializer>(value: &F, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&super::to_hex(value))
+        } else {
+            super::to_be_bytes(value).serialize(s)
+        }
+    }
+
+    pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(d: D) -> Result<F, D::Error> {
+        let value = if d.is_human_readable() {
+            super::from_hex(&String::deserialize(d)?)
+        } else {
+            super::from_be_bytes(&Vec::<u8>::deserialize(d)?)
+        };
+        value.ok_or_else(|| D::Error::custom("invalid field element"))
+    }
+}
+
+/// `#[serde(with = "crate::encoding::field_vec")]` for a `Vec` of field
+/// elements, encoded element-wise like [`field`]
+#[cfg(feature = "serde")]
+pub mod field_vec {
+    use halo2curves::group::ff::PrimeField;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<F: PrimeField, S: Serializer>(values: &[F], s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_seq(values.iter().map(super::to_hex))
+        } else {
+            s.collect_seq(values.iter().map(super::to_be_bytes))
+        }
+    }
+
+    pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(d: D) -> Result<Vec<F>, D::Error> {
+        let values = if d.is_human_readable() {
+            Vec::<String>::deserialize(d)?
+                .iter()
+                .map(|hex| super::from_hex(hex))
+                .collect::<Option<Vec<_>>>()
+        } else {
+            Vec::<Vec<u8>>::deserialize(d)?
+                .iter()
+                .map(|bytes| super::from_be_bytes(bytes))
+                .collect::<Option<Vec<_>>>()
+        };
+        values.ok_or_else(|| D::Error::custom("invalid field element"))
+    }
+}
+
+/// Borsh encoding helpers: an element is its [`to_be_bytes`], a `Vec` of
+/// elements is prefixed with its length as a little-endian `u32`, following
+/// borsh's own `Vec` encoding
+#[cfg(feature = "borsh")]
+pub mod borsh {
+    use std::io::{self, Read, Write};
+
+    use halo2curves::group::ff::PrimeField;
+
+    pub fn write_field<F: PrimeField, W: Write>(value: &F, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&super::to_be_bytes(value))
+    }
+
+    pub fn read_field<F: PrimeField, R: Read>(reader: &mut R) -> io::Result<F> {
+        let mut bytes = vec![0u8; F::Repr::default().as_ref().len()];
+        reader.read_exact(&mut bytes)?;
+        super::from_be_bytes(&bytes)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid field element"))
+    }
+
+    pub fn write_fields<F: PrimeField, W: Write>(values: &[F], writer: &mut W) -> io::Result<()> {
+        let len = u32::try_from(values.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many elements"))?;
+        writer.write_all(&len.to_le_bytes())?;
+        values
+            .iter()
+            .try_for_each(|value| write_field(value, writer))
+    }
+
+    pub fn read_fields<F: PrimeField, R: Read>(reader: &mut R) -> io::Result<Vec<F>> {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        (0..u32::from_le_bytes(len))
+            .map(|_| read_field(reader))
+            .collect()
+    }
+
+    pub fn write_len<W: Write>(len: usize, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(len as u64).to_le_bytes())
+    }
+
+    pub fn read_len<R: Read>(reader: &mut R) -> io::Result<usize> {
+        let mut len = [0u8; 8];
+        reader.read_exact(&mut len)?;
+        usize::try_from(u64::from_le_bytes(len))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "length overflow"))
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> ::borsh::BorshSerialize for Digest<F> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        borsh::write_field(&self.0, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> ::borsh::BorshDeserialize for Digest<F> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        borsh::read_field(reader).map(Self)
+    }
+}
+
+/// `#[serde(with = "crate::encoding::spec")]` for a [`PrecomputedSpec`].
+///
+/// Only `r_f` and `r_p` are written and the constants are regenerated on
+/// deserialization, which only describes the specs of
+/// [`PrecomputedSpec::new`]. Serializing any other spec, such as one of
+/// [`PrecomputedSpec::from_parts`] with external constants, fails rather
+/// than silently loading back as a different permutation; the spec's own
+/// serde encoding, with every constant, covers those. Serializing
+/// regenerates the spec to compare it.
+///
+/// Round numbers that the constant generation cannot handle are rejected:
+/// `r_f` must be even and positive, `r_p` positive, and both fit the 10 bits
+/// the Grain LFSR is seeded with.
+#[cfg(feature = "serde")]
+pub mod spec {
+    use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::precomputed::PrecomputedSpec;
+
+    #[derive(Serialize, Deserialize)]
+    struct Rounds {
+        r_f: usize,
+        r_p: usize,
+    }
+
+    fn supported(r_f: usize, r_p: usize) -> bool {
+        r_f != 0 && r_f.is_multiple_of(2) && r_f < 1 << 10 && r_p != 0 && r_p < 1 << 10
+    }
+
+    pub fn serialize<F, S, const T: usize, const RATE: usize>(
+        spec: &PrecomputedSpec<F, T, RATE>,
+        s: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        F: PrimeField + FromUniformBytes<64>,
+        S: Serializer,
+    {
+        let (r_f, r_p) = (spec.r_f(), spec.r_p());
+        if !supported(r_f, r_p) || *spec != PrecomputedSpec::new(r_f, r_p) {
+            return Err(ser::Error::custom(
+                "the spec is not the one generated for its round numbers",
+            ));
+        }
+        Rounds { r_f, r_p }.serialize(s)
+    }
+
+    pub fn deserialize<'de, F, D, const T: usize, const RATE: usize>(
+        d: D,
+    ) -> Result<PrecomputedSpec<F, T, RATE>, D::Error>
+    where
+        F: PrimeField + FromUniformBytes<64>,
+        D: Deserializer<'de>,
+    {
+        let Rounds { r_f, r_p } = Rounds::deserialize(d)?;
+        if !supported(r_f, r_p) {
+            return Err(de::Error::custom(format!(
+                "unsupported round numbers r_f = {}, r_p = {}",
+                r_f, r_p
+            )));
+        }
+        Ok(PrecomputedSpec::new(r_f, r_p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::Fr,
+        group::ff::Field,
+        pasta::{Fp, Fq},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let value = Fr::from_str_vartime(
+            "20304616028358001435806807494046171997958789835068077254356069730773893150537",
+        )
+        .unwrap();
+        let hex = to_hex(&value);
+        assert_eq!(
+            hex,
+            "0x2ce4016298e9e5fcaa94ccb686413e16add1bb813def8a3a0628aed46ea07749"
+        );
+        assert_eq!(from_hex::<Fr>(&hex), Some(value));
+        assert_eq!(to_be_bytes(&value)[0], 0x2c);
+        assert_eq!(from_be_bytes::<Fr>(&to_be_bytes(&value)), Some(value));
+        assert_eq!(from_hex::<Fr>("0x01"), None);
+        // `+` is not a hex digit, though `u8::from_str_radix` takes it
+        let signed = format!("0x+{}", &hex[3..]);
+        assert_eq!(from_hex::<Fr>(&signed), None);
+        // only the exact output of `to_hex` is accepted
+        assert_eq!(from_hex::<Fr>(&hex[2..]), None);
+        assert_eq!(from_hex::<Fr>(&hex.to_uppercase()), None);
+        assert_eq!(
+            from_hex::<Fr>(&format!("0x{}", hex[2..].to_uppercase())),
+            None
+        );
+        assert_eq!(from_hex::<Fr>(&format!("0X{}", &hex[2..])), None);
+        // the modulus itself is not canonical
+        assert_eq!(
+            from_hex::<Fr>("0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pack_bytes() {
+        use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait};
+
+        type PH = PoseidonHash<halo2curves::bn256::G1Affine, Fr, 3, 2>;
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        // little-endian, unlike the other encodings: the first byte is the
+        // least significant and the `0x01` terminator the most
+        assert_eq!(pack_bytes::<Fr>(&[1, 2]), vec![Fr::from(0x01_02_01)]);
+        for len in [0, 1, 30, 31, 32, 62, 100] {
+            let bytes = (0..len as u8).collect::<Vec<_>>();
+            let elements = pack_bytes::<Fr>(&bytes);
+            assert_eq!(elements.len(), len / 31 + 1);
+            let mut expected = PH::new(spec.clone());
+            expected.update_bytes(&bytes);
+            let mut packed = PH::new(spec.clone());
+            packed.update(&elements);
+            assert_eq!(packed.squeeze(), expected.squeeze());
+        }
+    }
+
+    #[test]
+    fn test_bytes_hex() {
+        assert_eq!(bytes_to_hex(&[]), "0x");
+        assert_eq!(bytes_to_hex(&[0, 0xab, 7]), "0x00ab07");
+        assert_eq!(bytes_from_hex("0x00ab07"), Some(vec![0, 0xab, 7]));
+        assert_eq!(bytes_from_hex("0x"), Some(vec![]));
+        assert_eq!(bytes_from_hex("00ab07"), None);
+        assert_eq!(bytes_from_hex("0xabc"), None);
+        assert_eq!(bytes_from_hex("0xzz"), None);
+        assert_eq!(bytes_from_hex("0x+f"), None);
+        assert_eq!(bytes_from_hex("0x-1"), None);
+        assert_eq!(bytes_from_hex("0x00AB07"), None);
+    }
+
+    /// Expected values computed independently as
+    /// `int.from_bytes(bytes, "big") % r` in Python
+    #[test]
+    fn test_reduction_vectors() {
+        let modulus = hex_bytes("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001");
+        assert_eq!(from_be_bytes_reduced::<Fr>(&modulus), Fr::ZERO);
+        assert_eq!(from_be_bytes_reduced::<Fr>(&[]), Fr::ZERO);
+        assert_eq!(
+            to_hex(&from_be_bytes_reduced::<Fr>(b"poseidon")),
+            "0x000000000000000000000000000000000000000000000000706f736569646f6e"
+        );
+        assert_eq!(
+            to_hex(&from_be_bytes_reduced::<Fr>(&[0xff; 32])),
+            "0x0e0a77c19a07df2f666ea36f7879462e36fc76959f60cd29ac96341c4ffffffa"
+        );
+        assert_eq!(
+            to_hex(&from_be_bytes_reduced::<Fr>(&(0..64).collect::<Vec<u8>>())),
+            "0x12ee8bcf6d74bdaf77a7d50b3770109871eebda1fafdc6440ee56a7ac9131b45"
+        );
+
+        // canonical inputs reduce to themselves
+        let value = Fr::from(0x1234_5678);
+        assert_eq!(from_be_bytes_reduced::<Fr>(&to_be_bytes(&value)), value);
+        assert_eq!(from_be_bytes::<Fr>(&[0xff; 32]), None);
+        assert_eq!(from_be_bytes::<Fr>(&[0x01; 31]), None);
+    }
+
+    fn hex_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_truncate() {
+        let value = Fp::from(u64::MAX) * Fp::from(u64::MAX);
+        // (2^64 - 1)^2 = 2^128 - 2^65 + 1
+        assert_eq!(
+            truncate::<Fp, Fq>(&value, 128),
+            Fq::from(u64::MAX) * Fq::from(u64::MAX)
+        );
+        assert_eq!(truncate::<Fp, Fq>(&value, 64), Fq::ONE);
+        // low 66 bits: 2^65 + 1
+        assert_eq!(
+            truncate::<Fp, Fq>(&value, 66),
+            Fq::from(1 << 63) * Fq::from(4) + Fq::ONE
+        );
+    }
+
+    #[test]
+    fn test_decimal() {
+        let decimal =
+            "20304616028358001435806807494046171997958789835068077254356069730773893150537";
+        let digest = Digest::<Fr>::from_decimal(decimal).unwrap();
+        assert_eq!(digest.to_decimal(), decimal);
+        assert_eq!(
+            digest.to_string(),
+            "0x2ce4016298e9e5fcaa94ccb686413e16add1bb813def8a3a0628aed46ea07749"
+        );
+        assert_eq!(Digest::from_hex(&digest.to_hex()), Some(digest));
+        assert_eq!(to_decimal(&Fr::ZERO), "0");
+        assert_eq!(from_decimal::<Fr>("0"), Some(Fr::ZERO));
+        assert_eq!(from_decimal::<Fr>(""), None);
+        assert_eq!(from_decimal::<Fr>("-1"), None);
+        assert_eq!(from_decimal::<Fr>("+1"), None);
+        assert_eq!(from_decimal::<Fr>("00"), None);
+        assert_eq!(from_decimal::<Fr>("01"), None);
+        assert_eq!(from_decimal::<Fr>(&format!("0{}", decimal)), None);
+        // the modulus is rejected rather than reduced to zero
+        assert_eq!(
+            from_decimal::<Fr>(
+                "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+            ),
+            None
+        );
+        assert_eq!(from_decimal::<Fr>(&"9".repeat(80)), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bincode() {
+        use crate::hash_chain::Checkpoints;
+
+        let checkpoints = Checkpoints {
+            interval: 2,
+            len: 3,
+            states: vec![Fr::from(1), Fr::from(2)],
+            output: Fr::from(3),
+        };
+        let bytes = bincode::serialize(&checkpoints).unwrap();
+        // two lengths, a vector of two elements and one element, each element
+        // prefixed with its byte length
+        assert_eq!(bytes.len(), 8 + 8 + 8 + 3 * (8 + 32));
+        let decoded: Checkpoints<Fr> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, checkpoints);
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_borsh() {
+        let digest = Digest(Fr::from(42));
+        let bytes = ::borsh::to_vec(&digest).unwrap();
+        assert_eq!(bytes, to_be_bytes(&Fr::from(42)));
+        assert_eq!(::borsh::from_slice::<Digest<Fr>>(&bytes).unwrap(), digest);
+        assert!(::borsh::from_slice::<Digest<Fr>>(&[0xff; 32]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json() {
+        use crate::{
+            hash_chain::{Checkpoints, HashChain},
+            precomputed::PrecomputedSpec,
+        };
+
+        let chain =
+            HashChain::<halo2curves::bn256::G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 57));
+        let checkpoints = chain.hash_chain_with_checkpoints(Fr::from(1), 5, 2);
+        let json = serde_json::to_string(&checkpoints).unwrap();
+        assert!(json.contains(&format!("\"{}\"", to_hex(&checkpoints.output))));
+        let decoded: Checkpoints<Fr> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, checkpoints);
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Config {
+            #[serde(with = "crate::encoding::spec")]
+            spec: PrecomputedSpec<Fr, 3, 2>,
+        }
+        let json = serde_json::to_string(&Config {
+            spec: PrecomputedSpec::new(8, 57),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"spec":{"r_f":8,"r_p":57}}"#);
+        let decoded: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.spec.r_p(), 57);
+        for json in [
+            r#"{"spec":{"r_f":7,"r_p":57}}"#,
+            r#"{"spec":{"r_f":0,"r_p":57}}"#,
+            r#"{"spec":{"r_f":8,"r_p":0}}"#,
+            r#"{"spec":{"r_f":8,"r_p":1024}}"#,
+        ] {
+            assert!(serde_json::from_str::<Config>(json).is_err());
+        }
+
+        // round numbers cannot describe other constants; the spec's own
+        // encoding keeps them all
+        let (mut constants, mds) = crate::grain::parameters::<Fr, 3>(8, 57);
+        constants[0][0] += Fr::ONE;
+        let spec = PrecomputedSpec::<Fr, 3, 2>::from_parts(8, constants, mds).unwrap();
+        assert!(serde_json::to_string(&Config { spec: spec.clone() }).is_err());
+        let json = serde_json::to_string(&spec).unwrap();
+        let decoded: PrecomputedSpec<Fr, 3, 2> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, spec);
+        assert_ne!(decoded, PrecomputedSpec::new(8, 57));
+    }
+}
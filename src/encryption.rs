@@ -0,0 +1,325 @@
+//! Authenticated encryption of field elements with the Poseidon duplex, the
+//! construction of Khovratovich's "Encryption with Poseidon" over the
+//! permutation of this crate.
+//!
+//! The state starts as `[nonce + len * 2^128, key, 0, .., 0]` and is
+//! permuted once. Each block of `RATE` message elements, the last one
+//! padded with zeros, is added to words `1..=RATE`; these words are the
+//! ciphertext block, and the state is permuted again. The tag is word 1 of
+//! the final state. The ciphertext has `len` rounded up to a multiple of
+//! `RATE` elements, and decryption checks the tag and the zero padding.
+//!
+//! [`EncryptionChip::encrypt`] assigns the same ciphertext and tag in a
+//! circuit, from a key and message assigned elsewhere. A circuit proving
+//! that a public ciphertext decrypts to a witnessed message encrypts the
+//! message and constrains the result to the ciphertext.
+//!
+//! A nonce must never be reused with the same key. The constants differ
+//! from circomlib's, so ciphertexts do not interoperate with
+//! `poseidon-cipher` implementations.
+use halo2_proofs::plonk::Error;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    main_gate::{AssignedValue, MainGate, MainGateConfig, RegionCtx, WrapValue},
+    poseidon_circuit::PoseidonChip,
+    poseidon_hash::State,
+    precomputed::PrecomputedSpec,
+};
+
+fn start_state<F: PrimeField, const T: usize>(key: F, nonce: u128, len: usize) -> [F; T] {
+    let mut state = [F::ZERO; T];
+    state[0] = F::from_u128(nonce) + F::from_u128(len as u128) * F::from_u128(1 << 127).double();
+    state[1] = key;
+    state
+}
+
+/// The ciphertext and tag of `message` under `key` and `nonce`
+pub fn encrypt<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    key: F,
+    nonce: u128,
+    message: &[F],
+) -> (Vec<F>, F)
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut state = State::<F, T, RATE>::new(start_state(key, nonce, message.len()));
+    state.permutation(spec, &[F::ZERO; RATE]);
+    let mut ciphertext = Vec::new();
+    for chunk in message.chunks(RATE) {
+        let mut block = [F::ZERO; RATE];
+        block[..chunk.len()].copy_from_slice(chunk);
+        ciphertext.extend(state.inner[1..].iter().zip(&block).map(|(s, m)| *s + m));
+        state.permutation(spec, &block);
+    }
+    (ciphertext, state.inner[1])
+}
+
+/// The message of `len` elements that `ciphertext` and `tag` encrypt under
+/// `key` and `nonce`, `None` if they do not authenticate
+pub fn decrypt<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    key: F,
+    nonce: u128,
+    ciphertext: &[F],
+    tag: &F,
+    len: usize,
+) -> Option<Vec<F>>
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    if ciphertext.len() != len.div_ceil(RATE) * RATE {
+        return None;
+    }
+    let mut state = State::<F, T, RATE>::new(start_state(key, nonce, len));
+    state.permutation(spec, &[F::ZERO; RATE]);
+    let mut message = Vec::new();
+    for chunk in ciphertext.chunks(RATE) {
+        let mut block = [F::ZERO; RATE];
+        for ((m, c), s) in block.iter_mut().zip(chunk).zip(&state.inner[1..]) {
+            *m = *c - s;
+        }
+        message.extend_from_slice(&block);
+        state.permutation(spec, &block);
+    }
+    let padding = message.split_off(len);
+    let padded = padding.iter().all(|m| bool::from(m.is_zero()));
+    (bool::from(state.inner[1].ct_eq(tag)) && padded).then_some(message)
+}
+
+/// [`encrypt`] over the main gate
+pub struct EncryptionChip<F: PrimeField, const T: usize, const RATE: usize> {
+    main_gate: MainGate<F, T>,
+    poseidon: PoseidonChip<F, T, RATE>,
+}
+
+impl<F: PrimeField, const T: usize, const RATE: usize> EncryptionChip<F, T, RATE> {
+    pub fn new(config: MainGateConfig<T>, spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        Self {
+            main_gate: MainGate::new(config.clone()),
+            poseidon: PoseidonChip::new(config, spec),
+        }
+    }
+
+    /// A cell constrained to `a + b`
+    fn add(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let mut q_1 = vec![F::ZERO; T];
+        q_1[..2].copy_from_slice(&[F::ONE, F::ONE]);
+        let sum = a.value().copied() + b.value().copied();
+        self.main_gate.apply(
+            ctx,
+            (Some(q_1), None, Some(vec![a.into(), b.into()])),
+            None,
+            (-F::ONE, sum.into()),
+        )
+    }
+
+    /// The ciphertext and tag of the assigned `message` under the assigned
+    /// `key` and `nonce`, equal to the ones of [`encrypt`]
+    pub fn encrypt(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        key: &AssignedValue<F>,
+        nonce: u128,
+        message: &[AssignedValue<F>],
+    ) -> Result<(Vec<AssignedValue<F>>, AssignedValue<F>), Error> {
+        let words = start_state::<F, T>(F::ZERO, nonce, message.len());
+        let mut state = Vec::with_capacity(T);
+        state.push(self.main_gate.assign_constant(ctx, words[0])?);
+        state.push(key.clone());
+        for _ in 2..T {
+            state.push(self.main_gate.assign_constant(ctx, F::ZERO)?);
+        }
+        let zeros = vec![WrapValue::Zero; RATE];
+        let mut state = self
+            .poseidon
+            .permutation(ctx, zeros, &state.try_into().unwrap())?;
+
+        let mut ciphertext = Vec::new();
+        for chunk in message.chunks(RATE) {
+            for (i, word) in state[1..].iter().enumerate() {
+                match chunk.get(i) {
+                    Some(m) => ciphertext.push(self.add(ctx, word, m)?),
+                    None => ciphertext.push(word.clone()),
+                }
+            }
+            let mut block = chunk.iter().map(WrapValue::from).collect::<Vec<_>>();
+            block.resize(RATE, WrapValue::Zero);
+            state = self.poseidon.permutation(ctx, block, &state)?;
+        }
+        Ok((ciphertext, state[1].clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::{FailureLocation, MockProver, VerifyFailure},
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::{group::ff::Field, pasta::Fp};
+
+    use super::*;
+    use crate::test_fixture::{spec, FixtureConfig, T};
+
+    fn message(len: u64) -> Vec<Fp> {
+        (1..=len).map(Fp::from).collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let key = Fp::from(42);
+        for len in 0..=5 {
+            let message = message(len);
+            let (ciphertext, tag) = encrypt(&spec(), key, 7, &message);
+            assert_eq!(ciphertext.len(), len.div_ceil(2) as usize * 2);
+            assert_eq!(
+                decrypt(&spec(), key, 7, &ciphertext, &tag, message.len()),
+                Some(message)
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects() {
+        let (key, message) = (Fp::from(42), message(3));
+        let (ciphertext, tag) = encrypt(&spec(), key, 7, &message);
+        assert_eq!(
+            decrypt(&spec(), Fp::from(43), 7, &ciphertext, &tag, 3),
+            None
+        );
+        assert_eq!(decrypt(&spec(), key, 8, &ciphertext, &tag, 3), None);
+        assert_eq!(
+            decrypt(&spec(), key, 7, &ciphertext, &(tag + Fp::ONE), 3),
+            None
+        );
+        // the length is authenticated
+        assert_eq!(decrypt(&spec(), key, 7, &ciphertext, &tag, 4), None);
+        assert_eq!(decrypt(&spec(), key, 7, &ciphertext[..2], &tag, 2), None);
+        let mut tampered = ciphertext.clone();
+        tampered[1] += Fp::ONE;
+        assert_eq!(decrypt(&spec(), key, 7, &tampered, &tag, 3), None);
+        // another nonce gives another ciphertext
+        assert_ne!(encrypt(&spec(), key, 8, &message).0, ciphertext);
+    }
+
+    struct EncryptionCircuit {
+        key: Fp,
+        message: Vec<Fp>,
+        /// Absorbs one in place of the first zero word of the keystream,
+        /// with a consistent output in that row
+        forged: bool,
+    }
+
+    /// The row of word 1 of the first permutation: after the key, the
+    /// message and the constant words 0 and 2 of the start state, and word 0
+    fn forged_row(len: usize) -> usize {
+        1 + len + (T - 1) + 1
+    }
+
+    impl Circuit<Fp> for EncryptionCircuit {
+        type Config = FixtureConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                key: Fp::ZERO,
+                message: vec![Fp::ZERO; self.message.len()],
+                forged: false,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            FixtureConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = EncryptionChip::new(config.main_gate.clone(), spec());
+            let cells = layouter.assign_region(
+                || "encryption",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let key = config.witness(ctx, [self.key])?.remove(0);
+                    let message = config.witness(ctx, self.message.iter().copied())?;
+                    let (mut ciphertext, tag) = chip.encrypt(ctx, &key, 7, &message)?;
+                    if self.forged {
+                        ctx.offset = forged_row(message.len());
+                        let out = self.key + spec::<Fp>().start_constants()[0][1] + Fp::ONE;
+                        let main_gate = &config.main_gate;
+                        ctx.assign_advice(|| "forged", main_gate.input, Value::known(Fp::ONE))?;
+                        ctx.assign_advice(|| "forged", main_gate.out, Value::known(out))?;
+                    }
+                    ciphertext.push(tag);
+                    Ok(ciphertext)
+                },
+            )?;
+            for (row, cell) in cells.iter().enumerate() {
+                layouter.constrain_instance(cell.cell(), config.instance, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        let key = Fp::from(42);
+        for len in [0, 1, 2, 3] {
+            let message = message(len);
+            let (mut expected, tag) = encrypt(&spec(), key, 7, &message);
+            expected.push(tag);
+            let circuit = EncryptionCircuit {
+                key,
+                message,
+                forged: false,
+            };
+            let prover = MockProver::run(11, &circuit, vec![expected.clone()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "{} elements", len);
+
+            // another key gives another ciphertext
+            let circuit = EncryptionCircuit {
+                key: Fp::from(43),
+                ..circuit
+            };
+            let prover = MockProver::run(11, &circuit, vec![expected]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    /// The zero words absorbed by the first permutation are constants, so a
+    /// prover cannot shift the keystream and the tag through them
+    #[test]
+    fn test_circuit_rejects_forged_zero_word() {
+        let (key, message) = (Fp::from(42), message(2));
+        let (mut expected, tag) = encrypt(&spec(), key, 7, &message);
+        expected.push(tag);
+        let circuit = EncryptionCircuit {
+            key,
+            message,
+            forged: true,
+        };
+        let prover = MockProver::run(11, &circuit, vec![expected]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert!(
+            failures.iter().any(|failure| matches!(
+                failure,
+                VerifyFailure::ConstraintNotSatisfied {
+                    location: FailureLocation::InRegion { offset, .. },
+                    ..
+                } if *offset == forged_row(2)
+            )),
+            "{:?}",
+            failures
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Hashing of small fixed-size tuples, such as nullifiers and note
+//! commitments, with one permutation.
+//!
+//! [`hash_fixed`] hashes `N` elements with the permutation of width `N + 1`,
+//! so they fill the rate exactly. Its digest is the one of a
+//! [`PoseidonHash`] of that width with [`Padding::FixedLength`]`(N)`: the
+//! capacity word is `N * 2^64` and the elements are absorbed without
+//! padding. The round numbers are the ones of
+//! [`round_numbers`](crate::rounds::round_numbers) at 128 bits of security,
+//! so the encoding is fixed by the arity and the field alone.
+//!
+//! The supported arities are those of [`FixedArity`]. Building the constants
+//! of a width dominates a single hash, so [`hash_fixed`] generates them once
+//! per process; a [`FixedHasher`] owns its copy.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+//! [`Padding::FixedLength`]: crate::poseidon_hash::Padding::FixedLength
+use std::fmt::Debug;
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    cached::CachedSpec,
+    poseidon_hash::State,
+    precomputed::PrecomputedSpec,
+    rounds::{round_numbers, CheckedSpec},
+};
+
+/// Security level of the round numbers of every arity
+const SECURITY: u32 = 128;
+
+/// Marker of the arity `N`
+#[derive(Clone, Copy, Debug)]
+pub struct Arity<const N: usize>;
+
+/// Width and constants of an arity
+pub trait FixedArity<F: PrimeField + FromUniformBytes<64>> {
+    /// The spec of width `N + 1` and rate `N`
+    type Spec: Clone + Debug;
+
+    fn spec() -> Self::Spec;
+
+    /// [`Self::spec`], generated once per process
+    fn cached() -> &'static Self::Spec;
+
+    /// Digest of `inputs`, `N` of them
+    fn hash(spec: &Self::Spec, inputs: &[F]) -> F;
+}
+
+macro_rules! fixed_arity {
+    ($($n:literal => $t:literal),*) => {
+        $(
+            impl<F: PrimeField + FromUniformBytes<64>> FixedArity<F> for Arity<$n> {
+                type Spec = PrecomputedSpec<F, $t, $n>;
+
+                fn spec() -> Self::Spec {
+                    PrecomputedSpec::with_security(SECURITY)
+                }
+
+                fn cached() -> &'static Self::Spec {
+                    let (r_f, r_p) = round_numbers(F::NUM_BITS, $t, SECURITY);
+                    PrecomputedSpec::cached(r_f, r_p)
+                }
+
+                fn hash(spec: &Self::Spec, inputs: &[F]) -> F {
+                    let mut inner = [F::ZERO; $t];
+                    inner[0] = F::from_u128($n << 64);
+                    let mut state = State::<F, $t, $n>::new(inner);
+                    state.permutation(spec, inputs);
+                    state.inner[1]
+                }
+            }
+        )*
+    };
+}
+
+fixed_arity!(2 => 3, 4 => 5, 8 => 9, 11 => 12);
+
+/// [`hash_fixed`] keeping the constants of its arity
+#[derive(Clone, Debug)]
+pub struct FixedHasher<F: PrimeField + FromUniformBytes<64>, const N: usize>
+where
+    Arity<N>: FixedArity<F>,
+{
+    spec: <Arity<N> as FixedArity<F>>::Spec,
+}
+
+impl<F: PrimeField + FromUniformBytes<64>, const N: usize> FixedHasher<F, N>
+where
+    Arity<N>: FixedArity<F>,
+{
+    pub fn new() -> Self {
+        Self {
+            spec: Arity::<N>::spec(),
+        }
+    }
+
+    pub fn hash(&self, inputs: &[F; N]) -> F {
+        Arity::<N>::hash(&self.spec, inputs)
+    }
+}
+
+impl<F: PrimeField + FromUniformBytes<64>, const N: usize> Default for FixedHasher<F, N>
+where
+    Arity<N>: FixedArity<F>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Digest of the `N` elements of `inputs` with one permutation
+pub fn hash_fixed<F: PrimeField + FromUniformBytes<64>, const N: usize>(inputs: &[F; N]) -> F
+where
+    Arity<N>: FixedArity<F>,
+{
+    Arity::<N>::hash(Arity::<N>::cached(), inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{
+        poseidon_hash::{hash_two, Padding, PoseidonHash},
+        ro_types::ROTrait,
+    };
+
+    fn inputs<const N: usize>() -> [Fr; N] {
+        std::array::from_fn(|i| Fr::from(i as u64 + 1))
+    }
+
+    macro_rules! matches_sponge {
+        ($name:ident, $n:literal, $t:literal) => {
+            #[test]
+            fn $name() {
+                let (r_f, r_p) = round_numbers(254, $t, 128);
+                let mut hasher = PoseidonHash::<G1Affine, Fr, $t, $n>::new_with_padding(
+                    PrecomputedSpec::new(r_f, r_p),
+                    Padding::FixedLength($n),
+                );
+                hasher.update(&inputs::<$n>());
+                assert_eq!(hash_fixed(&inputs::<$n>()), hasher.squeeze());
+            }
+        };
+    }
+
+    matches_sponge!(test_matches_sponge_2, 2, 3);
+    matches_sponge!(test_matches_sponge_4, 4, 5);
+    matches_sponge!(test_matches_sponge_8, 8, 9);
+    matches_sponge!(test_matches_sponge_11, 11, 12);
+
+    #[test]
+    fn test_hasher() {
+        let hasher = FixedHasher::<Fr, 4>::new();
+        let mut swapped = inputs::<4>();
+        swapped.swap(0, 3);
+        assert_eq!(hasher.hash(&inputs()), hash_fixed(&inputs::<4>()));
+        assert_ne!(hasher.hash(&inputs()), hasher.hash(&swapped));
+
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let (left, right) = (Fr::from(1), Fr::from(2));
+        assert_eq!(hash_fixed(&[left, right]), hash_two(&spec, left, right));
+    }
+}
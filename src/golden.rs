@@ -0,0 +1,167 @@
+//! Digest stability across releases.
+//!
+//! `golden/<version>.txt` holds the digests of a fixed corpus as produced by
+//! that release of the crate. The current code has to reproduce the files of
+//! every release since the last breaking digest change, so downstream systems
+//! that treat digests as consensus data can upgrade without surprises.
+//!
+//! `UPDATE_GOLDEN=1 cargo test golden` writes the file of the current
+//! version. Changing digests, whether those of an earlier release or those
+//! already recorded for the current version, additionally requires adding the
+//! current version to `golden/BREAKING`: a deliberate, reviewable marker of a
+//! breaking digest change.
+use std::{cmp::Ordering, env, fs, path::Path};
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::{
+    bn256::{Fr, G1Affine},
+    group::ff::{FromUniformBytes, PrimeField},
+    pasta::{EpAffine, EqAffine, Fp, Fq},
+};
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/golden");
+
+/// Lines of `label: digest` for the corpus at width `T`
+fn digests<C, F, const T: usize, const RATE: usize>(curve: &str) -> Vec<String>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let spec = PrecomputedSpec::<F, T, RATE>::new(8, 56);
+    let elements: [(&str, Vec<F>); 5] = [
+        ("empty", vec![]),
+        ("zero", vec![F::ZERO]),
+        ("one-two", vec![F::from(1), F::from(2)]),
+        ("range-10", (0..10).map(F::from).collect()),
+        ("minus-one", vec![-F::ONE]),
+    ];
+    let bytes: [(&str, Vec<u8>); 3] = [
+        ("bytes-empty", vec![]),
+        ("bytes-poseidon", b"poseidon".to_vec()),
+        ("bytes-range-100", (0..100).collect()),
+    ];
+
+    let mut lines = Vec::new();
+    let mut push = |case: &str, hasher: &mut PoseidonHash<C, F, T, RATE>| {
+        let digest = encoding::to_hex(&hasher.squeeze());
+        lines.push(format!("{} t={} {}: {}", curve, T, case, digest));
+    };
+    for (case, inputs) in elements {
+        let mut hasher = PoseidonHash::new(spec.clone());
+        hasher.update(&inputs);
+        push(case, &mut hasher);
+    }
+    for (case, input) in bytes {
+        let mut hasher = PoseidonHash::new(spec.clone());
+        hasher.update_bytes(&input);
+        push(case, &mut hasher);
+    }
+    lines
+}
+
+fn corpus() -> String {
+    let lines = [
+        digests::<G1Affine, Fr, 3, 2>("bn256"),
+        digests::<G1Affine, Fr, 4, 3>("bn256"),
+        digests::<G1Affine, Fr, 5, 4>("bn256"),
+        digests::<EpAffine, Fq, 3, 2>("pallas"),
+        digests::<EqAffine, Fp, 3, 2>("vesta"),
+    ]
+    .concat();
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// `major.minor.patch`, ignoring pre-release and build metadata
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap()
+        .split('.')
+        .map(|part| part.parse().unwrap())
+        .collect()
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    parse_version(a).cmp(&parse_version(b))
+}
+
+/// Lines of `path` without `#` comments and blank lines
+fn read_lines(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn test_golden_digests() {
+    let current = env!("CARGO_PKG_VERSION");
+    let corpus = corpus();
+    let dir = Path::new(DIR);
+
+    // the last breaking change up to the current version
+    let breaking = read_lines(&dir.join("BREAKING"))
+        .into_iter()
+        .filter(|version| compare_versions(version, current) != Ordering::Greater)
+        .max_by(|a, b| compare_versions(a, b));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        let path = dir.join(format!("{}.txt", current));
+        let existing = read_lines(&path);
+        assert!(
+            existing.is_empty()
+                || existing.join("\n") + "\n" == corpus
+                || breaking.as_deref() == Some(current),
+            "digests of {} changed; add it to golden/BREAKING to update them",
+            current
+        );
+        let header = format!(
+            "# Digests of the golden corpus produced by poseidon_circuit {}.\n",
+            current
+        );
+        fs::write(path, header + &corpus).unwrap();
+    }
+
+    let mut checked = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let version = match path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .strip_suffix(".txt")
+        {
+            Some(version) => version.to_string(),
+            None => continue,
+        };
+        let superseded = matches!(
+            &breaking,
+            Some(breaking) if compare_versions(&version, breaking) == Ordering::Less
+        );
+        if superseded || compare_versions(&version, current) == Ordering::Greater {
+            continue;
+        }
+        assert_eq!(
+            read_lines(&path).join("\n") + "\n",
+            corpus,
+            "digests differ from release {}; if this is intended, add {} to golden/BREAKING",
+            version,
+            current
+        );
+        checked.push(version);
+    }
+    assert!(
+        checked.iter().any(|version| version == current),
+        "no golden file for {}; run with UPDATE_GOLDEN=1",
+        current
+    );
+}
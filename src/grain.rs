@@ -0,0 +1,106 @@
+//! The Grain LFSR of the Poseidon paper, generating round constants and MDS
+//! matrices from the seed of [`seed`].
+//!
+//! [`parameters`] is the generation of the Poseidon reference implementation,
+//! before [`crate::optimize`] folds it for the optimized permutation. Neither
+//! module uses the rest of the crate, so that the build script can include
+//! both to bake specs.
+use halo2curves::group::ff::PrimeField;
+
+/// The 80-bit seed of the Grain LFSR generating the constants of a width-`t`
+/// spec over `F`, most significant bit first
+pub fn seed<F: PrimeField>(t: usize, r_f: usize, r_p: usize) -> Vec<bool> {
+    let mut bits = Vec::with_capacity(80);
+    let mut push = |len: usize, value: u64| {
+        bits.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+    };
+    // prime field, x^5 S-box
+    push(2, 1);
+    push(4, 0);
+    push(12, F::NUM_BITS as u64);
+    push(12, t as u64);
+    push(10, r_f as u64);
+    push(10, r_p as u64);
+    push(30, (1 << 30) - 1);
+    bits
+}
+
+/// An initialized generator, the first 160 output bits discarded
+pub(crate) struct Grain {
+    bits: Vec<bool>,
+}
+
+impl Grain {
+    pub(crate) fn new<F: PrimeField>(t: usize, r_f: usize, r_p: usize) -> Self {
+        let bits = seed::<F>(t, r_f, r_p);
+        let mut grain = Self { bits };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let bit = [62, 51, 38, 23, 13, 0]
+            .iter()
+            .fold(false, |acc, i| acc ^ self.bits[*i]);
+        self.bits.remove(0);
+        self.bits.push(bit);
+        bit
+    }
+
+    /// Output bits are filtered in pairs: a one keeps the next bit
+    fn next_bit(&mut self) -> bool {
+        while !self.step() {
+            self.step();
+        }
+        self.step()
+    }
+
+    /// Little-endian `Repr` of the next `F::NUM_BITS`-bit integer, whose
+    /// bits are output most significant first
+    fn next_repr<F: PrimeField>(&mut self) -> F::Repr {
+        let mut repr = F::Repr::default();
+        for i in (0..F::NUM_BITS as usize).rev() {
+            if self.next_bit() {
+                repr.as_mut()[i / 8] |= 1 << (i % 8);
+            }
+        }
+        repr
+    }
+
+    /// Round constants reject integers not below the modulus
+    pub(crate) fn field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            if let Some(value) = Option::from(F::from_repr(self.next_repr::<F>())) {
+                return value;
+            }
+        }
+    }
+
+    /// The MDS points are reduced instead
+    pub(crate) fn field_element_reduced<F: PrimeField>(&mut self) -> F {
+        let radix = F::from(256);
+        self.next_repr::<F>()
+            .as_ref()
+            .iter()
+            .rev()
+            .fold(F::ZERO, |acc, byte| acc * radix + F::from(*byte as u64))
+    }
+}
+
+/// The round constants of every round, in permutation order, and the Cauchy
+/// MDS matrix `1 / (x_i + y_j)` of a width-`T` permutation
+pub(crate) fn parameters<F: PrimeField, const T: usize>(
+    r_f: usize,
+    r_p: usize,
+) -> (Vec<[F; T]>, [[F; T]; T]) {
+    let mut grain = Grain::new::<F>(T, r_f, r_p);
+    let constants = (0..r_f + r_p)
+        .map(|_| std::array::from_fn(|_| grain.field_element()))
+        .collect();
+    let xs: [F; T] = std::array::from_fn(|_| grain.field_element_reduced());
+    let ys: [F; T] = std::array::from_fn(|_| grain.field_element_reduced());
+    let mds = xs.map(|x| ys.map(|y| (x + y).invert().unwrap()));
+    (constants, mds)
+}
@@ -0,0 +1,85 @@
+//! Sponges for the BN254–Grumpkin cycle.
+//!
+//! The base field of each curve is the scalar field of the other: Grumpkin
+//! points have coordinates in the BN254 scalar field `Fr`, and BN254 points
+//! in its base field `Fq`. [`PoseidonHashPrimary`] runs over `Fr`, the field
+//! of circuits on BN254, and [`PoseidonHashSecondary`] over `Fq`, the field
+//! of circuits on Grumpkin.
+//!
+//! Each sponge absorbs the elements and points of both curves:
+//!
+//! - elements of its field natively with [`PoseidonHash::update`], and
+//!   points of the curve with coordinates in that field, Grumpkin for the
+//!   primary sponge, with [`PoseidonHash::absorb_native_point`] as
+//!   `(x, y, is_identity)`;
+//! - elements of the other field as limbs with
+//!   [`PoseidonHash::absorb_base`], and points of the curve the sponge is
+//!   typed with, BN254 for the primary sponge, with
+//!   [`PoseidonHash::absorb_point`].
+//!
+//! halo2curves 0.3.2 has no Grumpkin type, so [`PoseidonHashSecondary`]
+//! takes the curve as a parameter, to be filled with a Grumpkin affine type
+//! whose scalar field is halo2curves' `bn256::Fq`.
+use halo2curves::bn256::{Fq, Fr, G1Affine};
+
+use crate::{
+    cached::CachedSpec, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+};
+
+pub const T: usize = 3;
+pub const RATE: usize = 2;
+pub const R_F: usize = 8;
+pub const R_P: usize = 56;
+
+/// Sponge over the BN254 scalar field
+pub type PoseidonHashPrimary = PoseidonHash<G1Affine, Fr, T, RATE>;
+
+/// Sponge over the BN254 base field, for a Grumpkin affine type `C`
+pub type PoseidonHashSecondary<C> = PoseidonHash<C, Fq, T, RATE>;
+
+/// The spec of [`PoseidonHashPrimary`]
+pub fn primary_spec() -> &'static PrecomputedSpec<Fr, T, RATE> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+/// The spec of [`PoseidonHashSecondary`]
+pub fn secondary_spec() -> &'static PrecomputedSpec<Fq, T, RATE> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+/// A fresh [`PoseidonHashPrimary`]
+pub fn primary() -> PoseidonHashPrimary {
+    PoseidonHashPrimary::new(primary_spec().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::CurveAffine;
+    use halo2curves::group::{ff::Field, prime::PrimeCurveAffine};
+
+    use super::*;
+    use crate::audit::Audit;
+
+    #[test]
+    fn test_specs() {
+        assert!(Audit::new(primary_spec(), 128).passes());
+        assert!(Audit::new(secondary_spec(), 128).passes());
+    }
+
+    #[test]
+    fn test_primary() {
+        let point = G1Affine::generator();
+        let coordinates = point.coordinates().unwrap();
+        let mut hasher = primary();
+        hasher.update(&[Fr::ONE]);
+        hasher.absorb_point(&point);
+
+        let mut expected = PoseidonHashPrimary::new(PrecomputedSpec::new(R_F, R_P));
+        expected.update(&[Fr::ONE]);
+        expected.absorb_base(*coordinates.x());
+        expected.absorb_base(*coordinates.y());
+        expected.update(&[Fr::ZERO]);
+        assert_eq!(hasher.squeeze(), expected.squeeze());
+    }
+}
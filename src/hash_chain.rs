@@ -0,0 +1,263 @@
+//! Iterated Poseidon hash chains `x_{i+1} = H(x_i)`, e.g. for
+//! commit-delay-reveal schemes or simple sequential-work constructions whose
+//! chain is later proven in-circuit.
+use std::marker::PhantomData;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait};
+
+/// A hash chain evaluation that stores every `interval`-th state, so that
+/// segments can be re-verified independently of each other
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Checkpoints<F: PrimeField> {
+    pub interval: usize,
+    /// Total number of steps
+    pub len: usize,
+    /// `states[k] = x_{k * interval}`, starting with the seed
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field_vec"))]
+    pub states: Vec<F>,
+    /// `x_len`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field"))]
+    pub output: F,
+}
+
+impl<F: PrimeField> Checkpoints<F> {
+    /// The first state, `None` for malformed checkpoints without one
+    pub fn seed(&self) -> Option<F> {
+        self.states.first().copied()
+    }
+
+    pub fn num_segments(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether the fields are consistent: a positive interval, a seed and no
+    /// checkpoint at or past the end of a non-empty chain. Checkpoints from
+    /// untrusted input are checked before any arithmetic on them.
+    fn is_well_formed(&self) -> bool {
+        self.interval > 0
+            && !self.states.is_empty()
+            && (self.states.len() - 1)
+                .checked_mul(self.interval)
+                .is_some_and(|last| last < self.len.max(1))
+    }
+
+    /// Start value, number of steps and expected end value of segment
+    /// `index`, `None` past the chain
+    fn segment(&self, index: usize) -> Option<(F, usize, F)> {
+        let start = index.checked_mul(self.interval)?;
+        let steps = self.interval.min(self.len.checked_sub(start)?);
+        let end = self.states.get(index + 1).copied().unwrap_or(self.output);
+        Some((*self.states.get(index)?, steps, end))
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshSerialize for Checkpoints<F> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        crate::encoding::borsh::write_len(self.interval, writer)?;
+        crate::encoding::borsh::write_len(self.len, writer)?;
+        crate::encoding::borsh::write_fields(&self.states, writer)?;
+        crate::encoding::borsh::write_field(&self.output, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshDeserialize for Checkpoints<F> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(Self {
+            interval: crate::encoding::borsh::read_len(reader)?,
+            len: crate::encoding::borsh::read_len(reader)?,
+            states: crate::encoding::borsh::read_fields(reader)?,
+            output: crate::encoding::borsh::read_field(reader)?,
+        })
+    }
+}
+
+pub struct HashChain<C, F, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    spec: PrecomputedSpec<F, T, RATE>,
+    _marker: PhantomData<C>,
+}
+
+impl<C, F, const T: usize, const RATE: usize> HashChain<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        Self {
+            spec,
+            _marker: PhantomData,
+        }
+    }
+
+    /// A single step of the chain, `H(x)`
+    pub fn step(&self, x: F) -> F {
+        let mut hasher = PoseidonHash::<C, F, T, RATE>::new(self.spec.clone());
+        hasher.update(&[x]);
+        hasher.squeeze()
+    }
+
+    /// Returns `x_n`, the result of hashing `seed` `n` times
+    pub fn hash_chain(&self, seed: F, n: usize) -> F {
+        (0..n).fold(seed, |x, _| self.step(x))
+    }
+
+    /// Same as [`Self::hash_chain`], additionally recording every
+    /// `interval`-th state
+    pub fn hash_chain_with_checkpoints(
+        &self,
+        seed: F,
+        n: usize,
+        interval: usize,
+    ) -> Checkpoints<F> {
+        assert!(interval > 0);
+        let mut states = vec![seed];
+        let mut x = seed;
+        for i in 1..=n {
+            x = self.step(x);
+            if i % interval == 0 && i < n {
+                states.push(x);
+            }
+        }
+        Checkpoints {
+            interval,
+            len: n,
+            states,
+            output: x,
+        }
+    }
+
+    pub fn verify(&self, seed: F, n: usize, output: F) -> bool {
+        self.hash_chain(seed, n) == output
+    }
+
+    /// Re-verifies a single segment between two consecutive checkpoints
+    pub fn verify_segment(&self, checkpoints: &Checkpoints<F>, index: usize) -> bool {
+        if !checkpoints.is_well_formed() {
+            return false;
+        }
+        match checkpoints.segment(index) {
+            Some((start, steps, end)) => self.hash_chain(start, steps) == end,
+            None => false,
+        }
+    }
+
+    /// Re-verifies every segment; equivalent to recomputing the whole chain
+    pub fn verify_checkpoints(&self, checkpoints: &Checkpoints<F>) -> bool {
+        if !checkpoints.is_well_formed() {
+            return false;
+        }
+        let expected_segments = if checkpoints.len == 0 {
+            1
+        } else {
+            checkpoints.len.div_ceil(checkpoints.interval)
+        };
+        checkpoints.num_segments() == expected_segments
+            && (0..checkpoints.num_segments()).all(|i| self.verify_segment(checkpoints, i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+
+    const T: usize = 4;
+    const RATE: usize = 3;
+    const R_F: usize = 8;
+    const R_P: usize = 56;
+
+    fn chain() -> HashChain<G1Affine, Fr, T, RATE> {
+        HashChain::new(PrecomputedSpec::new(R_F, R_P))
+    }
+
+    #[test]
+    fn test_hash_chain() {
+        let chain = chain();
+        let seed = Fr::from(42);
+        let expected = chain.step(chain.step(chain.step(seed)));
+        assert_eq!(chain.hash_chain(seed, 3), expected);
+        assert_eq!(chain.hash_chain(seed, 0), seed);
+        assert!(chain.verify(seed, 3, expected));
+        assert!(!chain.verify(seed, 2, expected));
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let chain = chain();
+        let seed = Fr::from(7);
+        let checkpoints = chain.hash_chain_with_checkpoints(seed, 10, 4);
+        assert_eq!(checkpoints.seed(), Some(seed));
+        assert_eq!(checkpoints.num_segments(), 3);
+        assert_eq!(checkpoints.states[1], chain.hash_chain(seed, 4));
+        assert_eq!(checkpoints.states[2], chain.hash_chain(seed, 8));
+        assert_eq!(checkpoints.output, chain.hash_chain(seed, 10));
+        assert!(chain.verify_checkpoints(&checkpoints));
+
+        let mut tampered = checkpoints.clone();
+        tampered.states[2] = Fr::from(1);
+        assert!(chain.verify_segment(&tampered, 0));
+        assert!(!chain.verify_segment(&tampered, 1));
+        assert!(!chain.verify_checkpoints(&tampered));
+    }
+
+    /// Inconsistent checkpoints, as deserialized from untrusted input, are
+    /// rejected without panicking
+    #[test]
+    fn test_rejects_malformed_checkpoints() {
+        let chain = chain();
+        let checkpoints = chain.hash_chain_with_checkpoints(Fr::from(7), 10, 4);
+        assert!(chain.verify_checkpoints(&chain.hash_chain_with_checkpoints(Fr::from(7), 0, 4)));
+
+        let zero_interval = Checkpoints {
+            interval: 0,
+            ..checkpoints.clone()
+        };
+        assert!(!chain.verify_checkpoints(&zero_interval));
+        assert!(!chain.verify_segment(&zero_interval, 0));
+
+        // the last checkpoint would start past the end of the chain
+        let short = Checkpoints {
+            len: 7,
+            ..checkpoints.clone()
+        };
+        assert!(!chain.verify_checkpoints(&short));
+        assert!(!chain.verify_segment(&short, 2));
+
+        let huge = Checkpoints {
+            interval: usize::MAX,
+            ..checkpoints.clone()
+        };
+        assert!(!chain.verify_segment(&huge, 2));
+
+        let empty = Checkpoints {
+            states: Vec::new(),
+            ..checkpoints
+        };
+        assert!(!chain.verify_checkpoints(&empty));
+        assert_eq!(empty.seed(), None);
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_checkpoints_borsh() {
+        let checkpoints = chain().hash_chain_with_checkpoints(Fr::from(7), 5, 2);
+        let bytes = borsh::to_vec(&checkpoints).unwrap();
+        assert_eq!(bytes.len(), 8 + 8 + 4 + 3 * 32 + 32);
+        assert_eq!(
+            borsh::from_slice::<Checkpoints<Fr>>(&bytes).unwrap(),
+            checkpoints
+        );
+    }
+}
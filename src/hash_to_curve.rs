@@ -0,0 +1,543 @@
+//! Hashing to a curve `C` with the sponge as the message hash.
+//!
+//! [`hash_to_curve`] follows the random-oracle construction of RFC 9380:
+//! two field elements `u0, u1` of the base field, mapped to the curve and
+//! added. The elements are the first two squeezes of a sponge of width 3
+//! with the round numbers of [`round_numbers`] at 128 bits over the base
+//! field, from the initial state of [`PoseidonHash`], that absorbed the
+//! label `poseidon-hash-to-curve` packed with [`pack_bytes`], then the
+//! inputs. The map is the constant-time Shallue–van de Woestijne map of
+//! RFC 9380, section 6.6.1, which takes any short Weierstrass curve,
+//! including the `a = 0` curves of bn256, Pasta and secp256k1 that the
+//! simplified SWU map does not; `Z` is the one of `find_z_svdw`. Curves of
+//! this crate have prime order, so there is no cofactor to clear.
+//!
+//! [`HashToCurveChip`] assigns the same point in a circuit over the base
+//! field of `C`, such as bn256 points in a circuit over its base field or
+//! Pallas points in a circuit over Vesta's scalar field. The sign of the
+//! ordinate is the parity of its canonical little-endian representation,
+//! so the chip decomposes `y` and `u` into bits below the modulus, about
+//! 900 rows each. The inversion and the final addition are incomplete, so
+//! the chip fails to prove for the few inputs that hit them, which is
+//! negligible for digests.
+//!
+//! [`round_numbers`]: crate::rounds::round_numbers
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use std::marker::PhantomData;
+
+use halo2_proofs::{arithmetic::CurveAffine, circuit::Value, plonk::Error};
+use halo2curves::group::{
+    ff::{Field, FromUniformBytes, PrimeField},
+    Curve,
+};
+
+use crate::{
+    cached::CachedSpec,
+    encoding::pack_bytes,
+    main_gate::{AssignedValue, MainGate, MainGateConfig, RegionCtx, WrapValue},
+    poseidon_circuit::PoseidonChip,
+    poseidon_hash::{initial_state, State},
+    precomputed::PrecomputedSpec,
+    rounds::round_numbers,
+};
+
+const LABEL: &[u8] = b"poseidon-hash-to-curve";
+
+fn spec<F: PrimeField + FromUniformBytes<64>>() -> &'static PrecomputedSpec<F, 3, 2> {
+    let (r_f, r_p) = round_numbers(F::NUM_BITS, 3, 128);
+    PrecomputedSpec::cached(r_f, r_p)
+}
+
+/// `x^3 + a x + b`
+fn curve_equation<C: CurveAffine>(x: C::Base) -> C::Base {
+    x.square() * x + C::a() * x + C::b()
+}
+
+fn is_square<F: Field>(value: F) -> bool {
+    value.sqrt().is_some().into()
+}
+
+/// The constants of the map for `C`, named as in RFC 9380
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Svdw<F> {
+    z: F,
+    c1: F,
+    c2: F,
+    c3: F,
+    c4: F,
+}
+
+impl<F: PrimeField> Svdw<F> {
+    fn new<C: CurveAffine<Base = F>>() -> Self {
+        let two = F::from(2);
+        let h = |z: F| z.square() * F::from(3) + C::a() * F::from(4);
+        // `find_z_svdw`: the first of 1, -1, 2, -2, .. that fits
+        let z = (1..)
+            .flat_map(|i| [F::from(i), -F::from(i)])
+            .find(|z| {
+                let gz = curve_equation::<C>(*z);
+                let t = -h(*z) * (gz * F::from(4)).invert().unwrap_or(F::ZERO);
+                !bool::from(t.is_zero())
+                    && is_square(t)
+                    && (is_square(gz)
+                        || is_square(curve_equation::<C>(-*z * two.invert().unwrap())))
+            })
+            .unwrap();
+        let gz = curve_equation::<C>(z);
+        let c3 = (-gz * h(z)).sqrt().unwrap();
+        Self {
+            z,
+            c1: gz,
+            c2: -z * two.invert().unwrap(),
+            c3: if bool::from(c3.is_odd()) { -c3 } else { c3 },
+            c4: -gz * F::from(4) * h(z).invert().unwrap(),
+        }
+    }
+
+    /// The ordinate of `x` on `C` whose parity is the one of `u`
+    fn ordinate<C: CurveAffine<Base = F>>(x: F, u: F) -> F {
+        let y = curve_equation::<C>(x).sqrt().unwrap();
+        // the even root, negated if `u` is odd
+        let y = F::conditional_select(&y, &-y, y.is_odd());
+        F::conditional_select(&y, &-y, u.is_odd())
+    }
+
+    /// `map_to_curve_svdw` of `u`, straight-line as in RFC 9380, appendix F.1
+    fn map<C: CurveAffine<Base = F>>(&self, u: F) -> C {
+        let tv1 = u.square() * self.c1;
+        let tv2 = F::ONE + tv1;
+        let tv1 = F::ONE - tv1;
+        let tv3 = (tv1 * tv2).invert().unwrap_or(F::ZERO);
+        let tv4 = u * tv1 * tv3 * self.c3;
+        let x1 = self.c2 - tv4;
+        let e1 = curve_equation::<C>(x1).sqrt().is_some();
+        let x2 = self.c2 + tv4;
+        let e2 = curve_equation::<C>(x2).sqrt().is_some() & !e1;
+        let x3 = (tv2.square() * tv3).square() * self.c4 + self.z;
+        let x = F::conditional_select(&x3, &x1, e1);
+        let x = F::conditional_select(&x, &x2, e2);
+        C::from_xy(x, Self::ordinate::<C>(x, u)).unwrap()
+    }
+}
+
+/// The two field elements hashed from `inputs`
+fn hash_to_field<F: PrimeField + FromUniformBytes<64>>(inputs: &[F]) -> [F; 2] {
+    let mut elements = pack_bytes(LABEL);
+    elements.extend_from_slice(inputs);
+    let mut state = State::<F, 3, 2>::new(initial_state());
+    state.absorb(spec(), &elements);
+    let u0 = state.inner[1];
+    state.absorb(spec(), &[]);
+    [u0, state.inner[1]]
+}
+
+/// The point of `C` hashed from `inputs`, whose discrete logarithm is
+/// unknown
+pub fn hash_to_curve<C, F>(inputs: &[F]) -> C
+where
+    C: CurveAffine<Base = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let svdw = Svdw::new::<C>();
+    let [u0, u1] = hash_to_field(inputs);
+    (svdw.map::<C>(u0) + svdw.map::<C>(u1)).to_affine()
+}
+
+/// [`hash_to_curve`] over the main gate of width 3, in a circuit over the
+/// base field of `C`
+pub struct HashToCurveChip<C: CurveAffine<Base = F>, F: PrimeField> {
+    main_gate: MainGate<F, 3>,
+    poseidon: PoseidonChip<F, 3, 2>,
+    svdw: Svdw<F>,
+    /// The first non-square of 2, 3, .., for proofs of non-squareness
+    non_square: F,
+    _marker: PhantomData<C>,
+}
+
+impl<C, F> HashToCurveChip<C, F>
+where
+    C: CurveAffine<Base = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(config: MainGateConfig<3>) -> Self {
+        Self {
+            main_gate: MainGate::new(config.clone()),
+            poseidon: PoseidonChip::new(config, spec().clone()),
+            svdw: Svdw::new::<C>(),
+            non_square: (2..).map(F::from).find(|n| !is_square(*n)).unwrap(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// A row `q_m * c_0 * c_1 + sum_i(q_1[i] * c_i) + constant` over
+    /// `cells`, assigned to a new cell, or constrained to zero if `output`
+    /// is false
+    fn gate(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        q_m: F,
+        cells: &[&AssignedValue<F>],
+        q_1: &[F],
+        constant: F,
+        output: bool,
+    ) -> Result<AssignedValue<F>, Error> {
+        let values = cells.iter().map(|c| c.value().copied()).collect::<Vec<_>>();
+        let mut value = Value::known(constant);
+        if q_m != F::ZERO {
+            value = value + values[0] * values[1] * Value::known(q_m);
+        }
+        for (v, q) in values.iter().zip(q_1) {
+            value = value + *v * Value::known(*q);
+        }
+        let out = match output {
+            true => (-F::ONE, value),
+            false => (F::ZERO, Value::known(F::ZERO)),
+        };
+        let state = cells.iter().map(|c| WrapValue::from(*c)).collect();
+        self.main_gate.apply(
+            ctx,
+            (Some(q_1.to_vec()), Some(q_m), Some(state)),
+            Some(constant),
+            (out.0, out.1.into()),
+        )
+    }
+
+    /// A cell holding `value`, free until constrained by other rows
+    fn witness(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: Value<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        self.main_gate
+            .apply(ctx, (None, None, None), None, (F::ZERO, value.into()))
+    }
+
+    /// A cell constrained to be 0 or 1
+    fn bit(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: Value<bool>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let bit = self.witness(ctx, value.map(|b| F::from(b as u64)))?;
+        self.gate(ctx, F::ONE, &[&bit, &bit], &[-F::ONE], F::ZERO, false)?;
+        Ok(bit)
+    }
+
+    /// `curve_equation` of `x`
+    fn curve_equation(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        x: &AssignedValue<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let square = self.gate(ctx, F::ONE, &[x, x], &[], F::ZERO, true)?;
+        self.gate(ctx, F::ONE, &[&square, x], &[F::ZERO, C::a()], C::b(), true)
+    }
+
+    /// A bit set if `value` is a square: with it a root `r` of
+    /// `value * (bit + (1 - bit) * non_square)`, which exists for a set bit
+    /// only if `value` is a square and for an unset one only if it is not
+    fn is_square(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: &AssignedValue<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let square = value.value().map(|v| is_square(*v));
+        let bit = self.bit(ctx, square)?;
+        let masked = self.gate(ctx, F::ONE, &[value, &bit], &[], F::ZERO, true)?;
+        let n = self.non_square;
+        let scaled = self.gate(
+            ctx,
+            F::ZERO,
+            &[value, &masked],
+            &[n, F::ONE - n],
+            F::ZERO,
+            true,
+        )?;
+        let root = scaled.value().map(|v| v.sqrt().unwrap());
+        let root = self.witness(ctx, root)?;
+        self.gate(
+            ctx,
+            F::ONE,
+            &[&root, &root, &scaled],
+            &[F::ZERO, F::ZERO, -F::ONE],
+            F::ZERO,
+            false,
+        )?;
+        Ok(bit)
+    }
+
+    /// The parity of the canonical representation of `value`, from its
+    /// little-endian bits constrained to sum to `value` and to be at most
+    /// `p - 1`
+    fn parity(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: &AssignedValue<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let bit_of = |repr: &F::Repr, i: usize| (repr.as_ref()[i / 8] >> (i % 8)) & 1 == 1;
+        let n = F::NUM_BITS as usize;
+        let bits = (0..n)
+            .map(|i| self.bit(ctx, value.value().map(|v| bit_of(&v.to_repr(), i))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut sum = bits[0].clone();
+        let mut power = F::ONE;
+        for pair in bits[1..].chunks(2) {
+            let mut cells = vec![&sum];
+            let mut q_1 = vec![F::ONE];
+            for bit in pair {
+                power = power.double();
+                cells.push(bit);
+                q_1.push(power);
+            }
+            sum = self.gate(ctx, F::ZERO, &cells, &q_1, F::ZERO, true)?;
+        }
+        ctx.constrain_equal(sum.cell(), value.cell())?;
+
+        // from the top, `equal` is set while the bits match those of
+        // `p - 1`; a set bit where `p - 1` has none must not follow
+        let max = (-F::ONE).to_repr();
+        let mut equal = self.main_gate.assign_constant(ctx, F::ONE)?;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            if bit_of(&max, i) {
+                equal = self.gate(ctx, F::ONE, &[&equal, bit], &[], F::ZERO, true)?;
+            } else {
+                self.gate(ctx, F::ONE, &[&equal, bit], &[], F::ZERO, false)?;
+            }
+        }
+        Ok(bits[0].clone())
+    }
+
+    /// The map of the assigned `u`, as affine coordinates
+    fn map(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        u: &AssignedValue<F>,
+    ) -> Result<(AssignedValue<F>, AssignedValue<F>), Error> {
+        let Svdw { z, c1, c2, c3, c4 } = self.svdw;
+        let (zero, one) = (F::ZERO, F::ONE);
+        let tv1 = self.gate(ctx, c1, &[u, u], &[], zero, true)?;
+        // `(1 - tv1) * (1 + tv1)` and its inverse
+        let tv3 = self.gate(ctx, -one, &[&tv1, &tv1], &[], one, true)?;
+        let inverse = tv3.value().map(|v| v.invert().unwrap_or(F::ZERO));
+        let inverse = self.witness(ctx, inverse)?;
+        self.gate(ctx, one, &[&tv3, &inverse], &[], -one, false)?;
+        let tv4 = self.gate(ctx, -one, &[u, &tv1], &[one], zero, true)?;
+        let tv4 = self.gate(ctx, c3, &[&tv4, &inverse], &[], zero, true)?;
+
+        let x1 = self.gate(ctx, zero, &[&tv4], &[-one], c2, true)?;
+        let x2 = self.gate(ctx, zero, &[&tv4], &[one], c2, true)?;
+        let gx1 = self.curve_equation(ctx, &x1)?;
+        let gx2 = self.curve_equation(ctx, &x2)?;
+        let e1 = self.is_square(ctx, &gx1)?;
+        let s2 = self.is_square(ctx, &gx2)?;
+        let e2 = self.gate(ctx, -one, &[&s2, &e1], &[one], zero, true)?;
+        let tv2 = self.gate(ctx, one, &[&tv1, &tv1], &[F::from(2)], one, true)?;
+        let x3 = self.gate(ctx, one, &[&tv2, &inverse], &[], zero, true)?;
+        let x3 = self.gate(ctx, c4, &[&x3, &x3], &[], z, true)?;
+
+        // `x3 + e1 * (x1 - x3) + e2 * (x2 - x3)`, as `e1` and `e2` are
+        // never both set
+        let d1 = self.gate(ctx, zero, &[&x1, &x3], &[one, -one], zero, true)?;
+        let d2 = self.gate(ctx, zero, &[&x2, &x3], &[one, -one], zero, true)?;
+        let x = self.gate(ctx, one, &[&e1, &d1, &x3], &[zero, zero, one], zero, true)?;
+        let x = self.gate(ctx, one, &[&e2, &d2, &x], &[zero, zero, one], zero, true)?;
+
+        let gx = self.curve_equation(ctx, &x)?;
+        let y = x
+            .value()
+            .zip(u.value())
+            .map(|(x, u)| Svdw::<F>::ordinate::<C>(*x, *u));
+        let y = self.witness(ctx, y)?;
+        self.gate(ctx, one, &[&y, &y, &gx], &[zero, zero, -one], zero, false)?;
+        let (sign_u, sign_y) = (self.parity(ctx, u)?, self.parity(ctx, &y)?);
+        ctx.constrain_equal(sign_u.cell(), sign_y.cell())?;
+        Ok((x, y))
+    }
+
+    /// The affine sum of two points with distinct abscissas
+    fn add(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        (x0, y0): &(AssignedValue<F>, AssignedValue<F>),
+        (x1, y1): &(AssignedValue<F>, AssignedValue<F>),
+    ) -> Result<(AssignedValue<F>, AssignedValue<F>), Error> {
+        let (zero, one) = (F::ZERO, F::ONE);
+        let dx = self.gate(ctx, zero, &[x1, x0], &[one, -one], zero, true)?;
+        let dy = self.gate(ctx, zero, &[y1, y0], &[one, -one], zero, true)?;
+        let lambda = dy
+            .value()
+            .zip(dx.value())
+            .map(|(dy, dx)| *dy * dx.invert().unwrap_or(F::ZERO));
+        let lambda = self.witness(ctx, lambda)?;
+        self.gate(
+            ctx,
+            one,
+            &[&lambda, &dx, &dy],
+            &[zero, zero, -one],
+            zero,
+            false,
+        )?;
+        let sum = self.gate(ctx, zero, &[x0, x1], &[one, one], zero, true)?;
+        let x = self.gate(
+            ctx,
+            one,
+            &[&lambda, &lambda, &sum],
+            &[zero, zero, -one],
+            zero,
+            true,
+        )?;
+        let dx = self.gate(ctx, zero, &[x0, &x], &[one, -one], zero, true)?;
+        let y = self.gate(
+            ctx,
+            one,
+            &[&lambda, &dx, y0],
+            &[zero, zero, -one],
+            zero,
+            true,
+        )?;
+        Ok((x, y))
+    }
+
+    /// The affine coordinates of [`hash_to_curve`] of the assigned `inputs`
+    pub fn hash_to_curve(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        inputs: &[AssignedValue<F>],
+    ) -> Result<(AssignedValue<F>, AssignedValue<F>), Error> {
+        let mut elements = Vec::new();
+        for word in pack_bytes::<F>(LABEL) {
+            elements.push(self.main_gate.assign_constant(ctx, word)?);
+        }
+        elements.extend_from_slice(inputs);
+
+        // the initial state, the capacity and the padding are fixed
+        // constants, as in a squeeze of the chip
+        let mut state = None;
+        for chunk in elements.chunks(2) {
+            let chunk = chunk.iter().map(WrapValue::from).collect();
+            state = Some(self.poseidon.permute(ctx, chunk, state.as_ref())?);
+        }
+        let mut state = state.expect("the label is absorbed");
+        if elements.len() % 2 == 0 {
+            state = self.poseidon.permutation(ctx, Vec::new(), &state)?;
+        }
+        let u0 = state[1].clone();
+        state = self.poseidon.permutation(ctx, Vec::new(), &state)?;
+        let u1 = state[1].clone();
+
+        let q0 = self.map(ctx, &u0)?;
+        let q1 = self.map(ctx, &u1)?;
+        self.add(ctx, &q0, &q1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::{
+        bn256::{Fq, G1Affine},
+        pasta::{EpAffine, Fp},
+        secp256k1::{Fp as SecpFp, Secp256k1Affine},
+    };
+
+    use super::*;
+    use crate::test_fixture::FixtureConfig;
+
+    #[test]
+    fn test_constants() {
+        // the `Z` of the BN254G1_XMD:SHA-256_SVDW_RO_ suite of RFC 9380
+        assert_eq!(Svdw::new::<G1Affine>().z, Fq::ONE);
+        let svdw = Svdw::new::<EpAffine>();
+        assert!(!bool::from(svdw.c3.is_odd()));
+        assert_eq!(svdw.c3.square(), -svdw.c1 * (svdw.z.square() * Fp::from(3)));
+    }
+
+    #[test]
+    fn test_map() {
+        let svdw = Svdw::new::<G1Affine>();
+        for u in [
+            Fq::ZERO,
+            Fq::ONE,
+            -Fq::ONE,
+            Fq::from(12345),
+            Fq::from(u64::MAX).square(),
+        ] {
+            let point: G1Affine = svdw.map(u);
+            assert!(bool::from(point.is_on_curve()));
+            let y = *point.coordinates().unwrap().y();
+            assert_eq!(bool::from(y.is_odd()), bool::from(u.is_odd()));
+        }
+    }
+
+    #[test]
+    fn test_hash_to_curve() {
+        let point: G1Affine = hash_to_curve(&[Fq::from(1), Fq::from(2)]);
+        assert!(bool::from(point.is_on_curve()));
+        assert_eq!(point, hash_to_curve(&[Fq::from(1), Fq::from(2)]));
+        assert_ne!(point, hash_to_curve(&[Fq::from(1)]));
+        assert_ne!(point, hash_to_curve(&[Fq::from(1), Fq::from(2), Fq::ZERO]));
+
+        let pallas: EpAffine = hash_to_curve(&[Fp::from(1)]);
+        assert!(bool::from(pallas.is_on_curve()));
+        let secp: Secp256k1Affine = hash_to_curve(&[SecpFp::from(1)]);
+        assert!(bool::from(secp.is_on_curve()));
+    }
+
+    struct HashToCurveCircuit {
+        inputs: Vec<Fq>,
+    }
+
+    impl Circuit<Fq> for HashToCurveCircuit {
+        type Config = FixtureConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                inputs: vec![Fq::ZERO; self.inputs.len()],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fq>) -> Self::Config {
+            FixtureConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fq>,
+        ) -> Result<(), Error> {
+            let chip = HashToCurveChip::<G1Affine, _>::new(config.main_gate.clone());
+            let (x, y) = layouter.assign_region(
+                || "hash to curve",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let inputs = config.witness(ctx, self.inputs.iter().copied())?;
+                    chip.hash_to_curve(ctx, &inputs)
+                },
+            )?;
+            layouter.constrain_instance(x.cell(), config.instance, 0)?;
+            layouter.constrain_instance(y.cell(), config.instance, 1)
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        for inputs in [vec![Fq::from(1), Fq::from(2)], vec![Fq::from(3)]] {
+            let point: G1Affine = hash_to_curve(&inputs);
+            let coordinates = point.coordinates().unwrap();
+            let expected = vec![*coordinates.x(), *coordinates.y()];
+            let circuit = HashToCurveCircuit { inputs };
+            let prover = MockProver::run(13, &circuit, vec![expected.clone()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let negated = vec![expected[0], -expected[1]];
+            let prover = MockProver::run(13, &circuit, vec![negated]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
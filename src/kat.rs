@@ -0,0 +1,243 @@
+//! Known-answer vectors for the native hash.
+//!
+//! [`generate`] hashes a fixed set of inputs for every curve and width from 2
+//! to 8, absorbed both as field elements and as byte strings, with the round
+//! numbers of [`round_numbers`] at 128-bit security. The vectors of each curve
+//! are committed as `kat/v{VERSION}/<curve>.json` and [`check`]ed against the
+//! current implementation by the tests, so any change to the sponge, the
+//! constants or the byte packing fails CI.
+//!
+//! Regenerate the files with `poseidon kat --out kat` only for an intended
+//! change of outputs, and bump [`VERSION`] when doing so: downstream users
+//! that pinned the previous files keep a consistent set.
+use std::{fs, io, path::Path};
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::{
+    bn256::{Fr, G1Affine},
+    group::ff::{FromUniformBytes, PrimeField},
+    pasta::{EpAffine, EqAffine, Fp, Fq},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+    rounds::round_numbers,
+};
+
+/// Version of the vector files, part of their path
+pub const VERSION: u32 = 1;
+
+/// Curves whose scalar fields have vectors: bn256, Pallas and Vesta
+pub const CURVES: [&str; 3] = ["bn256", "pallas", "vesta"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KatFile {
+    pub version: u32,
+    pub curve: String,
+    pub vectors: Vec<Vector>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vector {
+    pub t: usize,
+    pub r_f: usize,
+    pub r_p: usize,
+    #[serde(flatten)]
+    pub input: Input,
+    /// Hex digest
+    pub output: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Input {
+    /// Hex field elements absorbed with [`PoseidonHash::update`]
+    Elements { inputs: Vec<String> },
+    /// `0x`-prefixed hex byte string absorbed with
+    /// [`PoseidonHash::update_bytes`]
+    Bytes { bytes: String },
+}
+
+fn hash<C, F, const T: usize, const RATE: usize>(
+    r_f: usize,
+    r_p: usize,
+    input: &Input,
+) -> Option<String>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut hasher = PoseidonHash::<C, F, T, RATE>::new(PrecomputedSpec::new(r_f, r_p));
+    match input {
+        Input::Elements { inputs } => {
+            let inputs = inputs
+                .iter()
+                .map(|input| encoding::from_hex(input))
+                .collect::<Option<Vec<F>>>()?;
+            hasher.update(&inputs);
+        }
+        Input::Bytes { bytes } => hasher.update_bytes(&encoding::bytes_from_hex(bytes)?),
+    }
+    Some(encoding::to_hex(&hasher.squeeze()))
+}
+
+/// Inputs hashed at width `T`: `0, 1, ..` of every length up to two full
+/// blocks plus one, a block plus one of `-1`, and bytes `0, 1, ..` of lengths
+/// around the element packing boundary
+fn inputs<C, F, const T: usize, const RATE: usize>() -> Vec<Input>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let elements = |values: Vec<F>| Input::Elements {
+        inputs: values.iter().map(encoding::to_hex).collect(),
+    };
+    let mut inputs = (0..=2 * RATE + 1)
+        .map(|len| elements((0..len as u64).map(F::from).collect()))
+        .collect::<Vec<_>>();
+    inputs.push(elements(vec![-F::ONE; RATE + 1]));
+
+    let chunk = PoseidonHash::<C, F, T, RATE>::BYTES_PER_ELEMENT;
+    for len in [0, 1, chunk - 1, chunk, chunk + 1, 2 * chunk + 5] {
+        inputs.push(Input::Bytes {
+            bytes: encoding::bytes_to_hex(&(0..len).map(|i| i as u8).collect::<Vec<_>>()),
+        });
+    }
+    inputs
+}
+
+fn vectors<C, F, const T: usize, const RATE: usize>() -> Vec<Vector>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let (r_f, r_p) = round_numbers(F::NUM_BITS, T, 128);
+    inputs::<C, F, T, RATE>()
+        .into_iter()
+        .map(|input| Vector {
+            t: T,
+            r_f,
+            r_p,
+            output: hash::<C, F, T, RATE>(r_f, r_p, &input).unwrap(),
+            input,
+        })
+        .collect()
+}
+
+/// Instantiates `$f::<$c, $fld, T, T - 1>` for `T` from 2 to 8, with `$t`
+/// selecting one width if given
+macro_rules! widths {
+    ($f:ident, $c:ty, $fld:ty) => {
+        [
+            $f::<$c, $fld, 2, 1>(),
+            $f::<$c, $fld, 3, 2>(),
+            $f::<$c, $fld, 4, 3>(),
+            $f::<$c, $fld, 5, 4>(),
+            $f::<$c, $fld, 6, 5>(),
+            $f::<$c, $fld, 7, 6>(),
+            $f::<$c, $fld, 8, 7>(),
+        ]
+        .concat()
+    };
+    ($f:ident, $c:ty, $fld:ty, $t:expr $(, $arg:expr)*) => {
+        match $t {
+            2 => $f::<$c, $fld, 2, 1>($($arg),*),
+            3 => $f::<$c, $fld, 3, 2>($($arg),*),
+            4 => $f::<$c, $fld, 4, 3>($($arg),*),
+            5 => $f::<$c, $fld, 5, 4>($($arg),*),
+            6 => $f::<$c, $fld, 6, 5>($($arg),*),
+            7 => $f::<$c, $fld, 7, 6>($($arg),*),
+            8 => $f::<$c, $fld, 8, 7>($($arg),*),
+            _ => None,
+        }
+    };
+}
+
+/// Vectors of `curve`, one of [`CURVES`]
+///
+/// # Panics
+///
+/// If `curve` is not one of [`CURVES`]
+pub fn generate(curve: &str) -> KatFile {
+    let vectors = match curve {
+        "bn256" => widths!(vectors, G1Affine, Fr),
+        "pallas" => widths!(vectors, EpAffine, Fq),
+        "vesta" => widths!(vectors, EqAffine, Fp),
+        _ => panic!("unknown curve {}", curve),
+    };
+    KatFile {
+        version: VERSION,
+        curve: curve.to_string(),
+        vectors,
+    }
+}
+
+/// Writes the vectors of every curve to `dir/v{VERSION}/<curve>.json`
+pub fn write_all(dir: &Path) -> io::Result<()> {
+    let dir = dir.join(format!("v{}", VERSION));
+    fs::create_dir_all(&dir)?;
+    for curve in CURVES {
+        let json = serde_json::to_string_pretty(&generate(curve))?;
+        fs::write(dir.join(format!("{}.json", curve)), json + "\n")?;
+    }
+    Ok(())
+}
+
+/// Recomputes the vectors of `file` and returns those whose output drifted,
+/// or an error if `file` is for another version or malformed
+pub fn check(file: &KatFile) -> Result<Vec<&Vector>, String> {
+    if file.version != VERSION {
+        return Err(format!(
+            "vectors are version {}, expected {}",
+            file.version, VERSION
+        ));
+    }
+    let mut drifted = Vec::new();
+    for vector in &file.vectors {
+        let (t, r_f, r_p, input) = (vector.t, vector.r_f, vector.r_p, &vector.input);
+        let output = match file.curve.as_str() {
+            "bn256" => widths!(hash, G1Affine, Fr, t, r_f, r_p, input),
+            "pallas" => widths!(hash, EpAffine, Fq, t, r_f, r_p, input),
+            "vesta" => widths!(hash, EqAffine, Fp, t, r_f, r_p, input),
+            curve => return Err(format!("unknown curve {}", curve)),
+        }
+        .ok_or_else(|| format!("malformed vector {:?}", vector))?;
+        if output != vector.output {
+            drifted.push(vector);
+        }
+    }
+    Ok(drifted)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::group::ff::Field;
+
+    use super::*;
+
+    #[test]
+    fn test_committed_vectors() {
+        let files = [
+            include_str!("../kat/v1/bn256.json"),
+            include_str!("../kat/v1/pallas.json"),
+            include_str!("../kat/v1/vesta.json"),
+        ];
+        for (curve, json) in CURVES.iter().zip(files) {
+            let file: KatFile = serde_json::from_str(json).unwrap();
+            assert_eq!(file.curve, *curve);
+            assert_eq!(check(&file).unwrap(), Vec::<&Vector>::new());
+            assert_eq!(file, generate(curve));
+        }
+    }
+
+    #[test]
+    fn test_check_reports_drift() {
+        let mut file = generate("bn256");
+        file.vectors[3].output = encoding::to_hex(&Fr::ONE);
+        assert_eq!(check(&file).unwrap(), vec![&file.vectors[3]]);
+
+        file.version += 1;
+        assert!(check(&file).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Key derivation with the extract-then-expand pattern of HKDF, over the
+//! sponge.
+//!
+//! [`extract`] compresses input key material into one pseudorandom element,
+//! and [`expand`] derives any number of elements from it and a context
+//! string `info`. Every digest is the one of a sponge of width 3 with the
+//! round numbers of [`round_numbers`] at 128 bits, from the initial state of
+//! [`PoseidonHash`], over:
+//!
+//! - extract: the label `poseidon-kdf-extract`, `ikm.len()`, then `ikm`
+//! - output block `i`: the label `poseidon-kdf-expand`, the pseudorandom
+//!   element, `i`, then `info`
+//!
+//! Labels and `info` are packed with [`pack_bytes`], so a block is the
+//! digest of a [`PoseidonHash`] that absorbed the same sequence through
+//! [`PoseidonHash::update_bytes`] and [`PoseidonHash::update`], and a
+//! circuit recomputes it with the same absorptions in
+//! [`PoseidonChip`](crate::poseidon_circuit::PoseidonChip).
+//!
+//! [`round_numbers`]: crate::rounds::round_numbers
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+//! [`PoseidonHash::update_bytes`]: crate::poseidon_hash::PoseidonHash::update_bytes
+//! [`PoseidonHash::update`]: crate::poseidon_hash::PoseidonHash::update
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{encoding::pack_bytes, poseidon_hash::digest_128};
+
+const EXTRACT: &[u8] = b"poseidon-kdf-extract";
+const EXPAND: &[u8] = b"poseidon-kdf-expand";
+
+/// The pseudorandom element of the input key material `ikm`
+pub fn extract<F: PrimeField + FromUniformBytes<64>>(ikm: &[F]) -> F {
+    let mut elements = pack_bytes(EXTRACT);
+    elements.push(F::from(ikm.len() as u64));
+    elements.extend_from_slice(ikm);
+    digest_128(&elements)
+}
+
+/// `n` elements derived from the pseudorandom element `prk` for the context
+/// `info`, one block each
+pub fn expand_prk<F: PrimeField + FromUniformBytes<64>>(prk: F, info: &[u8], n: usize) -> Vec<F> {
+    let label = pack_bytes(EXPAND);
+    let info = pack_bytes(info);
+    (0..n as u64)
+        .map(|block| {
+            let mut elements = label.clone();
+            elements.extend([prk, F::from(block)]);
+            elements.extend_from_slice(&info);
+            digest_128(&elements)
+        })
+        .collect()
+}
+
+/// `n` elements derived from the input key material `ikm` for the context
+/// `info`: [`expand_prk`] of [`extract`]
+pub fn expand<F: PrimeField + FromUniformBytes<64>>(ikm: &[F], info: &[u8], n: usize) -> Vec<F> {
+    expand_prk(extract(ikm), info, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait};
+
+    #[test]
+    fn test_matches_hasher() {
+        let ikm = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let keys = expand(&ikm, b"blinders", 3);
+
+        let hasher = || PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 56));
+        let mut extract = hasher();
+        extract.update_bytes(EXTRACT);
+        extract.update(&[Fr::from(3)]);
+        extract.update(&ikm);
+        let prk = extract.squeeze();
+        for (block, key) in keys.iter().enumerate() {
+            let mut expand = hasher();
+            expand.update_bytes(EXPAND);
+            expand.update(&[prk, Fr::from(block as u64)]);
+            expand.update_bytes(b"blinders");
+            assert_eq!(expand.squeeze(), *key);
+        }
+    }
+
+    #[test]
+    fn test_separation() {
+        let ikm = [Fr::from(1), Fr::from(2)];
+        let keys = expand(&ikm, b"a", 4);
+        // blocks are distinct, and prefixes of longer outputs
+        assert!((0..4).all(|i| (i + 1..4).all(|j| keys[i] != keys[j])));
+        assert_eq!(expand(&ikm, b"a", 2), keys[..2]);
+        assert_ne!(expand(&ikm, b"b", 1)[0], keys[0]);
+        assert_ne!(expand(&ikm, b"a\0", 1)[0], keys[0]);
+        assert_ne!(
+            expand(&[Fr::from(1), Fr::from(2), Fr::from(0)], b"a", 1)[0],
+            keys[0]
+        );
+        assert!(expand(&ikm, b"a", 0).is_empty());
+    }
+}
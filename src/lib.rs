@@ -2,8 +2,80 @@ pub use ff;
 pub use halo2_proofs;
 pub use halo2curves;
 
+// lets the paths of `#[derive(Absorb)]` resolve in the tests of this crate
+#[cfg(all(test, feature = "derive"))]
+extern crate self as poseidon_circuit;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+pub mod absorb;
+#[cfg(feature = "arkworks")]
+pub mod arkworks;
+pub mod audit;
+#[cfg(feature = "babyjubjub")]
+pub mod babyjubjub;
+#[cfg(feature = "baked-specs")]
+pub mod baked;
+#[cfg(any(
+    feature = "ffi",
+    feature = "node",
+    feature = "python",
+    feature = "uniffi",
+    feature = "wasm"
+))]
+pub mod bindings;
+pub mod cached;
+pub mod circom;
+pub mod commitment;
+pub mod cost;
+#[cfg(feature = "diff-fuzz")]
+pub mod diff_fuzz;
+pub mod encoding;
+pub mod encryption;
+pub mod fixed;
+#[cfg(test)]
+mod golden;
+mod grain;
+pub mod grumpkin;
+pub mod hash_chain;
+pub mod hash_to_curve;
+#[cfg(all(feature = "serde", any(test, feature = "test-utils")))]
+pub mod kat;
+pub mod kdf;
 pub mod main_gate;
+pub mod merkle;
+pub mod nova;
+#[cfg(feature = "op-counters")]
+pub mod op_count;
+mod optimize;
+pub mod orchard;
+pub mod pasta;
+pub mod poseidon2;
 pub mod poseidon_circuit;
 pub mod poseidon_hash;
+pub mod precomputed;
+#[cfg(test)]
+mod reference;
+pub mod rng;
 pub mod ro_types;
+pub mod rounds;
+#[cfg(feature = "digest")]
+pub mod rustcrypto;
+#[cfg(feature = "safe")]
+pub mod safe;
+pub mod secp256k1;
+pub mod shuffle;
+pub mod sig;
+pub mod solidity;
+mod spec;
+pub mod stream;
 pub mod test_circuit;
+#[cfg(test)]
+mod test_fixture;
+#[cfg(test)]
+mod toy_field;
+#[cfg(feature = "debug-trace")]
+pub mod trace;
+pub mod transcript;
+pub mod vrf;
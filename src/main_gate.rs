@@ -248,4 +248,18 @@ impl<F: PrimeField, const T: usize> MainGate<F, T> {
         ctx.next();
         Ok(res)
     }
+
+    /// A cell constrained to the constant `value`
+    pub fn assign_constant(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: F,
+    ) -> Result<AssignedValue<F>, Error> {
+        self.apply(
+            ctx,
+            (None, None, None),
+            Some(value),
+            (-F::ONE, Value::known(value).into()),
+        )
+    }
 }
@@ -0,0 +1,274 @@
+//! Binary Merkle trees over the native sponge.
+//!
+//! Every parent is `H(left, right)`, the [`PoseidonHash`] digest of its two
+//! children with a fresh sponge, and a tree over `n` leaves is padded with
+//! zero leaves to the next power of two. A [`MerkleProof`] is the index of a
+//! leaf and its siblings from the leaf up: the `i`-th bit of the index tells
+//! whether the node at height `i` is a right child.
+//!
+//! The width defaults to `T = 3`, the width of `poseidon merkle root`; the
+//! foreign-language bindings use their own preset. An
+//! [`IncrementalMerkleTree`] computes the same roots with the leaves
+//! appended one at a time, and a [`SparseMerkleTree`] places its leaves by
+//! key and also proves non-membership. [`MerklePathChip`] verifies paths in
+//! a circuit.
+//!
+//! [`MerklePathChip`]: chip::MerklePathChip
+//! [`IncrementalMerkleTree`]: incremental::IncrementalMerkleTree
+//! [`SparseMerkleTree`]: sparse::SparseMerkleTree
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    poseidon_hash::{initial_state, State},
+    precomputed::PrecomputedSpec,
+};
+
+pub mod chip;
+pub mod incremental;
+pub mod sparse;
+
+/// `H(left, right)`
+pub fn parent<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    left: F,
+    right: F,
+) -> F
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut state = State::<F, T, RATE>::new(initial_state());
+    state.absorb(spec, &[left, right]);
+    state.inner[1]
+}
+
+/// Inclusion proof of the leaf at `index`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MerkleProof<F: PrimeField> {
+    pub index: u64,
+    /// Siblings from the leaf up
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field_vec"))]
+    pub siblings: Vec<F>,
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshSerialize for MerkleProof<F> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.index.to_le_bytes())?;
+        crate::encoding::borsh::write_fields(&self.siblings, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshDeserialize for MerkleProof<F> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut index = [0u8; 8];
+        reader.read_exact(&mut index)?;
+        Ok(Self {
+            index: u64::from_le_bytes(index),
+            siblings: crate::encoding::borsh::read_fields(reader)?,
+        })
+    }
+}
+
+impl<F: PrimeField + FromUniformBytes<64>> MerkleProof<F> {
+    /// Root of the tree `leaf` is at `self.index` of
+    pub fn compute_root<const T: usize, const RATE: usize>(
+        &self,
+        spec: &PrecomputedSpec<F, T, RATE>,
+        leaf: F,
+    ) -> F {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |node, (height, sibling)| {
+                if self.index.checked_shr(height as u32).unwrap_or(0) & 1 == 1 {
+                    parent(spec, *sibling, node)
+                } else {
+                    parent(spec, node, *sibling)
+                }
+            })
+    }
+
+    /// Whether `leaf` is at `self.index` of the tree of `depth` and `root`.
+    /// A proof of another depth is rejected, as a shorter one would present
+    /// a node as a leaf, and so is an index beyond the depth.
+    pub fn verify<const T: usize, const RATE: usize>(
+        &self,
+        spec: &PrecomputedSpec<F, T, RATE>,
+        depth: usize,
+        root: F,
+        leaf: F,
+    ) -> bool {
+        if self.siblings.len() != depth || (depth < 64 && self.index >> depth != 0) {
+            return false;
+        }
+        self.compute_root(spec, leaf) == root
+    }
+}
+
+/// Binary Merkle tree holding every level
+#[derive(Clone, Debug)]
+pub struct PoseidonMerkleTree<
+    F: PrimeField + FromUniformBytes<64>,
+    const T: usize = 3,
+    const RATE: usize = 2,
+> {
+    spec: PrecomputedSpec<F, T, RATE>,
+    /// `levels[0]` are the padded leaves and the last level is the root
+    levels: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize>
+    PoseidonMerkleTree<F, T, RATE>
+{
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>, mut leaves: Vec<F>) -> Self {
+        leaves.resize(leaves.len().max(1).next_power_of_two(), F::ZERO);
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| parent(&spec, pair[0], pair[1]))
+                .collect();
+            levels.push(next);
+        }
+        Self { spec, levels }
+    }
+
+    pub fn root(&self) -> F {
+        self.levels.last().unwrap()[0]
+    }
+
+    /// Number of levels above the leaves
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    /// The leaves, padding included
+    pub fn leaves(&self) -> &[F] {
+        &self.levels[0]
+    }
+
+    /// Proof of the leaf at `index`, `None` beyond the padded leaves
+    pub fn prove(&self, index: usize) -> Option<MerkleProof<F>> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+        let siblings = self.levels[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(height, level)| level[(index >> height) ^ 1])
+            .collect();
+        Some(MerkleProof {
+            index: index as u64,
+            siblings,
+        })
+    }
+
+    /// [`MerkleProof::verify`] with the spec and the depth of this tree
+    pub fn verify(&self, root: F, proof: &MerkleProof<F>, leaf: F) -> bool {
+        proof.verify(&self.spec, self.depth(), root, leaf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, ro_types::ROTrait};
+
+    fn spec() -> PrecomputedSpec<Fr, 3, 2> {
+        PrecomputedSpec::new(8, 56)
+    }
+
+    #[test]
+    fn test_parent_is_sponge_digest() {
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec());
+        hasher.update(&[Fr::from(1), Fr::from(2)]);
+        assert_eq!(parent(&spec(), Fr::from(1), Fr::from(2)), hasher.squeeze());
+
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 4, 3>::new(spec.clone());
+        hasher.update(&[Fr::from(1), Fr::from(2)]);
+        assert_eq!(parent(&spec, Fr::from(1), Fr::from(2)), hasher.squeeze());
+    }
+
+    #[test]
+    fn test_tree() {
+        let leaves = (0..3).map(Fr::from).collect::<Vec<_>>();
+        let tree = PoseidonMerkleTree::new(spec(), leaves.clone());
+        let left = parent(&spec(), leaves[0], leaves[1]);
+        let right = parent(&spec(), leaves[2], Fr::from(0));
+        assert_eq!(tree.root(), parent(&spec(), left, right));
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.leaves().len(), 4);
+
+        for (index, leaf) in tree.leaves().iter().enumerate() {
+            let proof = tree.prove(index).unwrap();
+            assert!(tree.verify(tree.root(), &proof, *leaf));
+            assert!(!tree.verify(tree.root(), &proof, *leaf + Fr::from(1)));
+        }
+        assert_eq!(
+            tree.prove(2).unwrap(),
+            MerkleProof {
+                index: 2,
+                siblings: vec![Fr::from(0), left],
+            }
+        );
+        assert_eq!(tree.prove(4), None);
+
+        let single = PoseidonMerkleTree::new(spec(), vec![Fr::from(7)]);
+        assert_eq!((single.root(), single.depth()), (Fr::from(7), 0));
+        assert!(single.verify(Fr::from(7), &single.prove(0).unwrap(), Fr::from(7)));
+    }
+
+    #[test]
+    fn test_rejects_other_positions() {
+        let tree = PoseidonMerkleTree::new(spec(), (0..4).map(Fr::from).collect());
+        let mut proof = tree.prove(1).unwrap();
+        proof.index = 2;
+        assert!(!tree.verify(tree.root(), &proof, Fr::from(1)));
+        // the same position with an index beyond the depth
+        proof.index = 5;
+        assert!(!tree.verify(tree.root(), &proof, Fr::from(1)));
+    }
+
+    #[test]
+    fn test_rejects_other_depths() {
+        let tree = PoseidonMerkleTree::new(spec(), (0..4).map(Fr::from).collect());
+        let proof = tree.prove(1).unwrap();
+        // the parent of leaves 0 and 1, presented as a leaf one level up
+        let node = parent(&spec(), Fr::from(0), Fr::from(1));
+        let short = MerkleProof {
+            index: 0,
+            siblings: proof.siblings[1..].to_vec(),
+        };
+        assert!(short.verify(&spec(), 1, tree.root(), node));
+        assert!(!tree.verify(tree.root(), &short, node));
+
+        // siblings past the bits of the index do not overflow the shift
+        let long = MerkleProof {
+            index: 1,
+            siblings: vec![Fr::from(0); 70],
+        };
+        assert!(!long.verify(&spec(), 2, tree.root(), Fr::from(1)));
+        assert!(!long.verify(&spec(), 70, tree.root(), Fr::from(1)));
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_proof_borsh() {
+        let proof = PoseidonMerkleTree::new(spec(), (0..4).map(Fr::from).collect())
+            .prove(2)
+            .unwrap();
+        let bytes = borsh::to_vec(&proof).unwrap();
+        assert_eq!(bytes.len(), 8 + 4 + 2 * 32);
+        assert_eq!(borsh::from_slice::<MerkleProof<Fr>>(&bytes).unwrap(), proof);
+    }
+}
@@ -0,0 +1,283 @@
+//! Merkle path verification over the main gate.
+//!
+//! [`MerklePathChip::compute_root`] hashes a leaf up a path of fixed depth
+//! like [`MerkleProof::compute_root`](super::MerkleProof::compute_root):
+//! at every height it witnesses the index bit and the sibling, orders the
+//! node and the sibling by the bit and hashes them with a fresh
+//! [`PoseidonChip`]. The caller constrains the returned root, to an
+//! instance or to another cell, and can bind the returned bits to an index.
+//!
+//! Each height takes five rows of the main gate before its permutations:
+//!
+//! ```text
+//! b = bit                   b * b = b
+//! d = sibling - node        left = b * d + node
+//! right = d + 2 * node - left
+//! ```
+//!
+//! so `(left, right)` is `(node, sibling)` for `b = 0` and `(sibling,
+//! node)` for `b = 1`. Every node is a squeeze of the chip, whose initial
+//! state and padding are fixed constants, so the root is bound to the leaf,
+//! the bits and the siblings.
+use ff::PrimeField;
+use halo2_proofs::{circuit::Value, plonk::Error};
+
+use crate::{
+    main_gate::{AssignedValue, MainGateConfig, RegionCtx, WrapValue},
+    poseidon_circuit::PoseidonChip,
+    precomputed::PrecomputedSpec,
+};
+
+pub struct MerklePathChip<F: PrimeField, const T: usize, const RATE: usize> {
+    config: MainGateConfig<T>,
+    spec: PrecomputedSpec<F, T, RATE>,
+    depth: usize,
+}
+
+impl<F: PrimeField, const T: usize, const RATE: usize> MerklePathChip<F, T, RATE> {
+    /// # Panics
+    ///
+    /// If `T` is below 3, the state words the path rows use
+    pub fn new(config: MainGateConfig<T>, spec: PrecomputedSpec<F, T, RATE>, depth: usize) -> Self {
+        assert!(T >= 3, "the path rows need three state words");
+        Self {
+            config,
+            spec,
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Assigns `q_m * s[0] * s[1] + sum_i q_1[i] * s[i] = out`, copying the
+    /// assigned words of `state`, and returns `out`
+    fn assign_row(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        q_m: F,
+        q_1: [F; 3],
+        state: [WrapValue<F>; 3],
+    ) -> Result<AssignedValue<F>, Error> {
+        let mut values = [Value::known(F::ZERO); 3];
+        for (i, word) in state.iter().enumerate() {
+            values[i] = match word {
+                WrapValue::Assigned(cell) => {
+                    let si = ctx.assign_advice(
+                        || "merkle path: state",
+                        self.config.state[i],
+                        cell.value().copied(),
+                    )?;
+                    ctx.constrain_equal(cell.cell(), si.cell())?;
+                    cell.value().copied()
+                }
+                WrapValue::Unassigned(value) => {
+                    ctx.assign_advice(|| "merkle path: state", self.config.state[i], *value)?;
+                    *value
+                }
+                WrapValue::Zero => Value::known(F::ZERO),
+            };
+            ctx.assign_fixed(|| "merkle path: q_1", self.config.q_1[i], q_1[i])?;
+        }
+        ctx.assign_fixed(|| "merkle path: q_m", self.config.q_m, q_m)?;
+        ctx.assign_fixed(|| "merkle path: q_o", self.config.q_o, -F::ONE)?;
+
+        let out_val = values[0] * values[1] * Value::known(q_m)
+            + values
+                .iter()
+                .zip(q_1)
+                .fold(Value::known(F::ZERO), |acc, (value, q)| {
+                    acc + *value * Value::known(q)
+                });
+        let out = ctx.assign_advice(|| "merkle path: out", self.config.out, out_val)?;
+        ctx.next();
+        Ok(out)
+    }
+
+    /// Root of the tree `leaf` is at `index` of, with `siblings` from the
+    /// leaf up, and the cells of the `depth` index bits, least significant
+    /// first
+    ///
+    /// # Panics
+    ///
+    /// If there are not `depth` siblings
+    pub fn compute_root(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        leaf: &AssignedValue<F>,
+        index: Value<u64>,
+        siblings: &[Value<F>],
+    ) -> Result<(AssignedValue<F>, Vec<AssignedValue<F>>), Error> {
+        assert_eq!(siblings.len(), self.depth, "path of another depth");
+        let (zero, one, two) = (F::ZERO, F::ONE, F::from(2));
+        let mut node = leaf.clone();
+        let mut bits = Vec::with_capacity(self.depth);
+        for (height, sibling) in siblings.iter().enumerate() {
+            let bit = index.map(|index| F::from((index >> height) & 1));
+            let b = self.assign_row(
+                ctx,
+                zero,
+                [one, zero, zero],
+                [bit.into(), WrapValue::Zero, WrapValue::Zero],
+            )?;
+            let square = self.assign_row(
+                ctx,
+                one,
+                [zero, zero, zero],
+                [(&b).into(), (&b).into(), WrapValue::Zero],
+            )?;
+            ctx.constrain_equal(square.cell(), b.cell())?;
+
+            let d = self.assign_row(
+                ctx,
+                zero,
+                [one, -one, zero],
+                [(*sibling).into(), (&node).into(), WrapValue::Zero],
+            )?;
+            let left = self.assign_row(
+                ctx,
+                one,
+                [zero, zero, one],
+                [(&b).into(), (&d).into(), (&node).into()],
+            )?;
+            let right = self.assign_row(
+                ctx,
+                zero,
+                [one, two, -one],
+                [(&d).into(), (&node).into(), (&left).into()],
+            )?;
+
+            let mut chip = PoseidonChip::new(self.config.clone(), self.spec.clone());
+            chip.update_assigned(&[left, right]);
+            node = chip.squeeze(ctx)?;
+            bits.push(b);
+        }
+        Ok((node, bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::{group::ff::Field, pasta::Fp};
+
+    use super::*;
+    use crate::{merkle::PoseidonMerkleTree, test_fixture::FixtureConfig};
+
+    const T: usize = 3;
+    const RATE: usize = 2;
+    const R_F: usize = 4;
+    const R_P: usize = 3;
+    const DEPTH: usize = 3;
+    const K: u32 = 10;
+
+    /// Computes the root of `leaf` and exposes it and the index bits as
+    /// instances
+    struct PathCircuit {
+        leaf: Fp,
+        index: u64,
+        siblings: Vec<Fp>,
+    }
+
+    impl Circuit<Fp> for PathCircuit {
+        type Config = FixtureConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                leaf: Fp::ZERO,
+                index: 0,
+                siblings: vec![Fp::ZERO; self.siblings.len()],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            FixtureConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = MerklePathChip::<Fp, T, RATE>::new(
+                config.main_gate.clone(),
+                PrecomputedSpec::new(R_F, R_P),
+                DEPTH,
+            );
+            let (root, bits) = layouter.assign_region(
+                || "merkle path",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let leaf = config.witness(ctx, [self.leaf])?.remove(0);
+                    let siblings = self
+                        .siblings
+                        .iter()
+                        .map(|sibling| Value::known(*sibling))
+                        .collect::<Vec<_>>();
+                    chip.compute_root(ctx, &leaf, Value::known(self.index), &siblings)
+                },
+            )?;
+            layouter.constrain_instance(root.cell(), config.instance, 0)?;
+            for (i, bit) in bits.iter().enumerate() {
+                layouter.constrain_instance(bit.cell(), config.instance, 1 + i)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn instances(root: Fp, index: u64) -> Vec<Vec<Fp>> {
+        let bits = (0..DEPTH).map(|height| Fp::from((index >> height) & 1));
+        vec![std::iter::once(root).chain(bits).collect()]
+    }
+
+    #[test]
+    fn test_mock_matches_native() {
+        let leaves = (0..8).map(|i| Fp::from(10 + i)).collect::<Vec<_>>();
+        let tree = PoseidonMerkleTree::new(
+            PrecomputedSpec::<Fp, T, RATE>::new(R_F, R_P),
+            leaves.clone(),
+        );
+        for index in [0, 3, 6] {
+            let circuit = PathCircuit {
+                leaf: leaves[index],
+                index: index as u64,
+                siblings: tree.prove(index).unwrap().siblings,
+            };
+            let prover =
+                MockProver::run(K, &circuit, instances(tree.root(), index as u64)).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "index {}", index);
+        }
+    }
+
+    #[test]
+    fn test_mock_rejects_other_paths() {
+        let leaves = (0..8).map(|i| Fp::from(10 + i)).collect::<Vec<_>>();
+        let tree = PoseidonMerkleTree::new(
+            PrecomputedSpec::<Fp, T, RATE>::new(R_F, R_P),
+            leaves.clone(),
+        );
+        let siblings = tree.prove(3).unwrap().siblings;
+
+        // another leaf, another index, another sibling
+        let cases = [
+            (leaves[2], 3, siblings.clone()),
+            (leaves[3], 2, siblings.clone()),
+            (leaves[3], 3, vec![siblings[0], siblings[2], siblings[1]]),
+        ];
+        for (leaf, index, siblings) in cases {
+            let circuit = PathCircuit {
+                leaf,
+                index,
+                siblings,
+            };
+            let prover = MockProver::run(K, &circuit, instances(tree.root(), index)).unwrap();
+            assert!(prover.verify().is_err(), "index {}", index);
+        }
+    }
+}
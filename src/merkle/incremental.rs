@@ -0,0 +1,183 @@
+//! Append-only Merkle tree of fixed depth keeping only its frontier.
+//!
+//! An [`IncrementalMerkleTree`] of depth `d` commits to `2^d` leaves, the
+//! ones not appended yet being zero, so its root is the root of a
+//! [`PoseidonMerkleTree`] over the appended leaves padded to `2^d`. Rather
+//! than the leaves it stores, for every height, the root of the empty
+//! subtree and the last left child on the path of the appended leaves, so
+//! each append hashes `d` parents and memory stays `O(d)`.
+//!
+//! Proofs of the `retain` most recent leaves are kept up to date as leaves
+//! are appended: a sibling changes only when the new leaf falls into its
+//! subtree, and it then becomes the node on the new leaf's path.
+//!
+//! [`PoseidonMerkleTree`]: super::PoseidonMerkleTree
+use std::collections::VecDeque;
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use super::{parent, MerkleProof};
+use crate::precomputed::PrecomputedSpec;
+
+#[derive(Clone, Debug)]
+pub struct IncrementalMerkleTree<
+    F: PrimeField + FromUniformBytes<64>,
+    const T: usize = 3,
+    const RATE: usize = 2,
+> {
+    spec: PrecomputedSpec<F, T, RATE>,
+    /// Roots of the empty subtrees of every height, the leaf first
+    zeros: Vec<F>,
+    /// Left children of every height whose right sibling is not complete
+    frontier: Vec<F>,
+    len: u64,
+    root: F,
+    retain: usize,
+    /// Proofs of the most recent leaves, oldest first
+    witnesses: VecDeque<MerkleProof<F>>,
+}
+
+impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize>
+    IncrementalMerkleTree<F, T, RATE>
+{
+    /// An empty tree of `depth` keeping proofs of its `retain` most recent
+    /// leaves
+    ///
+    /// # Panics
+    ///
+    /// If `depth` is 64 or more
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>, depth: usize, retain: usize) -> Self {
+        assert!(depth < 64, "depth {} above 63", depth);
+        let mut zeros = vec![F::ZERO];
+        for height in 0..depth {
+            zeros.push(parent(&spec, zeros[height], zeros[height]));
+        }
+        Self {
+            spec,
+            root: zeros[depth],
+            frontier: zeros[..depth].to_vec(),
+            zeros,
+            len: 0,
+            retain,
+            witnesses: VecDeque::new(),
+        }
+    }
+
+    /// Appends `leaf` at index [`Self::len`] and returns the new root
+    ///
+    /// # Panics
+    ///
+    /// If the tree already holds `2^depth` leaves
+    pub fn append(&mut self, leaf: F) -> F {
+        let index = self.len;
+        assert!(index >> self.depth() == 0, "tree is full");
+        let (mut node, mut path, mut siblings) = (leaf, Vec::new(), Vec::new());
+        for height in 0..self.depth() {
+            path.push(node);
+            if (index >> height) & 1 == 0 {
+                self.frontier[height] = node;
+                siblings.push(self.zeros[height]);
+                node = parent(&self.spec, node, self.zeros[height]);
+            } else {
+                siblings.push(self.frontier[height]);
+                node = parent(&self.spec, self.frontier[height], node);
+            }
+        }
+
+        for witness in self.witnesses.iter_mut() {
+            for (height, sibling) in witness.siblings.iter_mut().enumerate() {
+                if (witness.index >> height) ^ 1 == index >> height {
+                    *sibling = path[height];
+                }
+            }
+        }
+        if self.retain > 0 {
+            if self.witnesses.len() == self.retain {
+                self.witnesses.pop_front();
+            }
+            self.witnesses.push_back(MerkleProof { index, siblings });
+        }
+        self.len += 1;
+        self.root = node;
+        node
+    }
+
+    pub fn root(&self) -> F {
+        self.root
+    }
+
+    /// Number of leaves appended
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frontier.len()
+    }
+
+    /// Proof of the leaf at `index` against the current root, if it is one
+    /// of the retained most recent leaves
+    pub fn witness(&self, index: u64) -> Option<MerkleProof<F>> {
+        self.witnesses
+            .iter()
+            .find(|witness| witness.index == index)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::merkle::PoseidonMerkleTree;
+
+    fn spec() -> PrecomputedSpec<Fr, 3, 2> {
+        PrecomputedSpec::new(8, 56)
+    }
+
+    /// Roots and retained proofs match a tree holding every leaf
+    #[test]
+    fn test_matches_full_tree() {
+        let depth = 3;
+        let mut tree = IncrementalMerkleTree::new(spec(), depth, 3);
+        assert_eq!(
+            tree.root(),
+            PoseidonMerkleTree::new(spec(), vec![Fr::from(0); 8]).root()
+        );
+
+        let mut leaves = Vec::new();
+        for i in 0..8u64 {
+            let leaf = Fr::from(100 + i);
+            leaves.push(leaf);
+            let mut padded = leaves.clone();
+            padded.resize(1 << depth, Fr::from(0));
+            let full = PoseidonMerkleTree::new(spec(), padded);
+            assert_eq!(tree.append(leaf), full.root(), "after {} leaves", i + 1);
+
+            for index in i.saturating_sub(2)..=i {
+                let witness = tree.witness(index).unwrap();
+                assert_eq!(witness, full.prove(index as usize).unwrap());
+                assert!(witness.verify(&spec(), depth, tree.root(), leaves[index as usize]));
+            }
+            if i >= 3 {
+                assert_eq!(tree.witness(i - 3), None);
+            }
+        }
+        assert_eq!(tree.len(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "tree is full")]
+    fn test_rejects_append_to_full_tree() {
+        let mut tree = IncrementalMerkleTree::new(spec(), 1, 0);
+        tree.append(Fr::from(1));
+        tree.append(Fr::from(2));
+        assert_eq!(tree.witness(0), None);
+        tree.append(Fr::from(3));
+    }
+}
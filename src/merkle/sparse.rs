@@ -0,0 +1,358 @@
+//! Sparse Merkle tree of fixed depth keyed by field elements.
+//!
+//! A [`SparseMerkleTree`] of depth `d` has a leaf for every key below
+//! `2^d`, at the position given by the bits of the key, least significant
+//! first like the index of a [`MerkleProof`](super::MerkleProof). An empty
+//! leaf is zero and the leaf of `key` holding `value` is `H(key, value)`, so
+//! a value of zero is still distinguished from no value. With `d` equal to
+//! the bits of the field every element is a key, which suits nullifier sets.
+//!
+//! Only the nodes of non-empty subtrees are stored: any other node is the
+//! root of an empty subtree of its height, computed once. A
+//! [`SparseMerkleProof`] holds the siblings of a key's leaf and proves
+//! either that the key holds a value or, the leaf being empty, that it holds
+//! none. Leaves and nodes hash alike, so proofs are verified against the
+//! depth of the tree: a shorter one would present a node as a leaf.
+use std::collections::HashMap;
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::parent;
+use crate::precomputed::PrecomputedSpec;
+
+/// Little-endian bytes of `key`
+fn key_bytes<F: PrimeField>(key: &F) -> Vec<u8> {
+    key.to_repr().as_ref().to_vec()
+}
+
+fn bit(bytes: &[u8], i: usize) -> bool {
+    (bytes[i / 8] >> (i % 8)) & 1 == 1
+}
+
+/// `key` with its `height` low bits cleared, identifying the node of that
+/// height above its leaf
+fn node_id(bytes: &[u8], height: usize) -> Vec<u8> {
+    let mut id = bytes.to_vec();
+    for i in 0..height {
+        id[i / 8] &= !(1 << (i % 8));
+    }
+    id
+}
+
+/// [`node_id`] of the sibling of the node of `height` above the leaf
+fn sibling_id(bytes: &[u8], height: usize) -> Vec<u8> {
+    let mut id = node_id(bytes, height);
+    id[height / 8] ^= 1 << (height % 8);
+    id
+}
+
+/// Whether `key` is below `2^depth`
+fn fits<F: PrimeField>(key: &F, depth: usize) -> bool {
+    let bytes = key_bytes(key);
+    (depth..bytes.len() * 8).all(|i| !bit(&bytes, i))
+}
+
+/// Proof of the leaf of `key`, occupied or empty
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SparseMerkleProof<F: PrimeField> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field"))]
+    pub key: F,
+    /// Siblings from the leaf up
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field_vec"))]
+    pub siblings: Vec<F>,
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshSerialize for SparseMerkleProof<F> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        crate::encoding::borsh::write_field(&self.key, writer)?;
+        crate::encoding::borsh::write_fields(&self.siblings, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshDeserialize for SparseMerkleProof<F> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(Self {
+            key: crate::encoding::borsh::read_field(reader)?,
+            siblings: crate::encoding::borsh::read_fields(reader)?,
+        })
+    }
+}
+
+impl<F: PrimeField + FromUniformBytes<64>> SparseMerkleProof<F> {
+    fn compute_root<const T: usize, const RATE: usize>(
+        &self,
+        spec: &PrecomputedSpec<F, T, RATE>,
+        leaf: F,
+    ) -> F {
+        let bytes = key_bytes(&self.key);
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |node, (height, sibling)| {
+                if bit(&bytes, height) {
+                    parent(spec, *sibling, node)
+                } else {
+                    parent(spec, node, *sibling)
+                }
+            })
+    }
+
+    /// Whether the proof has the shape of a leaf of a tree of `depth`: one
+    /// sibling per level, a key below `2^depth` and a depth the keys have
+    /// bits for. A shorter proof would present an internal node as a leaf.
+    fn fits_depth(&self, depth: usize) -> bool {
+        depth <= F::NUM_BITS as usize && self.siblings.len() == depth && fits(&self.key, depth)
+    }
+
+    /// Whether `key` holds `value` in the tree of `depth` and `root`
+    pub fn verify_membership<const T: usize, const RATE: usize>(
+        &self,
+        spec: &PrecomputedSpec<F, T, RATE>,
+        depth: usize,
+        root: F,
+        value: F,
+    ) -> bool {
+        self.fits_depth(depth) && self.compute_root(spec, parent(spec, self.key, value)) == root
+    }
+
+    /// Whether `key` holds no value in the tree of `depth` and `root`
+    pub fn verify_non_membership<const T: usize, const RATE: usize>(
+        &self,
+        spec: &PrecomputedSpec<F, T, RATE>,
+        depth: usize,
+        root: F,
+    ) -> bool {
+        self.fits_depth(depth) && self.compute_root(spec, F::ZERO) == root
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<
+    F: PrimeField + FromUniformBytes<64>,
+    const T: usize = 3,
+    const RATE: usize = 2,
+> {
+    spec: PrecomputedSpec<F, T, RATE>,
+    /// Roots of the empty subtrees of every height, the leaf first
+    zeros: Vec<F>,
+    /// Nodes of non-empty subtrees by height and [`node_id`]
+    nodes: HashMap<(usize, Vec<u8>), F>,
+    values: HashMap<Vec<u8>, F>,
+}
+
+impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize>
+    SparseMerkleTree<F, T, RATE>
+{
+    /// An empty tree with keys below `2^depth`
+    ///
+    /// # Panics
+    ///
+    /// If `depth` is above the bits of `F`
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>, depth: usize) -> Self {
+        assert!(
+            depth <= F::NUM_BITS as usize,
+            "depth {} above the field",
+            depth
+        );
+        let mut zeros = vec![F::ZERO];
+        for height in 0..depth {
+            zeros.push(parent(&spec, zeros[height], zeros[height]));
+        }
+        Self {
+            spec,
+            zeros,
+            nodes: HashMap::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.zeros.len() - 1
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.depth(), &vec![0; F::Repr::default().as_ref().len()])
+    }
+
+    fn node(&self, height: usize, id: &[u8]) -> F {
+        match self.nodes.get(&(height, id.to_vec())) {
+            Some(node) => *node,
+            None => self.zeros[height],
+        }
+    }
+
+    pub fn get(&self, key: &F) -> Option<F> {
+        self.values.get(&key_bytes(key)).copied()
+    }
+
+    /// Sets the value of `key`, returning the previous one
+    ///
+    /// # Panics
+    ///
+    /// If `key` is not below `2^depth`
+    pub fn insert(&mut self, key: F, value: F) -> Option<F> {
+        self.set(key, Some(value))
+    }
+
+    /// Empties the leaf of `key`, returning its value
+    pub fn remove(&mut self, key: &F) -> Option<F> {
+        self.set(*key, None)
+    }
+
+    fn set(&mut self, key: F, value: Option<F>) -> Option<F> {
+        assert!(fits(&key, self.depth()), "key beyond the depth of the tree");
+        let bytes = key_bytes(&key);
+        let previous = match value {
+            Some(value) => self.values.insert(bytes.clone(), value),
+            None => self.values.remove(&bytes),
+        };
+
+        let mut node = match value {
+            Some(value) => parent(&self.spec, key, value),
+            None => F::ZERO,
+        };
+        for height in 0..=self.depth() {
+            let id = node_id(&bytes, height);
+            if node == self.zeros[height] {
+                self.nodes.remove(&(height, id));
+            } else {
+                self.nodes.insert((height, id), node);
+            }
+            if height == self.depth() {
+                break;
+            }
+            let sibling = self.node(height, &sibling_id(&bytes, height));
+            node = if bit(&bytes, height) {
+                parent(&self.spec, sibling, node)
+            } else {
+                parent(&self.spec, node, sibling)
+            };
+        }
+        previous
+    }
+
+    /// Proof of membership of `key` if it holds a value, of non-membership
+    /// otherwise
+    ///
+    /// # Panics
+    ///
+    /// If `key` is not below `2^depth`
+    pub fn prove(&self, key: F) -> SparseMerkleProof<F> {
+        assert!(fits(&key, self.depth()), "key beyond the depth of the tree");
+        let bytes = key_bytes(&key);
+        let siblings = (0..self.depth())
+            .map(|height| self.node(height, &sibling_id(&bytes, height)))
+            .collect();
+        SparseMerkleProof { key, siblings }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::merkle::PoseidonMerkleTree;
+
+    fn spec() -> PrecomputedSpec<Fr, 3, 2> {
+        PrecomputedSpec::new(8, 56)
+    }
+
+    /// A small tree is the dense tree of its leaves
+    #[test]
+    fn test_matches_dense_tree() {
+        let mut tree = SparseMerkleTree::new(spec(), 3);
+        let dense = |leaves: &[(u64, u64)]| {
+            let mut dense = vec![Fr::from(0); 8];
+            for (key, value) in leaves {
+                dense[*key as usize] = parent(&spec(), Fr::from(*key), Fr::from(*value));
+            }
+            PoseidonMerkleTree::new(spec(), dense).root()
+        };
+        assert_eq!(tree.root(), dense(&[]));
+        assert_eq!(tree.insert(Fr::from(5), Fr::from(50)), None);
+        assert_eq!(tree.insert(Fr::from(2), Fr::from(0)), None);
+        assert_eq!(tree.root(), dense(&[(5, 50), (2, 0)]));
+        assert_eq!(tree.insert(Fr::from(5), Fr::from(51)), Some(Fr::from(50)));
+        assert_eq!(tree.root(), dense(&[(5, 51), (2, 0)]));
+        assert_eq!(tree.get(&Fr::from(5)), Some(Fr::from(51)));
+        assert_eq!(tree.remove(&Fr::from(5)), Some(Fr::from(51)));
+        assert_eq!(tree.root(), dense(&[(2, 0)]));
+        assert_eq!(tree.remove(&Fr::from(2)), Some(Fr::from(0)));
+        assert_eq!(tree.root(), SparseMerkleTree::new(spec(), 3).root());
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_proofs() {
+        let mut tree = SparseMerkleTree::new(spec(), Fr::NUM_BITS as usize);
+        let (key, other) = (-Fr::from(1), Fr::from(1 << 20));
+        tree.insert(key, Fr::from(7));
+        tree.insert(other, Fr::from(8));
+        let root = tree.root();
+
+        let depth = tree.depth();
+
+        let proof = tree.prove(key);
+        assert!(proof.verify_membership(&spec(), depth, root, Fr::from(7)));
+        assert!(!proof.verify_membership(&spec(), depth, root, Fr::from(8)));
+        assert!(!proof.verify_non_membership(&spec(), depth, root));
+
+        let absent = tree.prove(Fr::from(3));
+        assert!(absent.verify_non_membership(&spec(), depth, root));
+        assert!(!absent.verify_membership(&spec(), depth, root, Fr::from(0)));
+
+        // a proof for another key at the same depth does not transfer
+        let mut moved = proof.clone();
+        moved.key = other;
+        assert!(!moved.verify_membership(&spec(), depth, root, Fr::from(7)));
+    }
+
+    #[test]
+    fn test_rejects_other_depths() {
+        let mut tree = SparseMerkleTree::new(spec(), 3);
+        tree.insert(Fr::from(1), Fr::from(7));
+        let root = tree.root();
+
+        // the node above keys 0 and 1 is `H(0, H(1, 7))`, the leaf of key 0
+        // holding `H(1, 7)` one level up
+        let forged = SparseMerkleProof {
+            key: Fr::from(0),
+            siblings: tree.prove(Fr::from(0)).siblings[1..].to_vec(),
+        };
+        let value = parent(&spec(), Fr::from(1), Fr::from(7));
+        assert!(forged.verify_membership(&spec(), 2, root, value));
+        assert!(!forged.verify_membership(&spec(), 3, root, value));
+
+        // more siblings than the key has bits, rejected before reading them
+        let long = SparseMerkleProof {
+            key: Fr::from(0),
+            siblings: vec![Fr::from(0); 300],
+        };
+        assert!(!long.verify_non_membership(&spec(), 300, root));
+        assert!(!long.verify_non_membership(&spec(), 3, root));
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_proof_borsh() {
+        let proof = SparseMerkleTree::new(spec(), 3).prove(Fr::from(5));
+        let bytes = borsh::to_vec(&proof).unwrap();
+        assert_eq!(bytes.len(), 32 + 4 + 3 * 32);
+        assert_eq!(
+            borsh::from_slice::<SparseMerkleProof<Fr>>(&bytes).unwrap(),
+            proof
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "key beyond the depth of the tree")]
+    fn test_rejects_wide_keys() {
+        SparseMerkleTree::new(spec(), 3).insert(Fr::from(8), Fr::from(1));
+    }
+}
@@ -0,0 +1,123 @@
+//! Random-oracle conventions of microsoft/Nova on top of [`PoseidonHash`].
+//!
+//! Nova's RO runs over the base field of the curve whose points it absorbs.
+//! It absorbs a point as `(x, y, is_infinity)`, with the identity encoded as
+//! `(0, 0, 1)`. It squeezes a single element and keeps its low
+//! [`NUM_CHALLENGE_BITS`] bits as a scalar of that curve. This module
+//! reproduces those conventions. Nova's digests also depend on neptune's
+//! round constants, MDS matrix and sponge domain tag, which differ from the
+//! `PrecomputedSpec` used here, so transcripts only coincide once the same
+//! constants are supplied.
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::{
+    group::ff::{FromUniformBytes, PrimeField},
+    Coordinates,
+};
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+/// Challenge length used by Nova
+pub const NUM_CHALLENGE_BITS: usize = 128;
+
+pub struct NovaRO<C, F, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    hasher: PoseidonHash<C, F, T, RATE>,
+    num_absorbs: usize,
+    absorbed: usize,
+}
+
+impl<C, F, const T: usize, const RATE: usize> NovaRO<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    /// Like Nova, the number of absorbed elements is fixed up front
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>, num_absorbs: usize) -> Self {
+        Self {
+            hasher: PoseidonHash::new(spec),
+            num_absorbs,
+            absorbed: 0,
+        }
+    }
+
+    pub fn absorb(&mut self, element: F) {
+        self.hasher.update(&[element]);
+        self.absorbed += 1;
+    }
+
+    /// Absorbs `(x, y, is_infinity)` of a point whose coordinates live in `F`
+    pub fn absorb_point<P: CurveAffine<Base = F>>(&mut self, point: &P) {
+        let coordinates = Option::<Coordinates<P>>::from(point.coordinates());
+        let (x, y, is_infinity) = match coordinates {
+            Some(coordinates) => (*coordinates.x(), *coordinates.y(), F::ZERO),
+            None => (F::ZERO, F::ZERO, F::ONE),
+        };
+        self.absorb(x);
+        self.absorb(y);
+        self.absorb(is_infinity);
+    }
+
+    /// Squeezes one element and keeps its low `num_bits` bits as an `S`
+    ///
+    /// # Panics
+    ///
+    /// If the number of absorbed elements differs from the one given to
+    /// [`Self::new`], or if `num_bits` is not below the capacity of `S`
+    pub fn squeeze<S: PrimeField>(&mut self, num_bits: usize) -> S {
+        assert_eq!(self.absorbed, self.num_absorbs, "unexpected absorb count");
+        assert!(num_bits < S::CAPACITY as usize);
+        let hash = self.hasher.squeeze();
+        encoding::truncate(&hash, num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        group::{ff::Field, prime::PrimeCurveAffine},
+        pasta::{EpAffine, EqAffine, Fp, Fq},
+    };
+
+    use super::*;
+
+    const T: usize = 4;
+    const RATE: usize = 3;
+    const R_F: usize = 8;
+    const R_P: usize = 56;
+
+    fn spec() -> PrecomputedSpec<Fp, T, RATE> {
+        PrecomputedSpec::new(R_F, R_P)
+    }
+
+    #[test]
+    fn test_absorb_point() {
+        let point = EpAffine::generator();
+        let coordinates = point.coordinates().unwrap();
+
+        let mut ro = NovaRO::<EqAffine, Fp, T, RATE>::new(spec(), 6);
+        ro.absorb_point(&point);
+        ro.absorb_point(&EpAffine::identity());
+        let challenge: Fq = ro.squeeze(NUM_CHALLENGE_BITS);
+
+        let mut hasher = PoseidonHash::<EqAffine, Fp, T, RATE>::new(spec());
+        hasher.update(&[*coordinates.x(), *coordinates.y(), Fp::ZERO]);
+        hasher.update(&[Fp::ZERO, Fp::ZERO, Fp::ONE]);
+        assert_eq!(
+            challenge,
+            encoding::truncate(&hasher.squeeze(), NUM_CHALLENGE_BITS)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected absorb count")]
+    fn test_absorb_count() {
+        let mut ro = NovaRO::<EqAffine, Fp, T, RATE>::new(spec(), 2);
+        ro.absorb(Fp::ONE);
+        let _: Fq = ro.squeeze(NUM_CHALLENGE_BITS);
+    }
+}
@@ -0,0 +1,117 @@
+//! Field-operation counts of the native permutation.
+//!
+//! With the `op-counters` feature every [`PoseidonHash`] counts the
+//! multiplications, squarings and additions of its permutations, returned by
+//! [`PoseidonHash::take_op_count`]. [`permutation_ops`] counts a single
+//! permutation of a spec. One permutation absorbing a full block, with the
+//! bn256 round numbers of [`round_numbers`](crate::rounds::round_numbers):
+//!
+//! | `T` | `r_f` | `r_p` | mul  | square | add  |
+//! |-----|-------|-------|------|--------|------|
+//! | 2   | 8     | 56    | 344  | 144    | 275  |
+//! | 3   | 8     | 56    | 512  | 160    | 437  |
+//! | 4   | 8     | 56    | 696  | 176    | 615  |
+//! | 5   | 8     | 56    | 896  | 192    | 809  |
+//! | 6   | 8     | 57    | 1125 | 210    | 1031 |
+//! | 7   | 8     | 57    | 1359 | 226    | 1259 |
+//! | 8   | 8     | 57    | 1609 | 242    | 1503 |
+//!
+//! In general that is `T (T + 2) r_f + (2T + 1) r_p` multiplications,
+//! `2T r_f + 2 r_p` squarings and `T (T + 1) r_f + 2T r_p + 2T - 1` additions:
+//! each S-box takes two squarings and two multiplications, full rounds apply
+//! the dense MDS matrix and partial rounds the sparse one.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+//! [`PoseidonHash::take_op_count`]: crate::poseidon_hash::PoseidonHash::take_op_count
+use std::{fmt, ops::AddAssign};
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{poseidon_hash::State, precomputed::PrecomputedSpec};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpCount {
+    pub mul: usize,
+    pub square: usize,
+    pub add: usize,
+}
+
+impl AddAssign for OpCount {
+    fn add_assign(&mut self, other: Self) {
+        self.mul += other.mul;
+        self.square += other.square;
+        self.add += other.add;
+    }
+}
+
+impl fmt::Display for OpCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} mul, {} square, {} add",
+            self.mul, self.square, self.add
+        )
+    }
+}
+
+/// Operations of one permutation of `spec` absorbing `inputs` words
+///
+/// # Panics
+///
+/// If `inputs` exceeds `RATE`
+pub fn permutation_ops<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    inputs: usize,
+) -> OpCount
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut state = State::<F, T, RATE>::new([F::ZERO; T]);
+    state.permutation(spec, &vec![F::ZERO; inputs]);
+    state.ops
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, ro_types::ROTrait};
+
+    #[test]
+    fn test_permutation_ops() {
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let full = OpCount {
+            mul: 512,
+            square: 160,
+            add: 437,
+        };
+        assert_eq!(permutation_ops(&spec, 2), full);
+        // an input fewer, but one addition for the padding
+        assert_eq!(permutation_ops(&spec, 1), full);
+        assert_eq!(
+            permutation_ops(&spec, 0),
+            OpCount {
+                add: full.add - 1,
+                ..full
+            }
+        );
+        assert_eq!(
+            permutation_ops(&PrecomputedSpec::<Fr, 8, 7>::new(8, 57), 7),
+            OpCount {
+                mul: 1609,
+                square: 242,
+                add: 1503,
+            }
+        );
+
+        // three inputs take two permutations at width 3
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+        hasher.update(&[Fr::from(1), Fr::from(2), Fr::from(3)]);
+        hasher.squeeze();
+        let mut expected = full;
+        expected += permutation_ops(&spec, 1);
+        assert_eq!(hasher.take_op_count(), expected);
+        assert_eq!(hasher.take_op_count(), OpCount::default());
+    }
+}
@@ -0,0 +1,130 @@
+//! Folding of round constants and factoring of the MDS matrix for the
+//! optimized permutation, see [`optimize`].
+//!
+//! Like [`crate::grain`], this module only depends on the field traits, so
+//! that the build script can include it.
+use halo2curves::group::ff::Field;
+
+/// The constants and matrices of the optimized permutation, see
+/// [`crate::spec::PoseidonSpec`] for their layout
+pub(crate) struct Optimized<F, const T: usize, const RATE: usize> {
+    pub(crate) start: Vec<[F; T]>,
+    pub(crate) partial: Vec<F>,
+    pub(crate) end: Vec<[F; T]>,
+    pub(crate) pre_sparse_mds: [[F; T]; T],
+    pub(crate) sparse: Vec<([F; T], [F; RATE])>,
+}
+
+/// Inverse by Gauss-Jordan elimination, `None` if `matrix` is singular
+fn invert<F: Field>(matrix: &[Vec<F>]) -> Option<Vec<Vec<F>>> {
+    let n = matrix.len();
+    let mut rows = matrix
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row.extend((0..n).map(|j| if i == j { F::ONE } else { F::ZERO }));
+            row
+        })
+        .collect::<Vec<_>>();
+    for col in 0..n {
+        let pivot = (col..n).find(|row| rows[*row][col] != F::ZERO)?;
+        rows.swap(pivot, col);
+        let inv = rows[col][col].invert().unwrap();
+        for value in rows[col].iter_mut() {
+            *value *= inv;
+        }
+        let pivot_row = rows[col].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            let factor = row[col];
+            if i != col && factor != F::ZERO {
+                for (value, pivot) in row.iter_mut().zip(&pivot_row) {
+                    *value -= factor * pivot;
+                }
+            }
+        }
+    }
+    Some(rows.into_iter().map(|row| row[n..].to_vec()).collect())
+}
+
+fn mul_vector<F: Field, const T: usize>(matrix: &[Vec<F>], vector: &[F; T]) -> [F; T] {
+    std::array::from_fn(|i| {
+        matrix[i]
+            .iter()
+            .zip(vector)
+            .fold(F::ZERO, |acc, (m, v)| acc + *m * v)
+    })
+}
+
+/// The optimized form of the permutation with the round constants
+/// `constants`, one array per round, and the MDS matrix `mds`, whose first and
+/// last `r_f / 2` rounds are full and the others partial
+///
+/// Returns `None` if there are fewer than `r_f` rounds of constants, `mds` is
+/// singular, or one of the `RATE x RATE` matrices the partial rounds factor
+/// out is. The caller checks that `RATE + 1 == T` and `r_f` is even.
+pub(crate) fn optimize<F: Field, const T: usize, const RATE: usize>(
+    r_f: usize,
+    constants: &[[F; T]],
+    mds: &[[F; T]; T],
+) -> Option<Optimized<F, T, RATE>> {
+    let (half, r_p) = (r_f / 2, constants.len().checked_sub(r_f)?);
+    let inverse = invert(&mds.map(|row| row.to_vec()))?;
+
+    // A constant added after a matrix is added before it times the
+    // inverse. Those of the full rounds move after the S-boxes; through
+    // the partial rounds, all words but the first also move before the
+    // S-box and accumulate into the last first-half round.
+    let mut start = vec![constants[0]];
+    start.extend(constants[1..half].iter().map(|c| mul_vector(&inverse, c)));
+    let mut acc = constants[half + r_p];
+    let mut partial = vec![F::ZERO; r_p];
+    for round in (0..r_p).rev() {
+        let folded = mul_vector(&inverse, &acc);
+        partial[round] = folded[0];
+        acc = constants[half + round];
+        for (acc, folded) in acc.iter_mut().zip(folded).skip(1) {
+            *acc += folded;
+        }
+    }
+    start.push(mul_vector(&inverse, &acc));
+    let end = constants[half + r_p + 1..]
+        .iter()
+        .map(|c| mul_vector(&inverse, c))
+        .collect();
+
+    // The matrix of the last partial round is `S * diag(1, hat)` with `S`
+    // sparse; `diag(1, hat)` commutes with the S-box of the first word
+    // and joins the matrix of the round before, back to the last
+    // first-half round.
+    let mut sparse = vec![([F::ZERO; T], [F::ZERO; RATE]); r_p];
+    let mut acc = *mds;
+    for round in (0..r_p).rev() {
+        let hat = acc[1..]
+            .iter()
+            .map(|row| row[1..].to_vec())
+            .collect::<Vec<_>>();
+        let hat_inverse = invert(&hat)?;
+        let row = std::array::from_fn(|j| match j {
+            0 => acc[0][0],
+            j => (1..T).fold(F::ZERO, |sum, k| {
+                sum + acc[0][k] * hat_inverse[k - 1][j - 1]
+            }),
+        });
+        sparse[round] = (row, std::array::from_fn(|i| acc[i + 1][0]));
+        acc = std::array::from_fn(|i| match i {
+            0 => mds[0],
+            i => std::array::from_fn(|j| {
+                (1..T).fold(F::ZERO, |sum, k| sum + hat[i - 1][k - 1] * mds[k][j])
+            }),
+        });
+    }
+
+    Some(Optimized {
+        start,
+        partial,
+        end,
+        pre_sparse_mds: acc,
+        sparse,
+    })
+}
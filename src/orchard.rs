@@ -0,0 +1,96 @@
+//! Poseidon as instantiated by zcash's `halo2_gadgets::poseidon` with
+//! `P128Pow5T3`, the hash of Orchard, over the Pallas and Vesta base fields.
+//!
+//! The permutation is the one of this crate for `T = 3`, eight full and 56
+//! partial rounds: both take the constants of the Grain LFSR of the paper.
+//! The sponge differs from [`PoseidonHash`]'s. The capacity is the last
+//! word, where `ConstantLength<L>` puts `L * 2^64`; the message is absorbed
+//! into the first two words, zero-padded to a multiple of the rate, and the
+//! digest is the first word.
+//!
+//! `P128Pow5T3` is only defined for the two pasta fields; the functions
+//! below accept any field with the same conventions.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{cached::CachedSpec, poseidon_hash::State, precomputed::PrecomputedSpec};
+
+pub const P128_R_F: usize = 8;
+pub const P128_R_P: usize = 56;
+
+const RATE: usize = 2;
+
+/// The spec of `P128Pow5T3` over `F`
+pub fn p128_spec<F: PrimeField + FromUniformBytes<64>>() -> &'static PrecomputedSpec<F, 3, RATE> {
+    PrecomputedSpec::cached(P128_R_F, P128_R_P)
+}
+
+/// `halo2_gadgets::poseidon::primitives::permute` with `P128Pow5T3`
+pub fn permute<F: PrimeField + FromUniformBytes<64>>(state: &mut [F; 3]) {
+    let mut inner = State::<F, 3, RATE>::new(*state);
+    // zero inputs make no padding word, so this is the bare permutation
+    inner.permutation(p128_spec(), &[F::ZERO; RATE]);
+    *state = inner.inner;
+}
+
+/// Digest of `Hash::<F, P128Pow5T3, ConstantLength<L>, 3, 2>::init().hash(message)`
+pub fn hash<F: PrimeField + FromUniformBytes<64>, const L: usize>(message: [F; L]) -> F {
+    let mut state = [F::ZERO, F::ZERO, F::from_u128((L as u128) << 64)];
+    // an empty message is still one permutation
+    for block in message.chunks(RATE).chain((L == 0).then_some(&[][..])) {
+        for (word, element) in state.iter_mut().zip(block) {
+            *word += element;
+        }
+        permute(&mut state);
+    }
+    state[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_gadgets::poseidon::primitives::{self as gadgets, ConstantLength, P128Pow5T3};
+    use halo2curves::pasta::{Fp, Fq};
+
+    use super::*;
+    use crate::reference::Reference;
+
+    /// The same element in the field type of `pasta_curves`
+    fn convert<A: PrimeField, B: PrimeField<Repr = A::Repr>>(value: A) -> B {
+        B::from_repr(value.to_repr()).unwrap()
+    }
+
+    #[test]
+    fn test_permute_matches_reference() {
+        let reference = Reference::<Fp, 3>::new(P128_R_F, P128_R_P);
+        let mut state = [Fp::from(0), Fp::from(1), Fp::from(2)];
+        let mut expected = state;
+        permute(&mut state);
+        reference.permute(&mut expected);
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_matches_halo2_gadgets() {
+        let message = [Fp::from(3), Fp::from(4), Fp::from(5)];
+        let expected = gadgets::Hash::<_, P128Pow5T3, ConstantLength<2>, 3, 2>::init()
+            .hash([message[0], message[1]].map(convert::<_, pasta_curves::Fp>));
+        assert_eq!(hash([message[0], message[1]]), convert(expected));
+        let expected = gadgets::Hash::<_, P128Pow5T3, ConstantLength<3>, 3, 2>::init()
+            .hash(message.map(convert::<_, pasta_curves::Fp>));
+        assert_eq!(hash(message), convert(expected));
+        let expected = gadgets::Hash::<_, P128Pow5T3, ConstantLength<1>, 3, 2>::init()
+            .hash([convert::<_, pasta_curves::Fq>(Fq::from(7))]);
+        assert_eq!(hash([Fq::from(7)]), convert(expected));
+
+        // and the constants of the permutation are the reference ones
+        let reference = Reference::<Fq, 3>::new(P128_R_F, P128_R_P);
+        let (round_constants, mds, _) =
+            <P128Pow5T3 as gadgets::Spec<pasta_curves::Fq, 3, 2>>::constants();
+        assert_eq!(round_constants.len(), reference.constants.len());
+        for (constants, expected) in round_constants.iter().zip(&reference.constants) {
+            assert_eq!(constants.map(convert::<_, Fq>), *expected);
+        }
+        assert_eq!(mds.map(|row| row.map(convert::<_, Fq>)), reference.mds);
+    }
+}
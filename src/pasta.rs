@@ -0,0 +1,114 @@
+//! Sponges for the Pallas–Vesta cycle.
+//!
+//! Pallas has base field `Fp` and scalar field `Fq`, Vesta the other way
+//! round. [`PallasHash`] runs over `Fq` and absorbs Pallas points as limbs
+//! with [`PoseidonHash::absorb_point`], like the transcript of a proof
+//! committing with Pallas; [`VestaHash`] is the same over `Fp` for Vesta.
+//! Either absorbs points of the other curve natively with
+//! [`PoseidonHash::absorb_native_point`].
+//!
+//! Both fields have 255 bits, for which [`round_numbers`] picks eight full
+//! and 56 partial rounds at width 3 and 128-bit security, the parameters of
+//! [`orchard`](crate::orchard).
+//!
+//! A digest carried to the other side of the cycle has to be an element of
+//! the other field. `p < q`, so [`fp_to_fq`] keeps every element, while
+//! [`fq_to_fp`] fails on the elements of `Fq` not below `p`, a fraction of
+//! about `2^-167` of them.
+//!
+//! [`round_numbers`]: crate::rounds::round_numbers
+use halo2curves::{
+    group::ff::PrimeField,
+    pasta::{EpAffine, EqAffine, Fp, Fq},
+};
+
+use crate::{
+    cached::CachedSpec, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+};
+
+pub const T: usize = 3;
+pub const RATE: usize = 2;
+pub const R_F: usize = 8;
+pub const R_P: usize = 56;
+
+/// Sponge over `Fq`, the scalar field of Pallas
+pub type PallasHash = PoseidonHash<EpAffine, Fq, T, RATE>;
+
+/// Sponge over `Fp`, the scalar field of Vesta
+pub type VestaHash = PoseidonHash<EqAffine, Fp, T, RATE>;
+
+/// The spec of [`PallasHash`]
+pub fn pallas_spec() -> &'static PrecomputedSpec<Fq, T, RATE> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+/// The spec of [`VestaHash`]
+pub fn vesta_spec() -> &'static PrecomputedSpec<Fp, T, RATE> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+/// A fresh [`PallasHash`]
+pub fn pallas() -> PallasHash {
+    PallasHash::new(pallas_spec().clone())
+}
+
+/// A fresh [`VestaHash`]
+pub fn vesta() -> VestaHash {
+    VestaHash::new(vesta_spec().clone())
+}
+
+/// The element of `Fq` with the same integer value as `value`
+pub fn fp_to_fq(value: Fp) -> Fq {
+    Fq::from_repr(value.to_repr()).unwrap()
+}
+
+/// The element of `Fp` with the same integer value as `value`, `None` if
+/// `value` is not below `p`
+pub fn fq_to_fp(value: Fq) -> Option<Fp> {
+    Fp::from_repr(value.to_repr()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::CurveAffine;
+    use halo2curves::group::{ff::Field, prime::PrimeCurveAffine};
+
+    use super::*;
+    use crate::audit::Audit;
+
+    #[test]
+    fn test_specs() {
+        assert!(Audit::new(pallas_spec(), 128).passes());
+        assert!(Audit::new(vesta_spec(), 128).passes());
+    }
+
+    #[test]
+    fn test_cycle() {
+        // a Vesta digest over a Pallas point, absorbed on the Pallas side
+        let point = EpAffine::generator();
+        let mut hasher = vesta();
+        hasher.absorb_native_point(&point);
+        let digest = hasher.squeeze();
+
+        let mut hasher = pallas();
+        hasher.absorb_point(&point);
+        hasher.update(&[fp_to_fq(digest)]);
+        let coordinates = point.coordinates().unwrap();
+        let mut expected = PallasHash::new(PrecomputedSpec::new(R_F, R_P));
+        expected.absorb_base(*coordinates.x());
+        expected.absorb_base(*coordinates.y());
+        expected.update(&[Fq::ZERO, Fq::from_repr(digest.to_repr()).unwrap()]);
+        assert_eq!(hasher.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let value = Fp::from(u64::MAX).square();
+        assert_eq!(fp_to_fq(value), Fq::from(u64::MAX).square());
+        assert_eq!(fq_to_fp(fp_to_fq(value)), Some(value));
+        assert_eq!(fq_to_fp(fp_to_fq(-Fp::ONE)), Some(-Fp::ONE));
+        // p itself, the smallest element of Fq that does not fit
+        assert_eq!(fq_to_fp(fp_to_fq(-Fp::ONE) + Fq::ONE), None);
+    }
+}
@@ -0,0 +1,292 @@
+//! Poseidon2, the permutation of Grassi, Khovratovich and Schofnegger, and a
+//! sponge over it implementing [`ROTrait`].
+//!
+//! A permutation multiplies the state by the external matrix, runs half of
+//! the `r_f` external rounds, the `r_p` internal rounds and the other half of
+//! the external rounds. External rounds add `T` constants, apply the S-box to
+//! every word and multiply by the external matrix; internal rounds add one
+//! constant to word 0, apply the S-box to it alone and multiply by the
+//! internal matrix. For `T` of 2 and 3 both matrices are fixed by the paper,
+//! `circ(2, 1, .., 1)` and `J + diag(1, .., 1, 2)`, so each multiplication is
+//! a sum of the state and a few additions instead of the `T^2`
+//! multiplications of a dense MDS matrix. Wider states need matrices that
+//! are not standardized for these fields and are not implemented.
+//!
+//! The constants come from the Grain LFSR seeded like Poseidon's,
+//! [`audit::seed`](crate::audit::seed), `T` per external round and one per
+//! internal round in round order, as the reference implementation generates
+//! them; for BN254 at `T = 3` this gives its test vector.
+//!
+//! [`Poseidon2Hash`] is a sponge like [`PoseidonHash`], with the same
+//! capacity word, rate words and padding, so swapping the two only changes
+//! the outputs. It has no circuit counterpart.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use std::{marker::PhantomData, mem};
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    grain::Grain,
+    ro_types::{ROConstantsTrait, ROTrait},
+};
+
+/// Round constants of the Poseidon2 permutation of width `T`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Poseidon2Spec<F: PrimeField, const T: usize> {
+    /// `T` constants for each of the `r_f` external rounds
+    external: Vec<[F; T]>,
+    /// One constant for each of the `r_p` internal rounds
+    internal: Vec<F>,
+}
+
+impl<F: PrimeField, const T: usize> Poseidon2Spec<F, T> {
+    /// # Panics
+    ///
+    /// If `T` is not 2 or 3, or `r_f` is odd
+    pub fn new(r_f: usize, r_p: usize) -> Self {
+        assert!(T == 2 || T == 3, "unsupported Poseidon2 width {}", T);
+        assert!(r_f.is_multiple_of(2), "odd number of external rounds");
+        let mut grain = Grain::new::<F>(T, r_f, r_p);
+        let (mut external, mut internal) = (Vec::new(), Vec::new());
+        for round in 0..r_f + r_p {
+            if (r_f / 2..r_f / 2 + r_p).contains(&round) {
+                internal.push(grain.field_element());
+            } else {
+                external.push(std::array::from_fn(|_| grain.field_element()));
+            }
+        }
+        Self { external, internal }
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.external.len()
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.internal.len()
+    }
+
+    /// Applies the permutation to `state`
+    pub fn permute(&self, state: &mut [F; T]) {
+        let pow5 = |v: F| v.square().square() * v;
+        let half = self.external.len() / 2;
+        let external_round = |state: &mut [F; T], constants: &[F; T]| {
+            for (s, c) in state.iter_mut().zip(constants) {
+                *s = pow5(*s + c);
+            }
+            external_matrix(state);
+        };
+
+        external_matrix(state);
+        for constants in &self.external[..half] {
+            external_round(state, constants);
+        }
+        for constant in &self.internal {
+            state[0] = pow5(state[0] + constant);
+            internal_matrix(state);
+        }
+        for constants in &self.external[half..] {
+            external_round(state, constants);
+        }
+    }
+}
+
+/// `circ(2, 1, .., 1)`: every word plus the sum of the state
+fn external_matrix<F: PrimeField, const T: usize>(state: &mut [F; T]) {
+    let sum: F = state.iter().sum();
+    for s in state.iter_mut() {
+        *s += sum;
+    }
+}
+
+/// `J + diag(1, .., 1, 2)`: as [`external_matrix`], with the last word
+/// doubled first
+fn internal_matrix<F: PrimeField, const T: usize>(state: &mut [F; T]) {
+    let sum: F = state.iter().sum();
+    state[T - 1] = state[T - 1].double();
+    for s in state.iter_mut() {
+        *s += sum;
+    }
+}
+
+impl<F: PrimeField, const T: usize> ROConstantsTrait for Poseidon2Spec<F, T> {
+    fn new(r_f: usize, r_p: usize) -> Self {
+        Poseidon2Spec::new(r_f, r_p)
+    }
+}
+
+/// Native sponge over the Poseidon2 permutation
+///
+/// The state starts as `[2^64, 0, .., 0]`. A squeeze absorbs the buffered
+/// elements `RATE` at a time into words `1..=RATE`, pads the last block
+/// with a one, in a block of its own when the length is a multiple of
+/// `RATE`, and returns word 1, keeping the state for the next squeeze.
+#[derive(Clone, Debug)]
+pub struct Poseidon2Hash<
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    const T: usize,
+    const RATE: usize,
+> {
+    spec: Poseidon2Spec<F, T>,
+    state: [F; T],
+    buf: Vec<F>,
+    _marker: PhantomData<C>,
+}
+
+impl<C, F, const T: usize, const RATE: usize> ROTrait<C> for Poseidon2Hash<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    type Constants = Poseidon2Spec<F, T>;
+
+    fn new(constants: Self::Constants) -> Self {
+        assert_eq!(RATE, T - 1);
+        let mut state = [F::ZERO; T];
+        state[0] = F::from_u128(1 << 64);
+        Self {
+            spec: constants,
+            state,
+            buf: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    fn absorb(&mut self, input: C::Scalar) {
+        self.update(&[input]);
+    }
+
+    fn squeeze(&mut self) -> C::Scalar {
+        let buf = mem::take(&mut self.buf);
+        let exact = buf.len().is_multiple_of(RATE);
+        for chunk in buf.chunks(RATE) {
+            self.absorb_block(chunk);
+        }
+        if exact {
+            self.absorb_block(&[]);
+        }
+        self.state[1]
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize> Poseidon2Hash<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    /// Buffers `elements`; they are absorbed on the next squeeze
+    pub fn update(&mut self, elements: &[F]) {
+        self.buf.extend_from_slice(elements);
+    }
+
+    /// Adds `block` and, if it is short, the padding one to the rate words
+    /// and permutes
+    fn absorb_block(&mut self, block: &[F]) {
+        for (s, element) in self.state[1..].iter_mut().zip(block) {
+            *s += element;
+        }
+        if block.len() < RATE {
+            self.state[1 + block.len()] += F::ONE;
+        }
+        self.spec.permute(&mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec};
+
+    #[test]
+    fn test_vectors() {
+        // the BN254 vector of the reference implementation
+        let spec = Poseidon2Spec::<Fr, 3>::new(8, 56);
+        let mut state = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        spec.permute(&mut state);
+        assert_eq!(
+            state.map(|word| encoding::to_hex(&word)),
+            [
+                "0x0bb61d24daca55eebcb1929a82650f328134334da98ea4f847f760054f4a3033",
+                "0x303b6f7c86d043bfcbcc80214f26a30277a15d3f74ca654992defe7ff8d03570",
+                "0x1ed25194542b12eef8617361c3ba7c52e660b145994427cc86296242cf766ec8",
+            ]
+        );
+
+        // computed with a Python model of the same generation
+        let spec = Poseidon2Spec::<Fr, 2>::new(8, 56);
+        let mut state = [Fr::from(0), Fr::from(1)];
+        spec.permute(&mut state);
+        assert_eq!(
+            state.map(|word| encoding::to_hex(&word)),
+            [
+                "0x1d01e56f49579cec72319e145f06f6177f6c5253206e78c2689781452a31878b",
+                "0x0d189ec589c41b8cffa88cfc523618a055abe8192c70f75aa72fc514560f6c61",
+            ]
+        );
+        assert_eq!((spec.full_rounds(), spec.partial_rounds()), (8, 56));
+    }
+
+    /// The matrices agree with their dense form
+    #[test]
+    fn test_matrices() {
+        let dense = |matrix: [[u64; 3]; 3], state: [u64; 3]| {
+            matrix.map(|row| {
+                row.iter()
+                    .zip(state)
+                    .map(|(m, s)| Fr::from(m * s))
+                    .sum::<Fr>()
+            })
+        };
+        let mut state = [3, 5, 7].map(Fr::from);
+        external_matrix(&mut state);
+        assert_eq!(state, dense([[2, 1, 1], [1, 2, 1], [1, 1, 2]], [3, 5, 7]));
+        let mut state = [3, 5, 7].map(Fr::from);
+        internal_matrix(&mut state);
+        assert_eq!(state, dense([[2, 1, 1], [1, 2, 1], [1, 1, 3]], [3, 5, 7]));
+    }
+
+    /// First challenge of any [`ROTrait`] sponge
+    fn challenge<R: ROTrait<G1Affine>>(constants: R::Constants) -> Fr {
+        R::new(constants).squeeze()
+    }
+
+    #[test]
+    fn test_sponge() {
+        let spec = Poseidon2Spec::<Fr, 3>::new(8, 56);
+        for len in 0..5u64 {
+            let inputs = (1..=len).map(Fr::from).collect::<Vec<_>>();
+            let mut hasher = Poseidon2Hash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+            hasher.update(&inputs);
+
+            let mut state = [Fr::from_u128(1 << 64), Fr::from(0), Fr::from(0)];
+            let mut padded = inputs.clone();
+            padded.push(Fr::from(1));
+            padded.resize(padded.len().div_ceil(2) * 2, Fr::from(0));
+            for block in padded.chunks(2) {
+                state[1] += block[0];
+                state[2] += block[1];
+                spec.permute(&mut state);
+            }
+            assert_eq!(hasher.squeeze(), state[1], "{} inputs", len);
+        }
+
+        // the same call sites run either sponge
+        let poseidon = challenge::<PoseidonHash<G1Affine, Fr, 3, 2>>(
+            <PrecomputedSpec<Fr, 3, 2> as ROConstantsTrait>::new(8, 56),
+        );
+        let poseidon2 =
+            challenge::<Poseidon2Hash<G1Affine, Fr, 3, 2>>(ROConstantsTrait::new(8, 56));
+        assert_ne!(poseidon, poseidon2);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported Poseidon2 width 4")]
+    fn test_rejects_wide_states() {
+        Poseidon2Spec::<Fr, 4>::new(8, 56);
+    }
+}
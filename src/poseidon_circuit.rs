@@ -1,22 +1,33 @@
 use std::convert::TryInto;
 
-use ff::PrimeField;
+use ff::{FromUniformBytes, PrimeField};
 use halo2_proofs::{
+    arithmetic::CurveAffine,
     circuit::{AssignedCell, Chip, Value},
     plonk::Error,
 };
-use poseidon::Spec;
 
-use crate::main_gate::{AssignedValue, MainGate, MainGateConfig, RegionCtx};
+use crate::{
+    main_gate::{AssignedValue, MainGate, MainGateConfig, RegionCtx, WrapValue},
+    poseidon_hash::initial_state,
+    precomputed::PrecomputedSpec,
+    ro_types::ROCircuitTrait,
+    spec::PoseidonSpec,
+};
 
+/// The sponge of [`PoseidonHash`](crate::poseidon_hash::PoseidonHash) over
+/// the main gate: the same permutation, one row per state word and round,
+/// and the same padding, so a squeeze assigns the native digest. The initial
+/// state, the capacity and the padding are fixed constants of the circuit, so
+/// the prover only chooses the absorbed elements.
 pub struct PoseidonChip<F: PrimeField, const T: usize, const RATE: usize> {
     main_gate: MainGate<F, T>,
-    spec: Spec<F, T, RATE>,
-    buf: Vec<F>,
+    spec: PrecomputedSpec<F, T, RATE>,
+    buf: Vec<WrapValue<F>>,
 }
 
 impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE> {
-    pub fn new(config: MainGateConfig<T>, spec: Spec<F, T, RATE>) -> Self {
+    pub fn new(config: MainGateConfig<T>, spec: PrecomputedSpec<F, T, RATE>) -> Self {
         let main_gate: MainGate<F, T> = MainGate::new(config);
         Self {
             main_gate,
@@ -43,48 +54,78 @@ impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE>
         out * Value::known((-q_o).invert().unwrap())
     }
 
+    /// Adds word `state_idx` of the padded block `inputs` to the state.
+    /// Assigned inputs are copied into the input column.
     pub fn pre_round(
         &self,
         ctx: &mut RegionCtx<'_, F>,
-        inputs: Vec<F>,
+        inputs: Vec<WrapValue<F>>,
         state_idx: usize,
         state: &[AssignedValue<F>; T],
     ) -> Result<AssignedValue<F>, Error> {
-        assert!(inputs.len() <= RATE);
-        let s_val = state[state_idx].value().copied();
+        self.absorb_word(ctx, inputs, state_idx, Some(state))
+    }
 
-        let inputs = std::iter::once(F::ZERO)
-            .chain(inputs)
-            .chain(std::iter::once(F::ONE))
-            .chain(std::iter::repeat(F::ZERO))
-            .take(T)
-            .collect::<Vec<_>>();
-        let input_val = Value::known(inputs[state_idx]);
+    /// [`Self::pre_round`] of `state`, or of the initial state of the sponge
+    /// if `None`. The words that are constants, the initial state, the
+    /// capacity, the padding one and the words past it, go into the fixed
+    /// `rc` rather than an advice cell, where the prover could set them to
+    /// anything.
+    fn absorb_word(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        inputs: Vec<WrapValue<F>>,
+        state_idx: usize,
+        state: Option<&[AssignedValue<F>; T]>,
+    ) -> Result<AssignedValue<F>, Error> {
+        assert!(inputs.len() <= RATE);
+        let len = inputs.len();
+        let (input, padding) = match state_idx.checked_sub(1) {
+            Some(i) if i < len => (inputs.into_iter().nth(i).unwrap(), F::ZERO),
+            Some(i) if i == len => (WrapValue::Zero, F::ONE),
+            _ => (WrapValue::Zero, F::ZERO),
+        };
+        let mut rc_val = self.spec.start_constants()[0][state_idx] + padding;
+
+        let s_val = match state {
+            Some(state) => {
+                let si = ctx.assign_advice(
+                    || "first round: state",
+                    self.main_gate.config().state[state_idx],
+                    state[state_idx].value().copied(),
+                )?;
+                ctx.constrain_equal(state[state_idx].cell(), si.cell())?;
+                ctx.assign_fixed(
+                    || "pre_round: q_1",
+                    self.main_gate.config().q_1[state_idx],
+                    F::ONE,
+                )?;
+                si.value().copied()
+            }
+            None => {
+                rc_val += initial_state::<F, T>()[state_idx];
+                Value::known(F::ZERO)
+            }
+        };
 
-        let constants = self.spec.constants().start();
-        let pre_constants = constants[0];
-        let rc_val = pre_constants[state_idx];
+        let input_val = match &input {
+            WrapValue::Assigned(cell) => cell.value().copied(),
+            WrapValue::Unassigned(val) => *val,
+            WrapValue::Zero => Value::known(F::ZERO),
+        };
+        if !matches!(input, WrapValue::Zero) {
+            let input_cell = ctx.assign_advice(
+                || "pre_round: input",
+                self.main_gate.config().input,
+                input_val,
+            )?;
+            if let WrapValue::Assigned(cell) = &input {
+                ctx.constrain_equal(cell.cell(), input_cell.cell())?;
+            }
+            ctx.assign_fixed(|| "pre_round: q_i", self.main_gate.config().q_i, F::ONE)?;
+        }
 
         let out_val = s_val + input_val + Value::known(rc_val);
-
-        let si = ctx.assign_advice(
-            || "first round: state",
-            self.main_gate.config().state[state_idx],
-            s_val,
-        )?;
-        ctx.constrain_equal(state[state_idx].cell(), si.cell())?;
-
-        ctx.assign_advice(
-            || "pre_round: input",
-            self.main_gate.config().input,
-            input_val,
-        )?;
-        ctx.assign_fixed(
-            || "pre_round: q_1",
-            self.main_gate.config().q_1[state_idx],
-            F::ONE,
-        )?;
-        ctx.assign_fixed(|| "pre_round: q_i", self.main_gate.config().q_i, F::ONE)?;
         ctx.assign_fixed(|| "pre_round: q_o", self.main_gate.config().q_o, -F::ONE)?;
         ctx.assign_fixed(|| "pre_round: rc", self.main_gate.config().rc, rc_val)?;
         let out = ctx.assign_advice(|| "pre_round: out", self.main_gate.config().out, out_val)?;
@@ -107,11 +148,11 @@ impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE>
         let mut q_5_vals = [F::ZERO; T];
         let q_o_val = -F::ONE;
 
-        let r_f = self.spec.r_f() / 2;
+        let r_f = self.spec.full_rounds() / 2;
         let constants = if is_first_half_full {
-            self.spec.constants().start()
+            self.spec.start_constants()
         } else {
-            self.spec.constants().end()
+            self.spec.end_constants()
         };
         let rcs = if is_first_half_full {
             constants[round_idx + 1]
@@ -122,9 +163,9 @@ impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE>
         };
 
         let mds = if is_first_half_full && round_idx == r_f - 1 {
-            self.spec.mds_matrices().pre_sparse_mds().rows()
+            self.spec.pre_sparse_mds()
         } else {
-            self.spec.mds_matrices().mds().rows()
+            self.spec.mds()
         };
         let mds_row = mds[state_idx];
 
@@ -181,12 +222,8 @@ impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE>
         let mut q_5_vals = [F::ZERO; T];
         let q_o_val = -F::ONE;
 
-        let constants = self.spec.constants().partial();
-        let rc = constants[round_idx];
-
-        let sparse_mds = self.spec.mds_matrices().sparse_matrices();
-        let row = sparse_mds[round_idx].row();
-        let col_hat = sparse_mds[round_idx].col_hat();
+        let rc = self.spec.partial_constants()[round_idx];
+        let (row, col_hat) = self.spec.sparse_mds(round_idx);
 
         for (i, s) in state.iter().enumerate() {
             state_vals[i] = s.value().copied();
@@ -259,17 +296,28 @@ impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE>
     pub fn permutation(
         &self,
         ctx: &mut RegionCtx<'_, F>,
-        inputs: Vec<F>,
+        inputs: Vec<WrapValue<F>>,
         init_state: &[AssignedValue<F>; T],
+    ) -> Result<[AssignedValue<F>; T], Error> {
+        self.permute(ctx, inputs, Some(init_state))
+    }
+
+    /// [`Self::permutation`] of `init_state`, or of the initial state of the
+    /// sponge if `None`, see [`Self::absorb_word`]
+    pub(crate) fn permute(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        inputs: Vec<WrapValue<F>>,
+        init_state: Option<&[AssignedValue<F>; T]>,
     ) -> Result<[AssignedValue<F>; T], Error> {
         let mut state = Vec::new();
         for i in 0..T {
-            let si = self.pre_round(ctx, inputs.clone(), i, init_state)?;
+            let si = self.absorb_word(ctx, inputs.clone(), i, init_state)?;
             state.push(si);
         }
 
-        let r_f = self.spec.r_f() / 2;
-        let r_p = self.spec.constants().partial().len();
+        let r_f = self.spec.full_rounds() / 2;
+        let r_p = self.spec.partial_rounds();
 
         for round_idx in 0..r_f {
             let mut next_state = Vec::new();
@@ -314,49 +362,84 @@ impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonChip<F, T, RATE>
         Ok(res)
     }
 
+    /// Buffers `inputs` as fresh witnesses
     pub fn update(&mut self, inputs: Vec<F>) {
-        self.buf.extend(inputs)
+        self.buf
+            .extend(inputs.into_iter().map(|input| Value::known(input).into()))
     }
 
+    /// Buffers cells assigned elsewhere in the circuit. They are copied into
+    /// the permutation rows, so the digest is bound to their values.
+    pub fn update_assigned(&mut self, inputs: &[AssignedValue<F>]) {
+        self.buf.extend(inputs.iter().map(WrapValue::from))
+    }
+
+    /// Hashes everything passed to [`Self::update`] from the initial state,
+    /// padded like [`PoseidonHash`](crate::poseidon_hash::PoseidonHash). The
+    /// buffer is kept, so unlike the native sponge a second squeeze assigns
+    /// the same digest again rather than the next output of the stream.
     pub fn squeeze(&mut self, ctx: &mut RegionCtx<'_, F>) -> Result<AssignedValue<F>, Error> {
         let buf = self.buf.clone();
         let exact = buf.len() % RATE == 0;
 
-        let mut state: [_; T] = self
-            .main_gate
-            .config()
-            .state
-            .iter()
-            .zip(poseidon::State::<F, T>::default().words().iter())
-            .map(|(col, val)| ctx.assign_advice(|| "initial state", *col, Value::known(*val)))
-            .collect::<Result<Vec<_>, _>>()?
-            .try_into()
-            .expect("Safe, because zip two arrays");
-
+        let mut state = None;
         for chunk in buf.chunks(RATE) {
-            let next_state = self.permutation(ctx, chunk.to_vec(), &state)?;
-            state = next_state;
+            state = Some(self.permute(ctx, chunk.to_vec(), state.as_ref())?);
         }
 
         if exact {
-            let next_state = self.permutation(ctx, Vec::new(), &state)?;
-            state = next_state;
+            state = Some(self.permute(ctx, Vec::new(), state.as_ref())?);
         }
 
+        let state = state.expect("at least one permutation");
         Ok(state[1].clone())
     }
 }
 
+/// The circuit counterpart of the [`ROTrait`](crate::ro_types::ROTrait)
+/// implementation of [`PoseidonHash`](crate::poseidon_hash::PoseidonHash).
+/// Every squeeze hashes all the elements absorbed so far, see
+/// [`PoseidonChip::squeeze`].
+impl<C, F, const T: usize, const RATE: usize> ROCircuitTrait<C> for PoseidonChip<F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    type Constants = PrecomputedSpec<F, T, RATE>;
+    type Config = MainGateConfig<T>;
+
+    fn new(config: Self::Config, constants: Self::Constants) -> Self {
+        PoseidonChip::new(config, constants)
+    }
+
+    fn absorb(&mut self, input: &AssignedValue<F>) {
+        self.update_assigned(std::slice::from_ref(input));
+    }
+
+    fn squeeze(&mut self, ctx: &mut RegionCtx<'_, F>) -> Result<AssignedValue<F>, Error> {
+        PoseidonChip::squeeze(self, ctx)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner},
+        dev::{FailureLocation, MockProver, VerifyFailure},
         plonk::{Circuit, Column, ConstraintSystem, Instance},
     };
-    use halo2curves::{group::ff::FromUniformBytes, pasta::Fp};
+    use halo2curves::{
+        group::ff::{Field, FromUniformBytes},
+        pasta::{EqAffine, Fp},
+    };
+    use std::{cell::Cell, env, fs};
 
     use super::*;
-    use crate::main_gate::MainGateConfig;
+    use crate::{
+        main_gate::MainGateConfig,
+        poseidon_hash::{PoseidonHash, State},
+        ro_types::ROTrait,
+    };
 
     const T: usize = 3;
     const RATE: usize = 2;
@@ -369,13 +452,29 @@ mod tests {
         instance: Column<Instance>,
     }
 
+    /// Witness cell overwritten after the chip assigned the region, by row
+    #[derive(Clone, Copy)]
+    enum Tamper {
+        /// Sets the output of the row to an unrelated value
+        Out(usize),
+        /// Sets the absorbed word of a pre-round row to zero
+        Input(usize),
+    }
+
     struct TestCircuit<F: PrimeField> {
         inputs: Vec<F>,
+        tamper: Option<Tamper>,
+        /// Assigns the inputs in a region of their own and absorbs the cells
+        assigned: bool,
     }
 
     impl<F: PrimeField> TestCircuit<F> {
         fn new(inputs: Vec<F>) -> Self {
-            Self { inputs }
+            Self {
+                inputs,
+                tamper: None,
+                assigned: false,
+            }
         }
     }
 
@@ -384,7 +483,7 @@ mod tests {
         type FloorPlanner = SimpleFloorPlanner;
 
         fn without_witnesses(&self) -> Self {
-            Self { inputs: Vec::new() }
+            Self::new(Vec::new())
         }
 
         fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
@@ -401,14 +500,44 @@ mod tests {
             config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
-            let spec = Spec::<F, T, RATE>::new(R_F, R_P);
-            let mut pchip = PoseidonChip::new(config.pconfig, spec);
-            pchip.update(self.inputs.clone());
+            let spec = PrecomputedSpec::<F, T, RATE>::new(R_F, R_P);
+            let mut pchip = PoseidonChip::new(config.pconfig.clone(), spec);
+            if self.assigned {
+                let cells = layouter.assign_region(
+                    || "inputs",
+                    |region| {
+                        let ctx = &mut RegionCtx::new(region, 0);
+                        self.inputs
+                            .iter()
+                            .map(|input| {
+                                let cell = ctx.assign_advice(
+                                    || "input",
+                                    config.pconfig.state[0],
+                                    Value::known(*input),
+                                );
+                                ctx.next();
+                                cell
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    },
+                )?;
+                pchip.update_assigned(&cells);
+            } else {
+                pchip.update(self.inputs.clone());
+            }
             let output = layouter.assign_region(
                 || "poseidon hash",
                 |region| {
                     let ctx = &mut RegionCtx::new(region, 0);
-                    pchip.squeeze(ctx)
+                    let output = pchip.squeeze(ctx)?;
+                    let (column, row, value) = match self.tamper {
+                        None => return Ok(output),
+                        Some(Tamper::Out(row)) => (config.pconfig.out, row, F::from(12345)),
+                        Some(Tamper::Input(row)) => (config.pconfig.input, row, F::ZERO),
+                    };
+                    ctx.offset = row;
+                    ctx.assign_advice(|| "tampered", column, Value::known(value))?;
+                    Ok(output)
                 },
             )?;
             layouter.constrain_instance(output.cell(), config.instance, 0)?;
@@ -416,25 +545,378 @@ mod tests {
         }
     }
 
+    const K: u32 = 10;
+
+    fn out_hash() -> Fp {
+        // hex = 0x1cd3150d8e12454ff385da8a4d864af6d0f021529207b16dd6c3d8f2b52cfc67
+        Fp::from_str_vartime(
+            "13037709793114148810823325920380362524528554380279235267325741570708489436263",
+        )
+        .unwrap()
+    }
+
     #[test]
     fn test_mock() {
-        use halo2_proofs::dev::MockProver;
-        const K: u32 = 10;
         let mut inputs = Vec::new();
         for i in 0..5 {
             inputs.push(Fp::from(i as u64));
         }
         let circuit = TestCircuit::new(inputs);
-        // hex = 0x1cd3150d8e12454ff385da8a4d864af6d0f021529207b16dd6c3d8f2b52cfc67
-        let out_hash = Fp::from_str_vartime(
-            "13037709793114148810823325920380362524528554380279235267325741570708489436263",
-        )
-        .unwrap();
-        let public_inputs = vec![vec![out_hash]];
+        let public_inputs = vec![vec![out_hash()]];
         let prover = match MockProver::run(K, &circuit, public_inputs) {
             Ok(prover) => prover,
             Err(e) => panic!("{:#?}", e),
         };
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    /// The chip pads like the native sponge at and around block boundaries
+    #[test]
+    fn test_mock_rate_boundaries() {
+        for len in [0, 1, RATE, RATE + 1, 2 * RATE] {
+            let inputs = (0..len as u64).map(Fp::from).collect::<Vec<_>>();
+            let mut native =
+                PoseidonHash::<EqAffine, Fp, T, RATE>::new(PrecomputedSpec::new(R_F, R_P));
+            native.update(&inputs);
+            let expected = native.squeeze();
+            let prover =
+                MockProver::run(K, &TestCircuit::new(inputs), vec![vec![expected]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "{} inputs", len);
+        }
+    }
+
+    #[test]
+    fn test_mock_rejects_tampered_witness() {
+        // Each permutation takes T * (1 + R_F + R_P) = 24 rows: T pre-round
+        // rows, then T rows per round. The five inputs are absorbed as
+        // [0, 1], [2, 3] and [4] followed by the padding word.
+        let cases = [
+            // S-box and MDS row of the first full round
+            Tamper::Out(3),
+            // the S-box word of the first partial round
+            Tamper::Out(9),
+            // a word of the first partial round only mixed by the sparse MDS
+            Tamper::Out(10),
+            // the last full round, whose output feeds the next permutation
+            Tamper::Out(23),
+            // the second input, absorbed by the first permutation
+            Tamper::Input(2),
+        ];
+        for tamper in cases {
+            let row = match tamper {
+                Tamper::Out(row) | Tamper::Input(row) => row,
+            };
+            let circuit = TestCircuit {
+                inputs: (0..5).map(Fp::from).collect(),
+                tamper: Some(tamper),
+                assigned: false,
+            };
+            let prover = MockProver::run(K, &circuit, vec![vec![out_hash()]]).unwrap();
+            let failures = prover.verify().unwrap_err();
+            // the main gate itself must reject the row, not only the copy
+            // constraints into the next one
+            assert!(
+                failures.iter().any(|failure| matches!(
+                    failure,
+                    VerifyFailure::ConstraintNotSatisfied {
+                        location: FailureLocation::InRegion { offset, .. },
+                        ..
+                    } if *offset == row
+                )),
+                "row {} accepted: {:?}",
+                row,
+                failures
+            );
+        }
+    }
+
+    /// Assigns the layout of hashing `0..5`, then overwrites the third
+    /// permutation with the witness of a prover who absorbs `padding` in
+    /// place of the padding one and recomputes every later row
+    struct ForgedPaddingCircuit {
+        padding: Fp,
+    }
+
+    impl Circuit<Fp> for ForgedPaddingCircuit {
+        type Config = TestCircuitConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { padding: Fp::ONE }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::<Fp>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let spec = PrecomputedSpec::<Fp, T, RATE>::new(R_F, R_P);
+            let mut pchip = PoseidonChip::new(config.pconfig.clone(), spec.clone());
+            pchip.update((0..5).map(Fp::from).collect());
+            let block = |words: [Fp; RATE]| words.map(|word| Value::known(word).into()).to_vec();
+            let output = layouter.assign_region(
+                || "poseidon hash",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    pchip.squeeze(ctx)?;
+
+                    ctx.offset = 0;
+                    let state = pchip.permute(ctx, block([Fp::from(0), Fp::from(1)]), None)?;
+                    let state =
+                        pchip.permutation(ctx, block([Fp::from(2), Fp::from(3)]), &state)?;
+                    let state =
+                        pchip.permutation(ctx, block([Fp::from(4), self.padding]), &state)?;
+                    // the fixed cells of the padding row, as the honest
+                    // squeeze assigned them
+                    ctx.offset = 2 * 24 + 2;
+                    ctx.assign_fixed(|| "q_i", config.pconfig.q_i, Fp::ZERO)?;
+                    let rc = spec.start_constants()[0][2] + Fp::ONE;
+                    ctx.assign_fixed(|| "rc", config.pconfig.rc, rc)?;
+                    Ok(state[1].clone())
+                },
+            )?;
+            layouter.constrain_instance(output.cell(), config.instance, 0)?;
+            Ok(())
+        }
+    }
+
+    /// Changing the padding word and recomputing the rest of the witness
+    /// consistently is caught where the padding is absorbed
+    #[test]
+    fn test_mock_rejects_forged_padding() {
+        let spec = PrecomputedSpec::<Fp, T, RATE>::new(R_F, R_P);
+        for padding in [Fp::ONE, Fp::from(2)] {
+            let mut state = State::<Fp, T, RATE>::new(initial_state());
+            state.permutation(&spec, &[Fp::from(0), Fp::from(1)]);
+            state.permutation(&spec, &[Fp::from(2), Fp::from(3)]);
+            state.permutation(&spec, &[Fp::from(4), padding]);
+            let circuit = ForgedPaddingCircuit { padding };
+            let prover = MockProver::run(K, &circuit, vec![vec![state.inner[1]]]).unwrap();
+            if padding == Fp::ONE {
+                // the honest witness, whose input cell is ignored
+                assert_eq!(state.inner[1], out_hash());
+                assert_eq!(prover.verify(), Ok(()));
+                continue;
+            }
+            let failures = prover.verify().unwrap_err();
+            assert!(
+                failures.iter().any(|failure| matches!(
+                    failure,
+                    VerifyFailure::ConstraintNotSatisfied {
+                        location: FailureLocation::InRegion { offset, .. },
+                        ..
+                    } if *offset == 2 * 24 + 2
+                )),
+                "{:?}",
+                failures
+            );
+        }
+    }
+
+    /// Cells assigned outside the chip hash like the native sponge, and the
+    /// chip's copies of them cannot be changed
+    #[test]
+    fn test_mock_assigned_inputs() {
+        let circuit = TestCircuit {
+            inputs: (0..5).map(Fp::from).collect(),
+            tamper: None,
+            assigned: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![vec![out_hash()]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the copy of the second input, in the pre-round of the first
+        // permutation
+        let circuit = TestCircuit {
+            tamper: Some(Tamper::Input(2)),
+            ..circuit
+        };
+        let prover = MockProver::run(K, &circuit, vec![vec![out_hash()]]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert!(
+            failures
+                .iter()
+                .any(|failure| matches!(failure, VerifyFailure::Permutation { .. })),
+            "{:?}",
+            failures
+        );
+    }
+
+    /// Absorbs `inputs` through [`ROCircuitTrait`], assigned in a region of
+    /// their own
+    struct RoCircuit {
+        inputs: Vec<Fp>,
+    }
+
+    impl Circuit<Fp> for RoCircuit {
+        type Config = TestCircuitConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { inputs: Vec::new() }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::<Fp>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let mut ro = <PoseidonChip<Fp, T, RATE> as ROCircuitTrait<EqAffine>>::new(
+                config.pconfig.clone(),
+                PrecomputedSpec::new(R_F, R_P),
+            );
+            let cells = layouter.assign_region(
+                || "inputs",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    self.inputs
+                        .iter()
+                        .map(|input| {
+                            let cell = ctx.assign_advice(
+                                || "input",
+                                config.pconfig.state[0],
+                                Value::known(*input),
+                            );
+                            ctx.next();
+                            cell
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                },
+            )?;
+            for cell in &cells {
+                ROCircuitTrait::<EqAffine>::absorb(&mut ro, cell);
+            }
+            let output = layouter.assign_region(
+                || "ro",
+                |region| {
+                    ROCircuitTrait::<EqAffine>::squeeze(&mut ro, &mut RegionCtx::new(region, 0))
+                },
+            )?;
+            layouter.constrain_instance(output.cell(), config.instance, 0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ro_circuit_matches_native() {
+        for len in [0, 1, RATE, 5] {
+            let inputs = (0..len as u64).map(Fp::from).collect::<Vec<_>>();
+            let mut native = <PoseidonHash<EqAffine, Fp, T, RATE> as ROTrait<EqAffine>>::new(
+                PrecomputedSpec::new(R_F, R_P),
+            );
+            native.update(&inputs);
+            let challenge = ROTrait::<EqAffine>::squeeze(&mut native);
+            let prover = MockProver::run(K, &RoCircuit { inputs }, vec![vec![challenge]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "{} inputs", len);
+        }
+    }
+
+    /// Hashes `inputs` zeros and records the rows used by the chip
+    struct SizeCircuit<const T: usize, const RATE: usize> {
+        r_f: usize,
+        r_p: usize,
+        inputs: usize,
+        rows: Cell<usize>,
+    }
+
+    impl<const T: usize, const RATE: usize> Circuit<Fp> for SizeCircuit<T, RATE> {
+        type Config = MainGateConfig<T>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                r_f: self.r_f,
+                r_p: self.r_p,
+                inputs: self.inputs,
+                rows: Cell::new(0),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let adv_cols = (0..T + 2).map(|_| meta.advice_column()).collect::<Vec<_>>();
+            let fix_cols = (0..2 * T + 4)
+                .map(|_| meta.fixed_column())
+                .collect::<Vec<_>>();
+            MainGate::configure(meta, &mut adv_cols.into_iter(), &mut fix_cols.into_iter())
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let mut pchip =
+                PoseidonChip::<Fp, T, RATE>::new(config, PrecomputedSpec::new(self.r_f, self.r_p));
+            pchip.update(vec![Fp::from(0); self.inputs]);
+            layouter.assign_region(
+                || "poseidon hash",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    pchip.squeeze(ctx)?;
+                    self.rows.set(ctx.offset());
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    /// One line of `baselines/poseidon_chip.txt`
+    fn size<const T: usize, const RATE: usize>(r_f: usize, r_p: usize, inputs: usize) -> String {
+        let circuit = SizeCircuit::<T, RATE> {
+            r_f,
+            r_p,
+            inputs,
+            rows: Cell::new(0),
+        };
+        MockProver::run(11, &circuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        SizeCircuit::<T, RATE>::configure(&mut cs);
+        format!(
+            "t={} r_f={} r_p={} inputs={}: rows={} advice={} fixed={} instance={} gates={} lookups={} degree={}",
+            T,
+            r_f,
+            r_p,
+            inputs,
+            circuit.rows.get(),
+            cs.num_advice_columns(),
+            cs.num_fixed_columns(),
+            cs.num_instance_columns(),
+            cs.gates().len(),
+            cs.lookups().len(),
+            cs.degree(),
+        )
+    }
+
+    /// Compares the chip's size against the recorded baselines. Run with
+    /// `UPDATE_BASELINES=1` to rewrite them after an intended layout change,
+    /// and review the diff like any other.
+    #[test]
+    fn test_size_baselines() {
+        let sizes = [
+            size::<3, 2>(8, 56, 3),
+            size::<3, 2>(8, 56, 4),
+            size::<4, 3>(8, 56, 4),
+            size::<4, 3>(8, 56, 6),
+            size::<5, 4>(8, 56, 5),
+            size::<5, 4>(8, 56, 8),
+        ];
+        let actual = sizes.join("\n") + "\n";
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/baselines/poseidon_chip.txt");
+        if env::var_os("UPDATE_BASELINES").is_some() {
+            fs::write(path, &actual).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            actual,
+            "chip size changed; rerun with UPDATE_BASELINES=1 if intended"
+        );
+    }
 }
@@ -1,25 +1,80 @@
 #![allow(dead_code)]
-use std::{iter, marker::PhantomData, mem};
+use std::{marker::PhantomData, mem};
 
 use halo2_proofs::arithmetic::CurveAffine;
-use halo2curves::group::ff::{FromUniformBytes, PrimeField};
-use poseidon::{SparseMDSMatrix, Spec};
+use halo2curves::{
+    group::ff::{FromUniformBytes, PrimeField},
+    Coordinates,
+};
 
-use crate::ro_types::{ROConstantsTrait, ROTrait};
+use crate::{
+    cached::CachedSpec,
+    encoding,
+    precomputed::PrecomputedSpec,
+    ro_types::{ROConstantsTrait, ROTrait},
+    rounds::round_numbers,
+    spec::PoseidonSpec,
+    transcript::BITS,
+};
 
 // adapted from: https://github.com/privacy-scaling-explorations/snark-verifier
 
+/// The initial state of every sponge, `[2^64, 0, .., 0]`
+pub(crate) fn initial_state<F: PrimeField, const T: usize>() -> [F; T] {
+    let mut state = [F::ZERO; T];
+    state[0] = F::from_u128(1 << 64);
+    state
+}
+
+/// The digest of `elements` with a sponge of width 3 and the round numbers
+/// of [`round_numbers`] at 128 bits, equal to the one of a [`PoseidonHash`]
+/// with these parameters that absorbed them
+pub(crate) fn digest_128<F: PrimeField + FromUniformBytes<64>>(elements: &[F]) -> F {
+    let (r_f, r_p) = round_numbers(F::NUM_BITS, 3, 128);
+    let mut state = State::<F, 3, 2>::new(initial_state());
+    state.absorb(PrecomputedSpec::cached(r_f, r_p), elements);
+    state.inner[1]
+}
+
 #[derive(Clone, Debug)]
-struct State<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> {
-    inner: [F; T],
+pub(crate) struct State<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> {
+    pub(crate) inner: [F; T],
+    /// State at the end of every round since the last [`PoseidonHash::take_trace`]
+    #[cfg(any(test, feature = "debug-trace"))]
+    pub(crate) trace: Vec<[F; T]>,
+    /// Field operations since the last [`PoseidonHash::take_op_count`]
+    #[cfg(feature = "op-counters")]
+    pub(crate) ops: crate::op_count::OpCount,
 }
 
 impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> State<F, T, RATE> {
-    fn new(inner: [F; T]) -> Self {
-        Self { inner }
+    pub(crate) fn new(inner: [F; T]) -> Self {
+        Self {
+            inner,
+            #[cfg(any(test, feature = "debug-trace"))]
+            trace: Vec::new(),
+            #[cfg(feature = "op-counters")]
+            ops: Default::default(),
+        }
+    }
+
+    fn end_round(&mut self) {
+        #[cfg(any(test, feature = "debug-trace"))]
+        self.trace.push(self.inner);
+    }
+
+    #[allow(unused_variables)]
+    fn count(&mut self, mul: usize, square: usize, add: usize) {
+        #[cfg(feature = "op-counters")]
+        {
+            self.ops.mul += mul;
+            self.ops.square += square;
+            self.ops.add += add;
+        }
     }
 
     fn sbox_full(&mut self, constants: &[F; T]) {
+        self.count(2 * T, 2 * T, T);
         let pow5 = |v: &F| v.square() * v.square() * v;
         for (state, constant) in self.inner.iter_mut().zip(constants.iter()) {
             *state = pow5(state) + *constant;
@@ -27,6 +82,7 @@ impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> St
     }
 
     fn sbox_part(&mut self, constant: &F) {
+        self.count(2, 2, 1);
         let pow5 = |v: &F| v.square() * v.square() * v;
         self.inner[0] = pow5(&self.inner[0]) + *constant;
     }
@@ -34,6 +90,8 @@ impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> St
     fn pre_round(&mut self, inputs: &[F], pre_constants: &[F; T]) {
         assert!(RATE == T - 1);
         assert!(inputs.len() <= RATE);
+        // two additions per input, one per other word and the padding
+        self.count(0, 0, T + inputs.len() + (inputs.len() < RATE) as usize);
 
         self.inner[0] += pre_constants[0];
         self.inner
@@ -59,45 +117,91 @@ impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> St
     }
 
     fn apply_mds(&mut self, mds: &[[F; T]; T]) {
-        self.inner = mds
+        self.count(T * T, 0, T * T);
+        // the product reads every word, so it goes through a copy on the stack
+        let scratch = self.inner;
+        for (state, row) in self.inner.iter_mut().zip(mds) {
+            *state = row
+                .iter()
+                .zip(scratch.iter())
+                .fold(F::ZERO, |acc, (mij, sj)| acc + *sj * *mij);
+        }
+    }
+
+    fn apply_sparse_mds(&mut self, (row, col_hat): (&[F; T], &[F; RATE])) {
+        self.count(T + RATE, 0, T + RATE);
+        // only word 0 is read by the other words, so the update is in place
+        let first = self.inner[0];
+        self.inner[0] = row
             .iter()
-            .map(|row| {
-                row.iter()
-                    .clone()
-                    .zip(self.inner.iter())
-                    .fold(F::ZERO, |acc, (mij, sj)| acc + *sj * *mij)
-            })
-            .collect::<Vec<F>>()
-            .try_into()
-            .unwrap();
+            .zip(self.inner.iter())
+            .fold(F::ZERO, |acc, (vi, si)| acc + *vi * *si);
+        for (state, coeff) in self.inner.iter_mut().skip(1).zip(col_hat) {
+            *state += *coeff * first;
+        }
     }
 
-    fn apply_sparse_mds(&mut self, mds: &SparseMDSMatrix<F, T, RATE>) {
-        self.inner = iter::once(
-            mds.row()
-                .iter()
-                .cloned()
-                .zip(self.inner.iter())
-                .fold(F::ZERO, |acc, (vi, si)| acc + vi * si),
-        )
-        .chain(
-            mds.col_hat()
-                .iter()
-                .zip(self.inner.iter().skip(1))
-                .map(|(coeff, state)| *coeff * self.inner[0] + *state),
-        )
-        .collect::<Vec<F>>()
-        .try_into()
-        .unwrap();
+    /// Adds `inputs` to the rate part, padding with a single one if there are
+    /// fewer than `RATE` of them, and applies the permutation
+    pub(crate) fn permutation(
+        &mut self,
+        spec: &(impl PoseidonSpec<F, T, RATE> + ?Sized),
+        inputs: &[F],
+    ) {
+        let r_f = spec.full_rounds() / 2;
+        let mds = spec.mds();
+        let pre_sparse_mds = spec.pre_sparse_mds();
+
+        // First half of the full rounds
+        let constants = spec.start_constants();
+        self.pre_round(inputs, &constants[0]);
+        for constants in constants.iter().skip(1).take(r_f - 1) {
+            self.sbox_full(constants);
+            self.apply_mds(&mds);
+            self.end_round();
+        }
+        self.sbox_full(constants.last().unwrap());
+        self.apply_mds(&pre_sparse_mds);
+        self.end_round();
+
+        // Partial rounds
+        for (round, constant) in spec.partial_constants().iter().enumerate() {
+            self.sbox_part(constant);
+            self.apply_sparse_mds(spec.sparse_mds(round));
+            self.end_round();
+        }
+
+        // Second half of the full rounds
+        for constants in spec.end_constants().iter() {
+            self.sbox_full(constants);
+            self.apply_mds(&mds);
+            self.end_round();
+        }
+        self.sbox_full(&[F::ZERO; T]);
+        self.apply_mds(&mds);
+        self.end_round();
+    }
+
+    /// Absorbs `inputs` `RATE` at a time with the padding of
+    /// [`PoseidonHash`], `inputs.len() / RATE + 1` permutations
+    pub(crate) fn absorb(&mut self, spec: &(impl PoseidonSpec<F, T, RATE> + ?Sized), inputs: &[F]) {
+        let exact = inputs.len() % RATE == 0;
+
+        for chunk in inputs.chunks(RATE) {
+            self.permutation(spec, chunk);
+        }
+        if exact {
+            self.permutation(spec, &[]);
+        }
     }
 }
 
-impl<F, const T: usize, const RATE: usize> ROConstantsTrait for Spec<F, T, RATE>
+impl<F, const T: usize, const RATE: usize> ROConstantsTrait for PrecomputedSpec<F, T, RATE>
 where
     F: PrimeField + FromUniformBytes<64>,
 {
     fn new(r_f: usize, r_p: usize) -> Self {
-        Spec::new(r_f, r_p)
+        PrecomputedSpec::new(r_f, r_p)
     }
 }
 
@@ -106,14 +210,13 @@ where
     C: CurveAffine<ScalarExt = F>,
     F: PrimeField + FromUniformBytes<64>,
 {
-    type Constants = Spec<F, T, RATE>;
+    type Constants = PrecomputedSpec<F, T, RATE>;
     fn new(constants: Self::Constants) -> Self {
-        Self {
-            spec: constants,
-            state: State::new(poseidon::State::default().words()),
-            buf: Vec::new(),
-            _marker: PhantomData,
-        }
+        Self::new_with_spec(constants)
+    }
+
+    fn absorb(&mut self, input: C::Scalar) {
+        self.update(&[input]);
     }
 
     fn squeeze(&mut self) -> C::Scalar {
@@ -121,6 +224,97 @@ where
     }
 }
 
+/// 2-to-1 compression of `left` and `right` with a single permutation and
+/// no allocation, for Merkle trees and other fixed-arity hashing
+///
+/// This is the digest of a [`PoseidonHash`] with
+/// [`Padding::FixedLength`]`(2)`: the capacity word is `2 * 2^64` and the
+/// block `[left, right]`, filled with zeros up to `RATE`, is absorbed without
+/// padding. It differs from the default sponge digest of the same two
+/// elements, which [`crate::merkle`] keeps so that its roots do not change.
+///
+/// # Panics
+///
+/// If `RATE` is below 2
+pub fn hash_two<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    left: F,
+    right: F,
+) -> F
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    assert!(RATE >= 2, "compression needs a rate of two");
+    let mut inner = [F::ZERO; T];
+    inner[0] = F::from_u128(2 << 64);
+    let mut state = State::<F, T, RATE>::new(inner);
+    let mut block = [F::ZERO; RATE];
+    block[0] = left;
+    block[1] = right;
+    state.permutation(spec, &block);
+    state.inner[1]
+}
+
+/// How a squeeze pads the elements it absorbs, see
+/// [`PoseidonHash::new_with_padding`]
+///
+/// Only [`Padding::OneZeroStar`] is injective on its own and only it is
+/// implemented by [`PoseidonChip`]. The modes are not separated from each
+/// other: `FixedLength(1)` and `None` absorb `[x]` the same way.
+///
+/// [`PoseidonChip`]: crate::poseidon_circuit::PoseidonChip
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Padding {
+    /// A one after the elements, in a block of its own when they fill the
+    /// last one, as described on [`PoseidonHash`]
+    #[default]
+    OneZeroStar,
+    /// Exactly this many elements are absorbed, in total. The length is put
+    /// in the capacity word, `len * 2^64`, like halo2_gadgets'
+    /// `ConstantLength`, so the last block is only filled with zeros and no
+    /// permutation is spent on padding.
+    FixedLength(usize),
+    /// The last block is filled with zeros. `m` and `m || [0]` collide, so
+    /// the caller must make the length unambiguous.
+    None,
+}
+
+/// Native Poseidon sponge, the PSE `poseidon` construction with its
+/// permutation optimized like [`PoseidonChip`]'s.
+///
+/// # Absorption and padding
+///
+/// The state starts as `[2^64, 0, .., 0]`, the capacity word first. Elements
+/// buffered by [`Self::update`] and [`Self::update_bytes`] are absorbed at the
+/// next squeeze, `RATE` at a time: each block is added to words `1..=RATE`
+/// and followed by one permutation. The last block is padded with a single
+/// one added after its elements. When the buffered length is a multiple of
+/// `RATE`, including zero, the padding gets a block and a permutation of its
+/// own, so `k` elements always take `k / RATE + 1` permutations and any two
+/// distinct inputs, such as `m` and `m || [0]`, are padded differently.
+/// A block is absorbed as soon as it is full, which only changes when the
+/// permutations run, so the sponge holds fewer than `RATE` elements however
+/// long the input.
+///
+/// A squeeze returns word 1 of the state and keeps the state: squeezing
+/// again without an update absorbs an empty block, that is permutes once more
+/// with the padding alone, so consecutive squeezes give a stream of outputs.
+/// A squeeze on a fresh sponge is the digest of the empty input.
+///
+/// # Interleaving
+///
+/// The state is never reset, so absorbing and squeezing can alternate freely
+/// like a duplex: elements buffered after a squeeze are absorbed, with their
+/// own padding, into the state that squeeze left, and the next output depends
+/// on everything absorbed and squeezed before it. In
+/// `update(a); squeeze(); update(b); squeeze()`, the second output is the
+/// digest of `b` computed from the state the first squeeze left rather than
+/// from the initial state, which is what a Fiat-Shamir transcript relies on.
+///
+/// These rules match the PSE `poseidon` sponge and are part of the stable
+/// digests checked by the golden files; changing them is a breaking change.
+///
+/// [`PoseidonChip`]: crate::poseidon_circuit::PoseidonChip
 #[derive(Clone, Debug)]
 pub struct PoseidonHash<
     C: CurveAffine<ScalarExt = F>,
@@ -128,9 +322,18 @@ pub struct PoseidonHash<
     const T: usize,
     const RATE: usize,
 > {
-    spec: Spec<F, T, RATE>,
-    state: State<F, T, RATE>,
+    spec: PrecomputedSpec<F, T, RATE>,
+    pub(crate) state: State<F, T, RATE>,
     buf: Vec<F>,
+    /// Tail of the byte string being absorbed by [`Self::update_bytes`], if any
+    bytes: Option<Vec<u8>>,
+    /// Width of the limbs of [`Self::absorb_base`]
+    limb_bits: usize,
+    padding: Padding,
+    /// Elements absorbed by all squeezes so far
+    absorbed: usize,
+    /// Elements of full blocks absorbed since the last squeeze
+    streamed: usize,
     _marker: PhantomData<C>,
 }
 
@@ -141,66 +344,637 @@ impl<
         const RATE: usize,
     > PoseidonHash<C, F, T, RATE>
 {
-    fn update(&mut self, elements: &[F]) {
-        self.buf.extend_from_slice(elements);
+    fn new_with_spec(spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        Self {
+            spec,
+            state: State::new(initial_state()),
+            buf: Vec::with_capacity(RATE),
+            bytes: None,
+            limb_bits: BITS,
+            padding: Padding::OneZeroStar,
+            absorbed: 0,
+            streamed: 0,
+            _marker: PhantomData,
+        }
     }
 
-    fn output(&mut self) -> F {
-        let buf = mem::take(&mut self.buf);
-        let exact = buf.len() % RATE == 0;
+    /// A sponge padding with `padding` instead of [`Padding::OneZeroStar`]
+    pub fn new_with_padding(spec: PrecomputedSpec<F, T, RATE>, padding: Padding) -> Self {
+        let mut hasher = <Self as ROTrait<C>>::new(spec);
+        if let Padding::FixedLength(len) = padding {
+            hasher.state.inner[0] = F::from_u128((len as u128) << 64);
+        }
+        hasher.padding = padding;
+        hasher
+    }
+
+    /// A sponge separated from every other domain: the capacity word is the
+    /// digest of `domain`, absorbed with [`Self::update_bytes`] by a fresh
+    /// sponge, instead of `2^64`, so protocols sharing a spec get unrelated
+    /// transcripts without absorbing a tag of their own. An empty domain is
+    /// still separated from [`ROTrait::new`].
+    pub fn new_with_domain(spec: PrecomputedSpec<F, T, RATE>, domain: &[u8]) -> Self {
+        let mut tag = <Self as ROTrait<C>>::new(spec.clone());
+        tag.update_bytes(domain);
+        let mut hasher = <Self as ROTrait<C>>::new(spec);
+        hasher.state.inner[0] = tag.output();
+        hasher
+    }
+
+    /// A keyed sponge for message authentication: the capacity word is `key`
+    /// instead of `2^64`. The key should be uniformly random; the tag of a
+    /// message is the squeeze after absorbing it, checked with
+    /// [`Self::verify_tag`].
+    pub fn new_keyed(spec: PrecomputedSpec<F, T, RATE>, key: F) -> Self {
+        let mut hasher = <Self as ROTrait<C>>::new(spec);
+        hasher.state.inner[0] = key;
+        hasher
+    }
 
-        for chunk in buf.chunks(RATE) {
-            self.permutation(chunk);
+    /// Squeezes and compares the output with `tag` in constant time
+    pub fn verify_tag(&mut self, tag: &F) -> bool {
+        self.output().ct_eq(tag).into()
+    }
+
+    /// Buffers `elements`, absorbing every block they fill; the rest is
+    /// absorbed on the next squeeze
+    pub fn update(&mut self, elements: &[F]) {
+        self.finish_bytes();
+        for element in elements {
+            self.push(*element);
         }
-        if exact {
-            self.permutation(&[]);
+    }
+
+    /// Buffers `value` as the element of its value
+    pub fn absorb_u64(&mut self, value: u64) {
+        self.update(&[F::from(value)]);
+    }
+
+    /// Buffers `value` as the element of its value, which every field of at
+    /// least 129 bits represents exactly
+    pub fn absorb_u128(&mut self, value: u128) {
+        self.update(&[F::from_u128(value)]);
+    }
+
+    /// Buffers `value` as the element of its value
+    pub fn absorb_usize(&mut self, value: usize) {
+        self.absorb_u64(value as u64);
+    }
+
+    /// Buffers `value` as `0` or `1`
+    pub fn absorb_bool(&mut self, value: bool) {
+        self.absorb_u64(value as u64);
+    }
+
+    /// Buffers `values` of `bits` bits each, packed `CAPACITY / bits` to an
+    /// element, the first value in the lowest bits
+    ///
+    /// The number of values is not absorbed, and a trailing zero value packs
+    /// like a missing one, so it should be fixed by the protocol or absorbed
+    /// before them.
+    ///
+    /// # Panics
+    ///
+    /// If `bits` is zero or above 64, or a value does not fit in `bits` bits
+    pub fn absorb_packed(&mut self, values: &[u64], bits: usize) {
+        assert!(bits > 0 && bits <= 64, "invalid packing width");
+        assert!(
+            values.iter().all(|value| bits == 64 || value >> bits == 0),
+            "value wider than the packing width"
+        );
+        let radix = F::from(2).pow_vartime([bits as u64]);
+        let per_element = F::CAPACITY as usize / bits;
+        let elements = values
+            .chunks(per_element)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .rev()
+                    .fold(F::ZERO, |acc, value| acc * radix + F::from(*value))
+            })
+            .collect::<Vec<_>>();
+        self.update(&elements);
+    }
+
+    /// Number of bytes packed into each element by [`Self::update_bytes`]
+    pub const BYTES_PER_ELEMENT: usize = F::CAPACITY as usize / 8;
+
+    /// Buffers `bytes` as part of a byte string. Consecutive calls extend the same
+    /// string, so splitting the input across calls does not change the digest.
+    ///
+    /// The string is packed into elements of [`Self::BYTES_PER_ELEMENT`]
+    /// little-endian bytes. It ends at the next [`Self::update`] or squeeze,
+    /// where a `0x01` byte is appended to the last, possibly empty, chunk so
+    /// that trailing zero bytes remain significant.
+    ///
+    /// The terminator takes the place of a length suffix: no element encodes
+    /// the length of the string, so these digests differ from those of
+    /// packings that absorb one. Unlike a suffix, it does not need the length
+    /// before the last byte is seen.
+    pub fn update_bytes(&mut self, bytes: &[u8]) {
+        let pending = self.bytes.get_or_insert_with(Vec::new);
+        let fill = (Self::BYTES_PER_ELEMENT - pending.len()).min(bytes.len());
+        pending.extend_from_slice(&bytes[..fill]);
+        if pending.len() < Self::BYTES_PER_ELEMENT {
+            return;
         }
+        let first = Self::pack(&mem::take(pending));
+        self.push(first);
+        let chunks = bytes[fill..].chunks_exact(Self::BYTES_PER_ELEMENT);
+        let tail = chunks.remainder();
+        for chunk in chunks {
+            self.push(Self::pack(chunk));
+        }
+        self.bytes = Some(tail.to_vec());
+    }
 
-        self.state.inner[1]
+    /// Buffers `element`, absorbing the block it fills. Only the last block
+    /// is padded, so this gives the same states as absorbing everything at
+    /// the squeeze.
+    fn push(&mut self, element: F) {
+        self.buf.push(element);
+        if self.buf.len() == RATE {
+            self.state.permutation(&self.spec, &self.buf);
+            self.buf.clear();
+            self.streamed += RATE;
+        }
     }
 
-    fn permutation(&mut self, inputs: &[F]) {
-        let r_f = self.spec.r_f() / 2;
-        let mds = self.spec.mds_matrices().mds().rows();
-        let pre_sparse_mds = self.spec.mds_matrices().pre_sparse_mds().rows();
-        let sparse_matrices = self.spec.mds_matrices().sparse_matrices();
+    fn finish_bytes(&mut self) {
+        if let Some(mut tail) = self.bytes.take() {
+            tail.push(1);
+            self.push(Self::pack(&tail));
+        }
+    }
 
-        // First half of the full rounds
-        let constants = self.spec.constants().start();
-        self.state.pre_round(inputs, &constants[0]);
-        for constants in constants.iter().skip(1).take(r_f - 1) {
-            self.state.sbox_full(constants);
-            self.state.apply_mds(&mds);
+    /// Sets the width of the limbs of [`Self::absorb_base`], [`BITS`] by
+    /// default
+    ///
+    /// # Panics
+    ///
+    /// If `bits` is zero or above the capacity of `F`, so that limbs would
+    /// not be embedded injectively
+    pub fn with_limb_bits(mut self, bits: usize) -> Self {
+        assert!(
+            bits > 0 && bits <= F::CAPACITY as usize,
+            "invalid limb width"
+        );
+        self.limb_bits = bits;
+        self
+    }
+
+    /// Elements buffered by [`Self::absorb_base`]
+    pub fn base_limbs(&self) -> usize {
+        (C::Base::NUM_BITS as usize).div_ceil(self.limb_bits)
+    }
+
+    /// Buffers an element of the base field of `C` as [`Self::base_limbs`]
+    /// little-endian limbs of the configured width. Every limb is below
+    /// `2^bits`, which is at most `2^CAPACITY`, so distinct elements absorb
+    /// distinct limbs.
+    pub fn absorb_base(&mut self, value: C::Base) {
+        self.update(&encoding::to_limbs::<_, F>(&value, self.limb_bits));
+    }
+
+    /// Elements buffered by [`Self::absorb_point`]
+    pub fn point_elements(&self) -> usize {
+        2 * self.base_limbs() + 1
+    }
+
+    /// Buffers `point` as its coordinates, each absorbed with
+    /// [`Self::absorb_base`], followed by an identity flag: `0` for an affine
+    /// point, and `1` after all-zero limbs for the identity, so that no two
+    /// points absorb the same elements.
+    ///
+    /// Unlike [`PoseidonTranscript`](crate::transcript::PoseidonTranscript),
+    /// which absorbs each coordinate reduced to a single scalar and rejects
+    /// the identity, every point is accepted and absorbed injectively.
+    pub fn absorb_point(&mut self, point: &C) {
+        match Option::<Coordinates<C>>::from(point.coordinates()) {
+            Some(coordinates) => {
+                self.absorb_base(*coordinates.x());
+                self.absorb_base(*coordinates.y());
+                self.update(&[F::ZERO]);
+            }
+            None => {
+                self.update(&vec![F::ZERO; 2 * self.base_limbs()]);
+                self.update(&[F::ONE]);
+            }
         }
-        self.state.sbox_full(constants.last().unwrap());
-        self.state.apply_mds(&pre_sparse_mds);
+    }
 
-        // Partial rounds
-        let constants = self.spec.constants().partial();
-        for (constant, sparse_mds) in constants.iter().zip(sparse_matrices.iter()) {
-            self.state.sbox_part(constant);
-            self.state.apply_sparse_mds(sparse_mds);
+    /// Buffers a point of the other curve of a cycle, whose coordinates are
+    /// elements of `F`, as `(x, y, 0)`, and the identity as `(0, 0, 1)`: the
+    /// convention of [`NovaRO::absorb_point`](crate::nova::NovaRO::absorb_point)
+    pub fn absorb_native_point<P: CurveAffine<Base = F>>(&mut self, point: &P) {
+        match Option::<Coordinates<P>>::from(point.coordinates()) {
+            Some(coordinates) => self.update(&[*coordinates.x(), *coordinates.y(), F::ZERO]),
+            None => self.update(&[F::ZERO, F::ZERO, F::ONE]),
         }
+    }
 
-        // Second half of the full rounds
-        let constants = self.spec.constants().end();
-        for constants in constants.iter() {
-            self.state.sbox_full(constants);
-            self.state.apply_mds(&mds);
+    fn pack(chunk: &[u8]) -> F {
+        let mut repr = F::Repr::default();
+        repr.as_mut()[..chunk.len()].copy_from_slice(chunk);
+        F::from_repr(repr).unwrap()
+    }
+
+    /// Takes the states recorded at the end of every round of the
+    /// permutations run since the last call, `r_f + r_p` per permutation.
+    ///
+    /// These are states of the optimized permutation: full rounds have the
+    /// next round's constants already added and partial rounds are in the
+    /// basis of the sparse MDS matrices. They compare directly with
+    /// [`PoseidonChip`](crate::poseidon_circuit::PoseidonChip) witnesses and
+    /// other implementations of the same optimization, such as the PSE
+    /// `poseidon` sponge.
+    #[cfg(feature = "debug-trace")]
+    pub fn take_trace(&mut self) -> crate::trace::Trace<F, T> {
+        mem::take(&mut self.state.trace)
+    }
+
+    /// Takes the number of field operations of the permutations run since
+    /// the last call, see [`crate::op_count`]
+    #[cfg(feature = "op-counters")]
+    pub fn take_op_count(&mut self) -> crate::op_count::OpCount {
+        mem::take(&mut self.state.ops)
+    }
+
+    /// `n` consecutive outputs of the sponge, the same as `n` calls to
+    /// [`ROTrait::squeeze`]: the first absorbs the buffered elements and each
+    /// of the others permutes the state once more, so the outputs are
+    /// independent and the sponge continues after the last one
+    pub fn squeeze_n(&mut self, n: usize) -> Vec<F> {
+        (0..n).map(|_| self.output()).collect()
+    }
+
+    /// [`Self::squeeze_n`] into an array
+    pub fn squeeze_array<const N: usize>(&mut self) -> [F; N] {
+        std::array::from_fn(|_| self.output())
+    }
+
+    /// One output of the sponge truncated to its low `bits` bits, for
+    /// challenges shorter than the field such as the 128-bit ones of folding
+    /// schemes
+    ///
+    /// # Panics
+    ///
+    /// If `bits` is zero or above the capacity of `F`
+    pub fn squeeze_challenge_bits(&mut self, bits: usize) -> F {
+        assert!(
+            bits > 0 && bits <= F::CAPACITY as usize,
+            "invalid challenge width"
+        );
+        encoding::truncate(&self.output(), bits)
+    }
+
+    /// One output of the sponge as an element of the base field of `C`, for
+    /// a circuit over the other field of a cycle: the integer of its low
+    /// `min(capacity of F, capacity of C::Base)` bits, which is below both
+    /// moduli
+    pub fn squeeze_base(&mut self) -> C::Base {
+        let bits = F::CAPACITY.min(C::Base::CAPACITY) as usize;
+        encoding::truncate(&self.output(), bits)
+    }
+
+    fn output(&mut self) -> F {
+        self.finish_bytes();
+        let streamed = mem::take(&mut self.streamed);
+        self.absorbed += streamed + self.buf.len();
+        let spec = &self.spec;
+        if self.padding == Padding::OneZeroStar {
+            self.state.absorb(spec, &self.buf);
+        } else {
+            if let Padding::FixedLength(len) = self.padding {
+                assert_eq!(self.absorbed, len, "fixed-length input of another length");
+            }
+            // full blocks get no padding one; an empty buffer still permutes
+            // so that squeezes continue the stream
+            if !self.buf.is_empty() || streamed == 0 {
+                let mut block = [F::ZERO; RATE];
+                block[..self.buf.len()].copy_from_slice(&self.buf);
+                self.state.permutation(spec, &block);
+            }
         }
-        self.state.sbox_full(&[F::ZERO; T]);
-        self.state.apply_mds(&mds);
+        self.buf.clear();
+
+        self.state.inner[1]
     }
 }
 
 #[cfg(test)]
 mod tests {
     use halo2curves::{
-        bn256::{Fr, G1Affine},
-        pasta::{EqAffine, Fp},
+        bn256::{Fq, Fr, G1Affine},
+        group::{ff::Field, prime::PrimeCurveAffine},
     };
 
+    use std::{env, fs};
+
+    use halo2curves::pasta::{EpAffine, EqAffine, Fp};
+    use proptest::prelude::*;
+
     use super::*;
+    use crate::{
+        encoding::{self, Digest},
+        reference::Reference,
+    };
+
+    #[test]
+    fn test_absorb_integers() {
+        type PH = PoseidonHash<G1Affine, Fr, 3, 2>;
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let mut poseidon = PH::new(spec.clone());
+        poseidon.absorb_u64(u64::MAX);
+        poseidon.absorb_u128(u128::MAX);
+        poseidon.absorb_usize(7);
+        poseidon.absorb_bool(true);
+        poseidon.absorb_bool(false);
+        let mut expected = PH::new(spec);
+        expected.update(&[
+            Fr::from(u64::MAX),
+            Fr::from(1 << 64).square() - Fr::ONE,
+            Fr::from(7),
+            Fr::ONE,
+            Fr::ZERO,
+        ]);
+        assert_eq!(poseidon.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    fn test_absorb_packed() {
+        type PH = PoseidonHash<G1Affine, Fr, 3, 2>;
+        let mut poseidon = PH::new(PrecomputedSpec::new(8, 56));
+        let mut expected = PH::new(PrecomputedSpec::new(8, 56));
+        // 253 / 64 = 3 values per element
+        poseidon.absorb_packed(&[1, 2, 3, u64::MAX], 64);
+        let radix = Fr::from(1 << 32).square();
+        expected.update(&[
+            Fr::from(1) + radix * (Fr::from(2) + radix * Fr::from(3)),
+            Fr::from(u64::MAX),
+        ]);
+
+        // 253 / 1 flags per element
+        poseidon.absorb_packed(&[1; 254], 1);
+        expected.update(&[Fr::from(2).pow_vartime([253]) - Fr::ONE, Fr::ONE]);
+        assert_eq!(poseidon.streamed, 4);
+        assert_eq!(poseidon.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    #[should_panic(expected = "value wider than the packing width")]
+    fn test_absorb_packed_rejects_wide_values() {
+        PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 56))
+            .absorb_packed(&[1, 8, 2], 3);
+    }
+
+    #[test]
+    fn test_eager_absorption() {
+        type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let data = (0..=255u8).cycle().take(100_000).collect::<Vec<_>>();
+        let mut poseidon = PH::new(spec.clone());
+        for chunk in data.chunks(1000) {
+            poseidon.update_bytes(chunk);
+            assert!(poseidon.buf.len() < 3);
+            assert!(poseidon.bytes.as_ref().unwrap().len() < PH::BYTES_PER_ELEMENT);
+        }
+
+        let bytes = PH::BYTES_PER_ELEMENT;
+        let mut tail = data[data.len() / bytes * bytes..].to_vec();
+        tail.push(1);
+        let elements = data
+            .chunks_exact(bytes)
+            .chain([&tail[..]])
+            .map(PH::pack)
+            .collect::<Vec<_>>();
+        let mut state = State::<Fr, 4, 3>::new(initial_state());
+        state.absorb(&spec, &elements);
+        assert_eq!(poseidon.squeeze(), state.inner[1]);
+
+        // without padding, streamed blocks count towards the fixed length
+        let elements = (0..30).map(Fr::from).collect::<Vec<_>>();
+        let mut poseidon = PH::new_with_padding(spec.clone(), Padding::FixedLength(elements.len()));
+        poseidon.update(&elements);
+        assert!(poseidon.buf.is_empty());
+        let mut state = State::<Fr, 4, 3>::new(initial_state());
+        state.inner[0] = Fr::from_u128((elements.len() as u128) << 64);
+        for block in elements.chunks(3) {
+            state.permutation(&spec, block);
+        }
+        assert_eq!(poseidon.squeeze(), state.inner[1]);
+    }
+
+    #[test]
+    fn test_buffer_reused() {
+        let mut poseidon = PoseidonHash::<G1Affine, Fr, 4, 3>::new(PrecomputedSpec::new(8, 56));
+        let capacity = poseidon.buf.capacity();
+        for i in 0..100 {
+            poseidon.update(&[Fr::from(i); 5]);
+            poseidon.update_bytes(b"bytes");
+            poseidon.squeeze();
+        }
+        assert_eq!(poseidon.buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_update_bytes() {
+        type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let digest = |parts: &[&[u8]]| {
+            let mut poseidon = PH::new(spec.clone());
+            for part in parts {
+                poseidon.update_bytes(part);
+            }
+            poseidon.squeeze()
+        };
+        let message = (0..100u8).collect::<Vec<_>>();
+        assert_eq!(PH::BYTES_PER_ELEMENT, 31);
+        assert_eq!(
+            digest(&[&message]),
+            digest(&[&message[..7], &message[7..62], &[], &message[62..]])
+        );
+        assert_ne!(digest(&[b"ab"]), digest(&[b"ab\0"]));
+        assert_ne!(digest(&[&message[..31]]), digest(&[&message[..32]]));
+
+        let mut poseidon = PH::new(spec.clone());
+        poseidon.update_bytes(&[7, 0]);
+        poseidon.update(&[Fr::from(5)]);
+        let mut expected = PH::new(spec);
+        expected.update(&[Fr::from(0x01_00_07), Fr::from(5)]);
+        assert_eq!(poseidon.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    fn test_absorb_point() {
+        type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let digest = |point: G1Affine| {
+            let mut poseidon = PH::new(spec.clone());
+            poseidon.absorb_point(&point);
+            assert_eq!(
+                poseidon.streamed + poseidon.buf.len(),
+                poseidon.point_elements()
+            );
+            poseidon.squeeze()
+        };
+
+        let generator = G1Affine::generator();
+        let coordinates = generator.coordinates().unwrap();
+        let mut expected = PH::new(spec.clone());
+        expected.update(&encoding::to_limbs::<Fq, Fr>(coordinates.x(), BITS));
+        expected.update(&encoding::to_limbs::<Fq, Fr>(coordinates.y(), BITS));
+        expected.update(&[Fr::ZERO]);
+        assert_eq!(digest(generator), expected.squeeze());
+
+        let mut expected = PH::new(spec.clone());
+        expected.update(&[Fr::ZERO; 2 * 4]);
+        expected.update(&[Fr::ONE]);
+        assert_eq!(digest(G1Affine::identity()), expected.squeeze());
+        assert_ne!(digest(G1Affine::identity()), digest(generator));
+    }
+
+    #[test]
+    fn test_absorb_native_point() {
+        type PH = PoseidonHash<EqAffine, Fp, 3, 2>;
+        let spec = PrecomputedSpec::<Fp, 3, 2>::new(8, 56);
+        let digest = |point: EpAffine| {
+            let mut poseidon = PH::new(spec.clone());
+            poseidon.absorb_native_point(&point);
+            poseidon.squeeze()
+        };
+
+        let generator = EpAffine::generator();
+        let coordinates = generator.coordinates().unwrap();
+        let mut expected = PH::new(spec.clone());
+        expected.update(&[*coordinates.x(), *coordinates.y(), Fp::ZERO]);
+        assert_eq!(digest(generator), expected.squeeze());
+
+        let mut expected = PH::new(spec.clone());
+        expected.update(&[Fp::ZERO, Fp::ZERO, Fp::ONE]);
+        assert_eq!(digest(EpAffine::identity()), expected.squeeze());
+    }
+
+    #[test]
+    fn test_absorb_base() {
+        type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let value = -Fq::ONE;
+        let digest = |mut poseidon: PH| {
+            poseidon.absorb_base(value);
+            assert_eq!(
+                poseidon.streamed + poseidon.buf.len(),
+                poseidon.base_limbs()
+            );
+            poseidon.squeeze()
+        };
+
+        // four limbs of BITS bits by default
+        let mut expected = PH::new(spec.clone());
+        expected.update(&encoding::to_limbs::<Fq, Fr>(&value, BITS));
+        assert_eq!(PH::new(spec.clone()).base_limbs(), 4);
+        assert_eq!(digest(PH::new(spec.clone())), expected.squeeze());
+
+        // 254 bits in 3 limbs of 85 bits, the last one of 84
+        let poseidon = PH::new(spec.clone()).with_limb_bits(85);
+        assert_eq!(poseidon.base_limbs(), 3);
+        let small = Fq::from(u64::MAX).square();
+        let limbs = encoding::to_limbs::<Fq, Fr>(&small, 85);
+        let radix = Fr::from(2).pow_vartime([85]);
+        assert_eq!(
+            limbs[0] + radix * (limbs[1] + radix * limbs[2]),
+            Fr::from(u64::MAX).square()
+        );
+        assert_eq!(limbs[2], Fr::ZERO);
+        assert_ne!(digest(poseidon), digest(PH::new(spec.clone())));
+        assert_eq!(PH::new(spec).with_limb_bits(1).base_limbs(), 254);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid limb width")]
+    fn test_absorb_base_rejects_wide_limbs() {
+        PoseidonHash::<G1Affine, Fr, 4, 3>::new(PrecomputedSpec::new(8, 56)).with_limb_bits(254);
+    }
+
+    #[test]
+    fn test_squeeze_n() {
+        type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let fresh = || {
+            let mut poseidon = PH::new(spec.clone());
+            poseidon.update(&words([1, 2, 3, 4]));
+            poseidon
+        };
+
+        let mut expected = fresh();
+        let one_by_one = (0..4).map(|_| expected.squeeze()).collect::<Vec<_>>();
+        let mut poseidon = fresh();
+        let outputs = poseidon.squeeze_n(3);
+        assert_eq!(outputs, one_by_one[..3]);
+        assert_eq!(poseidon.squeeze(), one_by_one[3]);
+        assert!(outputs[0] != outputs[1] && outputs[0] != outputs[2] && outputs[1] != outputs[2]);
+
+        assert_eq!(fresh().squeeze_array::<4>().to_vec(), one_by_one);
+        let mut poseidon = fresh();
+        assert!(poseidon.squeeze_n(0).is_empty());
+        assert_eq!(poseidon.squeeze(), one_by_one[0]);
+    }
+
+    #[test]
+    fn test_squeeze_challenge_bits() {
+        type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let fresh = || {
+            let mut poseidon = PH::new(spec.clone());
+            poseidon.update(&words([1, 2, 3]));
+            poseidon
+        };
+
+        let mut expected = fresh();
+        let outputs = expected.squeeze_n(2);
+        let mut poseidon = fresh();
+        let challenge = poseidon.squeeze_challenge_bits(128);
+        assert_eq!(challenge, encoding::truncate(&outputs[0], 128));
+        assert!(encoding::to_be_bytes(&challenge)[..16]
+            .iter()
+            .all(|byte| *byte == 0));
+        // the sponge continues like after a full squeeze
+        assert_eq!(poseidon.squeeze(), outputs[1]);
+        assert_eq!(
+            fresh().squeeze_challenge_bits(Fr::CAPACITY as usize),
+            encoding::truncate(&outputs[0], 253)
+        );
+    }
+
+    #[test]
+    fn test_squeeze_base() {
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let mut expected = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+        expected.update(&words([1, 2]));
+        let outputs = expected.squeeze_n(2);
+        let mut poseidon = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec);
+        poseidon.update(&words([1, 2]));
+        let base: Fq = poseidon.squeeze_base();
+        assert_eq!(base, encoding::truncate(&outputs[0], 253));
+        assert_eq!(poseidon.squeeze(), outputs[1]);
+
+        // vesta's scalar field into its base field, 254 bits
+        let spec = PrecomputedSpec::<Fp, 3, 2>::new(8, 56);
+        let mut expected = PoseidonHash::<EqAffine, Fp, 3, 2>::new(spec.clone());
+        let output = expected.squeeze();
+        let base = PoseidonHash::<EqAffine, Fp, 3, 2>::new(spec).squeeze_base();
+        assert_eq!(
+            base,
+            encoding::truncate::<_, halo2curves::pasta::Fq>(&output, 254)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid challenge width")]
+    fn test_squeeze_challenge_bits_rejects_width() {
+        PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 56))
+            .squeeze_challenge_bits(254);
+    }
 
     #[test]
     fn test_poseidon_hash() {
@@ -209,17 +983,534 @@ mod tests {
         const R_F: usize = 8;
         const R_P: usize = 56;
         type PH = PoseidonHash<G1Affine, Fr, T, RATE>;
-        let spec = Spec::<Fr, T, RATE>::new(R_F, R_P);
+        let spec = PrecomputedSpec::<Fr, T, RATE>::new(R_F, R_P);
         let mut poseidon = PH::new(spec);
         for i in 0..5 {
             poseidon.update(&[Fr::from(i as u64)]);
         }
-        let output = poseidon.squeeze();
-        // 0x2ce4016298e9e5fcaa94ccb686413e16add1bb813def8a3a0628aed46ea07749
-        let out_hash = Fr::from_str_vartime(
-            "20304616028358001435806807494046171997958789835068077254356069730773893150537",
-        )
-        .unwrap();
-        assert_eq!(output, out_hash);
+        let output = Digest(poseidon.squeeze());
+        assert_eq!(
+            output.to_hex(),
+            "0x2ce4016298e9e5fcaa94ccb686413e16add1bb813def8a3a0628aed46ea07749"
+        );
+        assert_eq!(
+            output.to_decimal(),
+            "20304616028358001435806807494046171997958789835068077254356069730773893150537"
+        );
+    }
+
+    fn words<const N: usize>(values: [u64; N]) -> [Fr; N] {
+        values.map(Fr::from)
+    }
+
+    #[test]
+    fn test_sbox_full() {
+        let mut state = State::<Fr, 3, 2>::new(words([1, 2, 3]));
+        state.sbox_full(&words([10, 20, 30]));
+        assert_eq!(state.inner, words([1 + 10, 32 + 20, 243 + 30]));
+        state.sbox_full(&[Fr::ZERO; 3]);
+        assert_eq!(
+            state.inner,
+            words([11u64.pow(5), 52u64.pow(5), 273u64.pow(5)])
+        );
+    }
+
+    #[test]
+    fn test_sbox_part() {
+        let mut state = State::<Fr, 3, 2>::new(words([2, 5, 7]));
+        state.sbox_part(&Fr::ONE);
+        assert_eq!(state.inner, words([33, 5, 7]));
+        state.inner[0] = -Fr::ONE;
+        state.sbox_part(&Fr::ZERO);
+        assert_eq!(state.inner, [-Fr::ONE, Fr::from(5), Fr::from(7)]);
+    }
+
+    #[test]
+    fn test_pre_round() {
+        let constants = words([1, 2, 3]);
+        let pre_round = |inputs: &[u64]| {
+            let mut state = State::<Fr, 3, 2>::new(words([100, 200, 300]));
+            let inputs = inputs.iter().copied().map(Fr::from).collect::<Vec<_>>();
+            state.pre_round(&inputs, &constants);
+            state.inner
+        };
+        // the padding one goes right after the inputs, if there is room
+        assert_eq!(pre_round(&[]), words([101, 200 + 1 + 2, 300 + 3]));
+        assert_eq!(pre_round(&[7]), words([101, 200 + 7 + 2, 300 + 1 + 3]));
+        assert_eq!(pre_round(&[7, 8]), words([101, 200 + 7 + 2, 300 + 8 + 3]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pre_round_rejects_overlong_block() {
+        let mut state = State::<Fr, 3, 2>::new([Fr::ZERO; 3]);
+        state.pre_round(&words([1, 2, 3]), &[Fr::ZERO; 3]);
+    }
+
+    #[test]
+    fn test_apply_mds() {
+        // not symmetric, so a transposed product fails
+        let mut state = State::<Fr, 2, 1>::new(words([5, 6]));
+        state.apply_mds(&[words([1, 2]), words([3, 4])]);
+        assert_eq!(state.inner, words([5 + 2 * 6, 3 * 5 + 4 * 6]));
+
+        let mut state = State::<Fr, 3, 2>::new(words([1, 10, 100]));
+        state.apply_mds(&[words([1, 0, 0]), words([0, 0, 1]), words([2, 3, 4])]);
+        assert_eq!(state.inner, words([1, 100, 2 + 30 + 400]));
+    }
+
+    #[test]
+    fn test_apply_sparse_mds() {
+        // [row; col_hat | I]: the first word mixes the whole state, the
+        // others only take a multiple of the first
+        let mut state = State::<Fr, 3, 2>::new(words([1, 10, 100]));
+        state.apply_sparse_mds((&words([2, 3, 4]), &words([5, 6])));
+        assert_eq!(state.inner, words([2 + 30 + 400, 5 + 10, 6 + 100]));
+
+        // and with the matrices of a spec, the dense product by the same
+        // matrix
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let initial = words([3, 1, 4, 1]);
+        for round in 0..spec.partial_rounds() {
+            let (row, col_hat) = spec.sparse_mds(round);
+            let dense: [[Fr; 4]; 4] = std::array::from_fn(|i| match i {
+                0 => *row,
+                i => std::array::from_fn(|j| match j {
+                    0 => col_hat[i - 1],
+                    j if j == i => Fr::ONE,
+                    _ => Fr::ZERO,
+                }),
+            });
+            let mut sparse_state = State::<Fr, 4, 3>::new(initial);
+            sparse_state.apply_sparse_mds((row, col_hat));
+            let mut dense_state = State::<Fr, 4, 3>::new(initial);
+            dense_state.apply_mds(&dense);
+            assert_eq!(sparse_state.inner, dense_state.inner);
+        }
+    }
+
+    /// Absorbs `inputs` into `state` like [`State::permutation`], but with the
+    /// reference permutation
+    fn reference_permutation<F: PrimeField, const T: usize>(
+        reference: &Reference<F, T>,
+        state: &mut [F; T],
+        inputs: &[F],
+    ) {
+        for (s, input) in state.iter_mut().skip(1).zip(inputs) {
+            *s += input;
+        }
+        if inputs.len() < T - 1 {
+            state[1 + inputs.len()] += F::ONE;
+        }
+        reference.permute(state);
+    }
+
+    /// Checks the padding rules documented on [`PoseidonHash`] against the
+    /// reference permutation for every length up to three blocks
+    fn check_rate_boundaries<C, F, const T: usize, const RATE: usize>()
+    where
+        C: CurveAffine<ScalarExt = F>,
+        F: PrimeField + FromUniformBytes<64>,
+    {
+        let spec = PrecomputedSpec::<F, T, RATE>::new(8, 56);
+        let reference = Reference::<F, T>::new(8, 56);
+        let digest = |inputs: &[F]| {
+            let mut hasher = PoseidonHash::<C, F, T, RATE>::new(spec.clone());
+            hasher.update(inputs);
+            hasher.squeeze()
+        };
+
+        for len in 0..=3 * RATE {
+            let inputs = (1..=len as u64).map(F::from).collect::<Vec<_>>();
+            let mut hasher = PoseidonHash::<C, F, T, RATE>::new(spec.clone());
+            hasher.update(&inputs);
+            let output = hasher.squeeze();
+            assert_eq!(
+                hasher.state.trace.len(),
+                (len / RATE + 1) * (8 + 56),
+                "permutations for {} inputs",
+                len
+            );
+
+            let mut expected = initial_state::<F, T>();
+            let mut blocks = inputs.chunks(RATE).collect::<Vec<_>>();
+            if len % RATE == 0 {
+                blocks.push(&[]);
+            }
+            for block in blocks {
+                reference_permutation(&reference, &mut expected, block);
+            }
+            assert_eq!(output, expected[1], "digest of {} inputs", len);
+
+            // a trailing zero is not absorbed as padding
+            assert_ne!(digest(&[&inputs[..], &[F::ZERO]].concat()), output);
+
+            // squeezing again permutes the padding alone
+            reference_permutation(&reference, &mut expected, &[]);
+            assert_eq!(hasher.squeeze(), expected[1]);
+        }
+
+        // an empty update and no update at all are the empty input
+        let mut fresh = PoseidonHash::<C, F, T, RATE>::new(spec.clone());
+        assert_eq!(fresh.squeeze(), digest(&[]));
+    }
+
+    #[test]
+    fn test_initial_state() {
+        let two_64 = Fr::from(u64::MAX) + Fr::ONE;
+        assert_eq!(initial_state::<Fr, 3>(), [two_64, Fr::ZERO, Fr::ZERO]);
+        assert_eq!(
+            initial_state::<Fr, 5>(),
+            [two_64, Fr::ZERO, Fr::ZERO, Fr::ZERO, Fr::ZERO]
+        );
+    }
+
+    /// Alternating updates and squeezes continue the state, as documented on
+    /// [`PoseidonHash`]
+    #[test]
+    fn test_interleaving() {
+        let (r_f, r_p) = (8, 56);
+        let reference = Reference::<Fr, 3>::new(r_f, r_p);
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(r_f, r_p));
+        let mut expected = initial_state::<Fr, 3>();
+
+        // blocks absorbed by each squeeze
+        let session: [(&[u64], &[&[u64]]); 4] = [
+            (&[1, 2, 3], &[&[1, 2], &[3]]),
+            (&[4], &[&[4]]),
+            (&[], &[&[]]),
+            (&[5, 6], &[&[5, 6], &[]]),
+        ];
+        for (inputs, blocks) in session {
+            hasher.update(&inputs.iter().copied().map(Fr::from).collect::<Vec<_>>());
+            for block in blocks {
+                let block = block.iter().copied().map(Fr::from).collect::<Vec<_>>();
+                reference_permutation(&reference, &mut expected, &block);
+            }
+            assert_eq!(hasher.squeeze(), expected[1], "after {:?}", inputs);
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        let (r_f, r_p) = (8, 56);
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(r_f, r_p);
+        let reference = Reference::<Fr, 3>::new(r_f, r_p);
+        let hasher =
+            |padding| PoseidonHash::<G1Affine, Fr, 3, 2>::new_with_padding(spec.clone(), padding);
+        let digest = |padding, inputs: &[u64]| {
+            let mut hasher = hasher(padding);
+            hasher.update(&inputs.iter().copied().map(Fr::from).collect::<Vec<_>>());
+            let output = hasher.squeeze();
+            (output, hasher.state.trace.len() / (r_f + r_p))
+        };
+        // blocks added to the rate words as they are
+        let expected = |capacity: Fr, blocks: &[[u64; 2]]| {
+            let mut state = [capacity, Fr::ZERO, Fr::ZERO];
+            for block in blocks {
+                state[1] += Fr::from(block[0]);
+                state[2] += Fr::from(block[1]);
+                reference.permute(&mut state);
+            }
+            state[1]
+        };
+        let default_capacity = Fr::from_u128(1 << 64);
+
+        let mut default = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+        default.update(&words([1, 2]));
+        assert_eq!(digest(Padding::OneZeroStar, &[1, 2]).0, default.squeeze());
+
+        // no block of padding, and zeros are not distinguished
+        assert_eq!(
+            digest(Padding::None, &[1, 2, 3]),
+            (expected(default_capacity, &[[1, 2], [3, 0]]), 2)
+        );
+        assert_eq!(
+            digest(Padding::None, &[1, 2]),
+            (expected(default_capacity, &[[1, 2]]), 1)
+        );
+        assert_eq!(
+            digest(Padding::None, &[1]).0,
+            digest(Padding::None, &[1, 0]).0
+        );
+        assert_eq!(digest(Padding::None, &[]).1, 1);
+
+        // the length goes into the capacity
+        let capacity = Fr::from_u128(4 << 64);
+        assert_eq!(
+            digest(Padding::FixedLength(4), &[1, 2, 3, 4]),
+            (expected(capacity, &[[1, 2], [3, 4]]), 2)
+        );
+        let mut stream = hasher(Padding::FixedLength(4));
+        stream.update(&words([1, 2, 3, 4]));
+        stream.squeeze();
+        // a permutation of zeros
+        assert_eq!(
+            stream.squeeze(),
+            expected(capacity, &[[1, 2], [3, 4], [0, 0]])
+        );
+    }
+
+    #[test]
+    fn test_domain() {
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let digest = |hasher: &mut PoseidonHash<G1Affine, Fr, 3, 2>| {
+            hasher.update(&words([1, 2, 3]));
+            hasher.squeeze()
+        };
+        let domain =
+            |domain: &[u8]| digest(&mut PoseidonHash::new_with_domain(spec.clone(), domain));
+        let default = digest(&mut PoseidonHash::new(spec.clone()));
+        assert_ne!(domain(b"nova"), domain(b"protostar"));
+        assert_ne!(domain(b"nova"), default);
+        assert_ne!(domain(b""), default);
+        assert_eq!(domain(b"nova"), domain(b"nova"));
+
+        // the capacity is the digest of the domain
+        let mut tag = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec.clone());
+        tag.update_bytes(b"nova");
+        let hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new_with_domain(spec, b"nova");
+        assert_eq!(hasher.state.inner, [tag.squeeze(), Fr::ZERO, Fr::ZERO]);
+    }
+
+    #[test]
+    fn test_keyed() {
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let keyed = |key: u64| {
+            let mut hasher =
+                PoseidonHash::<G1Affine, Fr, 3, 2>::new_keyed(spec.clone(), Fr::from(key));
+            hasher.update(&words([1, 2, 3]));
+            hasher
+        };
+        let tag = keyed(7).squeeze();
+        assert!(keyed(7).verify_tag(&tag));
+        assert!(!keyed(8).verify_tag(&tag));
+        assert!(!keyed(7).verify_tag(&(tag + Fr::ONE)));
+
+        let mut expected = State::<Fr, 3, 2>::new([Fr::from(7), Fr::ZERO, Fr::ZERO]);
+        expected.absorb(&spec, &words([1, 2, 3]));
+        assert_eq!(tag, expected.inner[1]);
+    }
+
+    #[test]
+    fn test_hash_two() {
+        let (r_f, r_p) = (8, 56);
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(r_f, r_p);
+        let (left, right) = (Fr::from(1), Fr::from(2));
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new_with_padding(
+            spec.clone(),
+            Padding::FixedLength(2),
+        );
+        hasher.update(&[left, right]);
+        assert_eq!(hash_two(&spec, left, right), hasher.squeeze());
+        assert_ne!(hash_two(&spec, left, right), hash_two(&spec, right, left));
+
+        // one permutation of the two elements under the length
+        let reference = Reference::<Fr, 4>::new(r_f, r_p);
+        let mut state = [Fr::from_u128(2 << 64), Fr::from(1), Fr::from(2), Fr::ZERO];
+        reference.permute(&mut state);
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(r_f, r_p);
+        assert_eq!(hash_two(&spec, Fr::from(1), Fr::from(2)), state[1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "fixed-length input of another length")]
+    fn test_fixed_length_rejects_other_lengths() {
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new_with_padding(
+            PrecomputedSpec::new(8, 56),
+            Padding::FixedLength(4),
+        );
+        hasher.update(&words([1, 2, 3]));
+        hasher.squeeze();
+    }
+
+    #[test]
+    fn test_rate_boundaries() {
+        check_rate_boundaries::<G1Affine, Fr, 2, 1>();
+        check_rate_boundaries::<G1Affine, Fr, 3, 2>();
+        check_rate_boundaries::<G1Affine, Fr, 5, 4>();
+        check_rate_boundaries::<EqAffine, Fp, 4, 3>();
+    }
+
+    #[test]
+    fn test_reference_matches_vector() {
+        let reference = Reference::<Fr, 4>::new(8, 56);
+        let mut state = initial_state::<Fr, 4>();
+        let inputs = (0..5).map(Fr::from).collect::<Vec<_>>();
+        reference_permutation(&reference, &mut state, &inputs[..3]);
+        reference_permutation(&reference, &mut state, &inputs[3..]);
+        assert_eq!(
+            Digest(state[1]).to_hex(),
+            "0x2ce4016298e9e5fcaa94ccb686413e16add1bb813def8a3a0628aed46ea07749"
+        );
+    }
+
+    fn elements<F: FromUniformBytes<64>>(bytes: &[u8]) -> Vec<F> {
+        bytes
+            .chunks(64)
+            .map(|chunk| F::from_uniform_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    macro_rules! optimized_matches_reference {
+        ($name:ident, $field:ty, $t:literal, $rate:literal) => {
+            proptest! {
+                #![proptest_config(ProptestConfig::with_cases(16))]
+                #[test]
+                fn $name(
+                    bytes in prop::collection::vec(any::<u8>(), 64 * ($t + $rate)),
+                    half_r_f in 1usize..5,
+                    r_p in 1usize..64,
+                    len in 0usize..=$rate,
+                ) {
+                    let (r_f, values) = (2 * half_r_f, elements::<$field>(&bytes));
+                    let initial: [$field; $t] = values[..$t].try_into().unwrap();
+                    let inputs = &values[$t..$t + len];
+
+                    let mut optimized = State::<$field, $t, $rate>::new(initial);
+                    optimized.permutation(&PrecomputedSpec::new(r_f, r_p), inputs);
+                    let mut expected = initial;
+                    reference_permutation(&Reference::new(r_f, r_p), &mut expected, inputs);
+                    prop_assert_eq!(optimized.inner, expected);
+                }
+            }
+        };
+    }
+
+    optimized_matches_reference!(test_optimized_matches_reference_t2, Fr, 2, 1);
+    optimized_matches_reference!(test_optimized_matches_reference_t3, Fr, 3, 2);
+    optimized_matches_reference!(test_optimized_matches_reference_t5, Fr, 5, 4);
+    optimized_matches_reference!(test_optimized_matches_reference_pasta_t4, Fp, 4, 3);
+
+    /// Squeezes `N` outputs in a row and checks that their low 248 bits are
+    /// balanced and that consecutive outputs are uncorrelated. These are only
+    /// smoke tests for catastrophic bugs, such as a constant or repeating
+    /// output, far from a statistical test suite.
+    fn check_squeeze_stream<C, F, const T: usize, const RATE: usize>(r_f: usize, r_p: usize)
+    where
+        C: CurveAffine<ScalarExt = F>,
+        F: PrimeField + FromUniformBytes<64>,
+    {
+        const N: usize = 512;
+        const BITS: usize = 248;
+
+        let mut poseidon = PoseidonHash::<C, F, T, RATE>::new(PrecomputedSpec::new(r_f, r_p));
+        poseidon.update(&[F::ONE]);
+        let outputs = (0..N)
+            .map(|_| poseidon.squeeze().to_repr().as_ref()[..BITS / 8].to_vec())
+            .collect::<Vec<_>>();
+
+        // the number of ones is within five standard deviations of half
+        let ones = outputs
+            .iter()
+            .flatten()
+            .map(|byte| byte.count_ones() as f64)
+            .sum::<f64>();
+        let bits = (N * BITS) as f64;
+        assert!(
+            (ones - bits / 2.0).abs() < 5.0 * bits.sqrt() / 2.0,
+            "bit balance"
+        );
+
+        // and so is the lag-1 correlation of the low 64 bits
+        let words = outputs
+            .iter()
+            .map(|bytes| u64::from_le_bytes(bytes[..8].try_into().unwrap()) as f64 / 2f64.powi(64))
+            .collect::<Vec<_>>();
+        let mean = words.iter().sum::<f64>() / N as f64;
+        let variance = words.iter().map(|w| (w - mean).powi(2)).sum::<f64>();
+        let covariance = words
+            .windows(2)
+            .map(|pair| (pair[0] - mean) * (pair[1] - mean))
+            .sum::<f64>();
+        assert!(
+            (covariance / variance).abs() < 5.0 / (N as f64).sqrt(),
+            "serial correlation"
+        );
+
+        let mut distinct = outputs.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), N, "repeated output");
+    }
+
+    #[test]
+    fn test_squeeze_stream() {
+        check_squeeze_stream::<G1Affine, Fr, 3, 2>(8, 56);
+        check_squeeze_stream::<G1Affine, Fr, 4, 3>(8, 56);
+        check_squeeze_stream::<G1Affine, Fr, 5, 4>(8, 56);
+        check_squeeze_stream::<EqAffine, Fp, 3, 2>(8, 56);
+    }
+
+    /// One line per step of a scripted session: what `update` and
+    /// `update_bytes` buffered, then the full state after every permutation
+    /// of a squeeze and its output
+    fn snapshot() -> String {
+        type PH = PoseidonHash<G1Affine, Fr, 3, 2>;
+        let words = |words: &[Fr]| {
+            let words = words.iter().map(encoding::to_hex).collect::<Vec<_>>();
+            format!("[{}]", words.join(", "))
+        };
+        let buffered = |hasher: &PH| {
+            let bytes = hasher.bytes.as_ref().map(|bytes| {
+                let hex = bytes
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                format!(" bytes 0x{}", hex)
+            });
+            format!("  buf {}{}", words(&hasher.buf), bytes.unwrap_or_default())
+        };
+
+        let mut hasher = PH::new(PrecomputedSpec::new(8, 56));
+        let mut lines = Vec::new();
+        let squeeze = |hasher: &mut PH, lines: &mut Vec<String>| {
+            let output = hasher.squeeze();
+            let trace = mem::take(&mut hasher.state.trace);
+            lines.push("squeeze".to_string());
+            for rounds in trace.chunks(8 + 56) {
+                lines.push(format!("  permutation {}", words(rounds.last().unwrap())));
+            }
+            lines.push(format!("  output {}", encoding::to_hex(&output)));
+        };
+
+        let elements = |values: &[u64]| values.iter().copied().map(Fr::from).collect::<Vec<_>>();
+        hasher.update(&elements(&[1]));
+        lines.extend(["update [1]".to_string(), buffered(&hasher)]);
+        hasher.update_bytes(b"ab");
+        lines.extend(["update_bytes \"ab\"".to_string(), buffered(&hasher)]);
+        hasher.update(&elements(&[2, 3, 4]));
+        lines.extend(["update [2, 3, 4]".to_string(), buffered(&hasher)]);
+        squeeze(&mut hasher, &mut lines);
+        squeeze(&mut hasher, &mut lines);
+        hasher.update(&elements(&[5, 6]));
+        lines.extend(["update [5, 6]".to_string(), buffered(&hasher)]);
+        squeeze(&mut hasher, &mut lines);
+        hasher.update_bytes(&[]);
+        lines.extend(["update_bytes []".to_string(), buffered(&hasher)]);
+        squeeze(&mut hasher, &mut lines);
+        hasher.update_bytes(&(0..40).collect::<Vec<_>>());
+        lines.extend(["update_bytes 0..40".to_string(), buffered(&hasher)]);
+        squeeze(&mut hasher, &mut lines);
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// Compares the scripted session against the recorded snapshot, so a
+    /// change to padding, absorption or output selection shows up as a diff
+    /// of states. Run with `UPDATE_SNAPSHOTS=1` to rewrite it after an
+    /// intended change, and review the diff like any other.
+    #[test]
+    fn test_sponge_snapshot() {
+        let actual = snapshot();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/sponge_states.txt");
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(path, &actual).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            actual,
+            "sponge states changed; rerun with UPDATE_SNAPSHOTS=1 if intended"
+        );
     }
 }
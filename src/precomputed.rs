@@ -0,0 +1,412 @@
+//! The permutation parameters of the crate, stored in full so that they can
+//! be generated once and loaded without rerunning the constant generation.
+//!
+//! [`PrecomputedSpec::new`] derives the round constants with the Grain LFSR,
+//! then inverts and factors the MDS matrix into sparse matrices, which
+//! dominates creating a hasher from scratch. A [`PrecomputedSpec`] holds the
+//! result: the round numbers, the optimized constants and every matrix. It is
+//! the spec every hasher and chip of the crate is built from.
+//!
+//! [`PrecomputedSpec::to_bytes`] writes, with big-endian integers and
+//! elements in the canonical bytes of [`encoding`]:
+//!
+//! ```text
+//! "PSPC" | version 1 (1 byte) | T | RATE | r_f | r_p | modulus length (4 bytes each)
+//! modulus, the `PrimeField::MODULUS` string of the field
+//! start constants    (r_f / 2 + 1) * T
+//! partial constants  r_p
+//! end constants      (r_f / 2 - 1) * T
+//! MDS matrix         T * T, by rows
+//! pre-sparse matrix  T * T, by rows
+//! sparse matrices    r_p * (T + RATE), each row then column
+//! ```
+//!
+//! [`PrecomputedSpec::from_parts`] builds a spec from round constants and an
+//! MDS matrix generated elsewhere, such as audited parameters of another
+//! implementation, folding the constants and factoring the matrix for the
+//! optimized permutation.
+//! [`PrecomputedSpec::new`] builds it from the Grain LFSR output of
+//! [`crate::audit::seed`], the constants of the Poseidon reference
+//! implementation.
+use halo2curves::group::ff::PrimeField;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{encoding, grain, optimize, spec::PoseidonSpec};
+
+const MAGIC: &[u8; 4] = b"PSPC";
+const VERSION: u8 = 1;
+
+/// Every constant of a permutation with `T` state elements, `RATE = T - 1`
+/// of them absorbing
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "Vec<u8>", into = "Vec<u8>", bound = "")
+)]
+pub struct PrecomputedSpec<F: PrimeField, const T: usize, const RATE: usize> {
+    r_f: usize,
+    start: Vec<[F; T]>,
+    partial: Vec<F>,
+    end: Vec<[F; T]>,
+    mds: [[F; T]; T],
+    pre_sparse_mds: [[F; T]; T],
+    sparse: Vec<([F; T], [F; RATE])>,
+}
+
+fn take<F, const N: usize>(elements: &mut impl Iterator<Item = F>) -> [F; N] {
+    std::array::from_fn(|_| elements.next().unwrap())
+}
+
+impl<F: PrimeField, const T: usize, const RATE: usize> PrecomputedSpec<F, T, RATE> {
+    /// The spec with `r_f` full and `r_p` partial rounds, generated by the
+    /// Grain LFSR
+    ///
+    /// # Panics
+    ///
+    /// If [`Self::from_parts`] rejects the generated parameters, for
+    /// `RATE + 1 != T` or an odd or zero `r_f`
+    pub fn new(r_f: usize, r_p: usize) -> Self {
+        let (constants, mds) = grain::parameters(r_f, r_p);
+        Self::from_parts(r_f, constants, mds).expect("invalid round numbers or width")
+    }
+
+    /// The spec of the permutation with the round constants `constants`, one
+    /// array per round, and the MDS matrix `mds`, whose first and last
+    /// `r_f / 2` rounds are full and the others partial
+    ///
+    /// Returns `None` if `RATE + 1 != T`, `r_f` is odd or zero, there are
+    /// fewer than `r_f` rounds of constants, `mds` is singular, or one of the
+    /// `RATE x RATE` matrices the partial rounds factor out is. Only
+    /// invertibility is checked, not the MDS property.
+    pub fn from_parts(r_f: usize, constants: Vec<[F; T]>, mds: [[F; T]; T]) -> Option<Self> {
+        if RATE + 1 != T || r_f < 2 || !r_f.is_multiple_of(2) {
+            return None;
+        }
+        let optimized = optimize::optimize::<F, T, RATE>(r_f, &constants, &mds)?;
+        Some(Self {
+            r_f,
+            start: optimized.start,
+            partial: optimized.partial,
+            end: optimized.end,
+            mds,
+            pre_sparse_mds: optimized.pre_sparse_mds,
+            sparse: optimized.sparse,
+        })
+    }
+
+    pub fn r_f(&self) -> usize {
+        self.r_f
+    }
+
+    pub fn r_p(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Folded constants of the first half of the full rounds, the first
+    /// entry added before any S-box
+    pub fn start_constants(&self) -> &[[F; T]] {
+        &self.start
+    }
+
+    /// Folded constants of the partial rounds, one per round
+    pub fn partial_constants(&self) -> &[F] {
+        &self.partial
+    }
+
+    /// Folded constants of the second half of the full rounds but the last
+    pub fn end_constants(&self) -> &[[F; T]] {
+        &self.end
+    }
+
+    /// MDS matrix of the full rounds
+    pub fn mds(&self) -> [[F; T]; T] {
+        self.mds
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &F> {
+        self.start
+            .iter()
+            .flatten()
+            .chain(&self.partial)
+            .chain(self.end.iter().flatten())
+            .chain(self.mds.iter().flatten())
+            .chain(self.pre_sparse_mds.iter().flatten())
+            .chain(
+                self.sparse
+                    .iter()
+                    .flat_map(|(row, col)| row.iter().chain(col)),
+            )
+    }
+
+    /// The format of the [module documentation](self)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        for n in [T, RATE, self.r_f, self.r_p(), F::MODULUS.len()] {
+            bytes.extend((n as u32).to_be_bytes());
+        }
+        bytes.extend(F::MODULUS.as_bytes());
+        for element in self.elements() {
+            bytes.extend(encoding::to_be_bytes(element));
+        }
+        bytes
+    }
+
+    /// Inverse of [`Self::to_bytes`]. Returns `None` for another format
+    /// version, width or field, odd or missing full rounds, a length that
+    /// does not match the round numbers, or non-canonical elements.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(MAGIC)?.strip_prefix(&[VERSION])?;
+        if rest.len() < 20 {
+            return None;
+        }
+        let (header, rest) = rest.split_at(20);
+        let mut header = header
+            .chunks(4)
+            .map(|n| u32::from_be_bytes(n.try_into().unwrap()) as usize);
+        let [t, rate, r_f, r_p, modulus] = take(&mut header);
+        if t != T || rate != RATE || r_f < 2 || !r_f.is_multiple_of(2) {
+            return None;
+        }
+        if modulus != F::MODULUS.len() {
+            return None;
+        }
+        let rest = rest.strip_prefix(F::MODULUS.as_bytes())?;
+
+        let size = F::Repr::default().as_ref().len();
+        let count = (r_f + 2 * T) * T + r_p * (1 + T + RATE);
+        if rest.len() != count * size {
+            return None;
+        }
+        let mut elements = rest
+            .chunks(size)
+            .map(encoding::from_be_bytes::<F>)
+            .collect::<Option<Vec<_>>>()?
+            .into_iter();
+        let elements = &mut elements;
+        Some(Self {
+            r_f,
+            start: (0..r_f / 2 + 1).map(|_| take(elements)).collect(),
+            partial: elements.take(r_p).collect(),
+            end: (0..r_f / 2 - 1).map(|_| take(elements)).collect(),
+            mds: std::array::from_fn(|_| take(elements)),
+            pre_sparse_mds: std::array::from_fn(|_| take(elements)),
+            sparse: (0..r_p).map(|_| (take(elements), take(elements))).collect(),
+        })
+    }
+}
+
+impl<F: PrimeField, const T: usize, const RATE: usize> PoseidonSpec<F, T, RATE>
+    for PrecomputedSpec<F, T, RATE>
+{
+    fn full_rounds(&self) -> usize {
+        self.r_f
+    }
+
+    fn partial_rounds(&self) -> usize {
+        self.partial.len()
+    }
+
+    fn start_constants(&self) -> &[[F; T]] {
+        &self.start
+    }
+
+    fn partial_constants(&self) -> &[F] {
+        &self.partial
+    }
+
+    fn end_constants(&self) -> &[[F; T]] {
+        &self.end
+    }
+
+    fn mds(&self) -> [[F; T]; T] {
+        self.mds
+    }
+
+    fn pre_sparse_mds(&self) -> [[F; T]; T] {
+        self.pre_sparse_mds
+    }
+
+    fn sparse_mds(&self, round: usize) -> (&[F; T], &[F; RATE]) {
+        let (row, col) = &self.sparse[round];
+        (row, col)
+    }
+}
+
+/// [`PrecomputedSpec::to_bytes`], also the serde encoding of a spec
+impl<F: PrimeField, const T: usize, const RATE: usize> From<PrecomputedSpec<F, T, RATE>>
+    for Vec<u8>
+{
+    fn from(spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        spec.to_bytes()
+    }
+}
+
+impl<F: PrimeField, const T: usize, const RATE: usize> TryFrom<Vec<u8>>
+    for PrecomputedSpec<F, T, RATE>
+{
+    type Error = &'static str;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::from_bytes(&bytes).ok_or("invalid precomputed spec")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::ff::Field,
+        pasta::Fp,
+    };
+
+    use super::*;
+    use crate::{
+        poseidon_hash::{PoseidonHash, State},
+        reference::Reference,
+        ro_types::ROTrait,
+    };
+
+    /// Checks the optimized permutation of `spec` against `reference`
+    fn check_permutation(spec: &PrecomputedSpec<Fr, 3, 2>, reference: &Reference<Fr, 3>) {
+        for (a, b) in [(0, 0), (1, 2), (7, 11)] {
+            let mut state = State::<Fr, 3, 2>::new([Fr::from(5); 3]);
+            state.permutation(spec, &[Fr::from(a), Fr::from(b)]);
+            let mut expected = [Fr::from(5), Fr::from(5 + a), Fr::from(5 + b)];
+            reference.permute(&mut expected);
+            assert_eq!(state.inner, expected);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let precomputed = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        let bytes = precomputed.to_bytes();
+        assert_eq!(
+            bytes.len(),
+            5 + 20 + Fr::MODULUS.len() + (8 * 4 + 2 * 16 + 56 * 8) * 32
+        );
+        assert_eq!(
+            PrecomputedSpec::from_bytes(&bytes),
+            Some(precomputed.clone())
+        );
+        assert_eq!((precomputed.r_f(), precomputed.r_p()), (8, 56));
+
+        let inputs = (0..5).map(Fr::from).collect::<Vec<_>>();
+        let mut generated = PoseidonHash::<G1Affine, Fr, 4, 3>::new(precomputed.clone());
+        let mut loaded =
+            PoseidonHash::<G1Affine, Fr, 4, 3>::new(PrecomputedSpec::from_bytes(&bytes).unwrap());
+        generated.update(&inputs);
+        loaded.update(&inputs);
+        assert_eq!(loaded.squeeze(), generated.squeeze());
+        assert_eq!(loaded.squeeze(), generated.squeeze());
+    }
+
+    #[test]
+    fn test_new() {
+        // the first round constant and MDS entry of the reference
+        // implementation for x^5 over bn256 with t = 3, r_f = 8, r_p = 57
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new(8, 57);
+        assert_eq!(
+            encoding::to_hex(&spec.start_constants()[0][0]),
+            "0x0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e"
+        );
+        assert_eq!(
+            encoding::to_hex(&spec.mds()[0][0]),
+            "0x109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b"
+        );
+        check_permutation(
+            &PrecomputedSpec::new(6, 20),
+            &Reference::<Fr, 3>::new(6, 20),
+        );
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let reference = Reference::<Fr, 3>::new(8, 56);
+        let spec =
+            PrecomputedSpec::from_parts(8, reference.constants.clone(), reference.mds).unwrap();
+        check_permutation(&spec, &reference);
+        assert_eq!((spec.r_f(), spec.r_p()), (8, 56));
+
+        let inputs = (0..5).map(Fr::from).collect::<Vec<_>>();
+        let mut generated = PoseidonHash::<G1Affine, Fr, 3, 2>::new(PrecomputedSpec::new(8, 56));
+        let mut parts = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec);
+        generated.update(&inputs);
+        parts.update(&inputs);
+        assert_eq!(parts.squeeze(), generated.squeeze());
+
+        // constants and a Cauchy matrix of no generator, and no partial round
+        for (r_f, r_p) in [(6, 9), (4, 0)] {
+            let reference = Reference::<Fr, 3> {
+                r_f,
+                r_p,
+                constants: (0..r_f + r_p)
+                    .map(|round| std::array::from_fn(|i| Fr::from((3 * round + i) as u64 + 1)))
+                    .collect(),
+                mds: std::array::from_fn(|i| {
+                    std::array::from_fn(|j| Fr::from((i + j + 3) as u64).invert().unwrap())
+                }),
+            };
+            let spec = PrecomputedSpec::from_parts(r_f, reference.constants.clone(), reference.mds)
+                .unwrap();
+            check_permutation(&spec, &reference);
+        }
+    }
+
+    #[test]
+    fn test_from_parts_rejects() {
+        let reference = Reference::<Fr, 3>::new(8, 56);
+        let parts = |r_f, constants: &[[Fr; 3]], mds| {
+            PrecomputedSpec::<Fr, 3, 2>::from_parts(r_f, constants.to_vec(), mds)
+        };
+        assert_eq!(parts(7, &reference.constants, reference.mds), None);
+        assert_eq!(parts(0, &reference.constants, reference.mds), None);
+        assert_eq!(parts(8, &reference.constants[..7], reference.mds), None);
+        assert_eq!(parts(8, &reference.constants, [[Fr::ONE; 3]; 3]), None);
+        // invertible, but with a singular lower-right block
+        let mds = [
+            [Fr::ZERO, Fr::ONE, Fr::ZERO],
+            [Fr::ONE, Fr::ONE, Fr::ONE],
+            [Fr::ZERO, Fr::ONE, Fr::ONE],
+        ];
+        assert_eq!(parts(8, &reference.constants, mds), None);
+        assert_eq!(
+            PrecomputedSpec::<Fr, 3, 3>::from_parts(8, vec![[Fr::ONE; 3]; 64], reference.mds),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_parameters() {
+        let bytes = PrecomputedSpec::<Fr, 3, 2>::new(8, 56).to_bytes();
+        assert_eq!(PrecomputedSpec::<Fr, 4, 3>::from_bytes(&bytes), None);
+        assert_eq!(PrecomputedSpec::<Fp, 3, 2>::from_bytes(&bytes), None);
+        assert_eq!(
+            PrecomputedSpec::<Fr, 3, 2>::from_bytes(&bytes[..bytes.len() - 1]),
+            None
+        );
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(PrecomputedSpec::<Fr, 3, 2>::from_bytes(&version), None);
+        // the last element set above the modulus
+        let mut element = bytes.clone();
+        let last = element.len() - 32;
+        element[last..].fill(0xff);
+        assert_eq!(PrecomputedSpec::<Fr, 3, 2>::from_bytes(&element), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let precomputed = PrecomputedSpec::<Fr, 3, 2>::new(8, 56);
+        let encoded = bincode::serialize(&precomputed).unwrap();
+        assert_eq!(
+            bincode::deserialize::<PrecomputedSpec<Fr, 3, 2>>(&encoded).unwrap(),
+            precomputed
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Slow reference permutation for testing the optimized one.
+//!
+//! Round constants and the MDS matrix come straight out of the Grain LFSR
+//! described in the Poseidon paper, in generation order, and every round adds
+//! the constants, applies the S-boxes and multiplies by the dense MDS matrix.
+//! There is no constant folding and no sparse matrix.
+use halo2curves::group::ff::PrimeField;
+
+use crate::grain;
+
+pub(crate) struct Reference<F: PrimeField, const T: usize> {
+    pub(crate) r_f: usize,
+    pub(crate) r_p: usize,
+    /// Constants of every round, in permutation order
+    pub(crate) constants: Vec<[F; T]>,
+    pub(crate) mds: [[F; T]; T],
+}
+
+impl<F: PrimeField, const T: usize> Reference<F, T> {
+    pub(crate) fn new(r_f: usize, r_p: usize) -> Self {
+        let (constants, mds) = grain::parameters(r_f, r_p);
+        Self {
+            r_f,
+            r_p,
+            constants,
+            mds,
+        }
+    }
+
+    pub(crate) fn permute(&self, state: &mut [F; T]) {
+        let pow5 = |v: F| v.square().square() * v;
+        let half = self.r_f / 2;
+        for (round, constants) in self.constants.iter().enumerate() {
+            for (s, c) in state.iter_mut().zip(constants.iter()) {
+                *s += c;
+            }
+            if round < half || round >= half + self.r_p {
+                for s in state.iter_mut() {
+                    *s = pow5(*s);
+                }
+            } else {
+                state[0] = pow5(state[0]);
+            }
+            let previous = *state;
+            for (s, row) in state.iter_mut().zip(self.mds.iter()) {
+                *s = row
+                    .iter()
+                    .zip(previous.iter())
+                    .fold(F::ZERO, |acc, (m, v)| acc + *m * v);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Deterministic randomness squeezed out of a seeded [`PoseidonHash`].
+//!
+//! [`PoseidonRng`] takes a sponge that has absorbed the seed, field elements
+//! or bytes, and squeezes it once per output element. The elements are the
+//! consecutive squeezes of the sponge, so a circuit holding the same seed
+//! recomputes them with [`PoseidonChip`]. Bytes come from the low
+//! [`PoseidonRng::BYTES_PER_ELEMENT`] bytes of each element: 128 bits below
+//! the capacity, so they are within `2^-128` of uniform.
+//!
+//! [`PoseidonChip`]: crate::poseidon_circuit::PoseidonChip
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+use rand_core::{impls, CryptoRng, Error, RngCore};
+
+use crate::{poseidon_hash::PoseidonHash, ro_types::ROTrait};
+
+pub struct PoseidonRng<C, F, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    hasher: PoseidonHash<C, F, T, RATE>,
+    /// Bytes of the last element not handed out yet, in reverse order
+    pending: Vec<u8>,
+}
+
+impl<C, F, const T: usize, const RATE: usize> PoseidonRng<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    /// Bytes taken from each element
+    pub const BYTES_PER_ELEMENT: usize = (F::CAPACITY as usize - 128) / 8;
+
+    /// A generator squeezing `hasher`, which should have absorbed the seed
+    ///
+    /// # Panics
+    ///
+    /// If the field has fewer than 137 bits, too few for a byte at `2^-128`
+    /// from uniform
+    pub fn new(hasher: PoseidonHash<C, F, T, RATE>) -> Self {
+        assert!(Self::BYTES_PER_ELEMENT > 0, "field too small");
+        Self {
+            hasher,
+            pending: Vec::new(),
+        }
+    }
+
+    /// The next element of the stream. Bytes left over from the previous
+    /// element are dropped, so that elements and bytes never overlap
+    pub fn next_element(&mut self) -> F {
+        self.pending.clear();
+        self.hasher.squeeze()
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize> RngCore for PoseidonRng<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            if self.pending.is_empty() {
+                let repr = self.hasher.squeeze().to_repr();
+                self.pending
+                    .extend(repr.as_ref()[..Self::BYTES_PER_ELEMENT].iter().rev());
+            }
+            *byte = self.pending.pop().unwrap();
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize> CryptoRng for PoseidonRng<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::precomputed::PrecomputedSpec;
+
+    type PH = PoseidonHash<G1Affine, Fr, 3, 2>;
+
+    fn seeded(seed: &[u8]) -> PH {
+        let mut hasher = PH::new(PrecomputedSpec::new(8, 56));
+        hasher.update_bytes(seed);
+        hasher
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut rng = PoseidonRng::new(seeded(b"seed"));
+        assert_eq!(PoseidonRng::<G1Affine, Fr, 3, 2>::BYTES_PER_ELEMENT, 15);
+        let mut bytes = [0; 40];
+        rng.fill_bytes(&mut bytes);
+        let element = rng.next_element();
+
+        let mut hasher = seeded(b"seed");
+        let outputs = hasher.squeeze_n(4);
+        let expected = outputs[..3]
+            .iter()
+            .flat_map(|output| output.to_repr().as_ref()[..15].to_vec())
+            .take(40)
+            .collect::<Vec<_>>();
+        assert_eq!(bytes.to_vec(), expected);
+        // the rest of the third element is dropped
+        assert_eq!(element, outputs[3]);
+
+        let mut again = PoseidonRng::new(seeded(b"seed"));
+        let mut other = PoseidonRng::new(seeded(b"seeds"));
+        let first = again.next_u64();
+        assert_eq!(
+            first.to_le_bytes().to_vec(),
+            outputs[0].to_repr().as_ref()[..8].to_vec()
+        );
+        assert_ne!(other.next_u64(), first);
+    }
+}
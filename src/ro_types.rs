@@ -14,21 +14,35 @@ pub trait ROTrait<C: CurveAffine> {
     /// Initializes the hash function
     fn new(constants: Self::Constants) -> Self;
 
+    /// Adds an element to the internal state
+    fn absorb(&mut self, input: C::Scalar);
+
     /// Returns a challenge by hashing the internal state
     fn squeeze(&mut self) -> C::Scalar;
 }
 
 /// A helper trait that defines the behavior of a hash function that we use as an RO in the circuit model
+///
+/// Absorbing the same elements as an [`ROTrait`] implementation over the same
+/// constants and squeezing once gives its challenge. Absorb outside the
+/// closures of `Layouter::assign_region`, which a floor planner may run more
+/// than once.
 pub trait ROCircuitTrait<C: CurveAffine> {
     /// A type representing constants/parameters associated with the hash function
     type Constants: ROConstantsTrait;
 
+    /// Columns the hash function is laid out in
+    type Config;
+
     /// Initializes the hash function
-    fn new(constants: Self::Constants) -> Self;
+    fn new(config: Self::Config, constants: Self::Constants) -> Self;
 
-    #[allow(clippy::type_complexity)]
+    /// Adds an assigned element to the internal state
+    fn absorb(&mut self, input: &AssignedCell<C::Scalar, C::Scalar>);
+
+    /// Returns a challenge by hashing the internal state
     fn squeeze(
         &mut self,
         ctx: &mut RegionCtx<'_, C::Scalar>,
-    ) -> Result<Vec<AssignedCell<C::Scalar, C::Scalar>>, Error>;
+    ) -> Result<AssignedCell<C::Scalar, C::Scalar>, Error>;
 }
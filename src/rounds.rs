@@ -0,0 +1,295 @@
+//! Round numbers for the `x^5` S-box at a target security level.
+//!
+//! This follows the round-number script accompanying the Poseidon paper:
+//! the smallest-cost pair resisting statistical, interpolation and Gröbner
+//! basis attacks is searched for, then a security margin of two full rounds
+//! and 7.5% more partial rounds is added. Cost is the number of S-boxes,
+//! `t * r_f + r_p`.
+//!
+//! [`validate_security`] checks chosen round numbers against the same
+//! attacks, and [`CheckedSpec::new_checked`] generates a spec only for round
+//! numbers that pass. [`CheckedSpec::with_security`] generates the spec of
+//! [`round_numbers`] for its field and width, so callers need not copy round
+//! numbers such as `(8, 56)`.
+
+use std::fmt;
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::precomputed::PrecomputedSpec;
+
+const ALPHA: f64 = 5.0;
+
+fn log(x: f64, base: f64) -> f64 {
+    x.ln() / base.ln()
+}
+
+/// `log2` of the binomial coefficient `n choose k`
+fn log2_binomial(n: usize, k: usize) -> f64 {
+    (1..=k)
+        .map(|i| ((n - k + i) as f64).log2() - (i as f64).log2())
+        .sum()
+}
+
+/// How far `r_f` full and `r_p` partial rounds exceed what each attack
+/// considered by the paper requires. Negative margins are attacks the rounds
+/// do not resist.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Margins {
+    /// Full rounds beyond the statistical attacks' minimum
+    pub statistical: f64,
+    /// Partial rounds beyond the interpolation attack's minimum
+    pub interpolation: f64,
+    /// Partial rounds beyond the minimum of the first Gröbner basis attack
+    pub groebner_1: f64,
+    /// Partial rounds beyond the minimum of the second Gröbner basis attack
+    pub groebner_2: f64,
+    /// Bits of cost beyond the security level for the Gröbner basis attack
+    /// of the 2023 revision
+    pub groebner_3: f64,
+}
+
+impl Margins {
+    pub fn new(field_bits: u32, t: usize, r_f: usize, r_p: usize, security: u32) -> Self {
+        let (n, m) = (field_bits as f64, security as f64);
+        let (t_f, r_f_f, r_p_f) = (t as f64, r_f as f64, r_p as f64);
+
+        // statistical attacks
+        let r_f_min = if m <= (n - (ALPHA - 1.0) / 2.0).floor() * (t_f + 1.0) {
+            6.0
+        } else {
+            10.0
+        };
+        // interpolation and Gröbner basis attacks
+        let r_p_1 = (log(2.0, ALPHA) * m.min(n)).ceil() + log(t_f, ALPHA).ceil() + 1.0 - r_f_f;
+        let r_p_2 = (log(2.0, ALPHA) * m.min(n) / 3.0 + log(t_f, ALPHA)).ceil() - r_f_f;
+        let r_p_3 = t_f - 1.0 + log(2.0, ALPHA) * (m / (t_f + 1.0)).min(n / 2.0) - r_f_f;
+        // Gröbner basis attack of the 2023 revision
+        let r_temp = t / 3;
+        let over = (r_f - 1) * t + r_p + r_temp + r_temp * (r_f / 2) + r_p + ALPHA as usize;
+        let under = r_temp * (r_f / 2) + r_p + ALPHA as usize;
+        let cost_gb4 = (2.0 * log2_binomial(over, under)).ceil();
+
+        Self {
+            statistical: r_f_f - r_f_min,
+            interpolation: r_p_f - r_p_1,
+            groebner_1: r_p_f - r_p_2,
+            groebner_2: r_p_f - r_p_3,
+            groebner_3: cost_gb4 - m,
+        }
+    }
+
+    fn named(&self) -> [(&'static str, f64); 5] {
+        [
+            ("statistical", self.statistical),
+            ("interpolation", self.interpolation),
+            ("groebner_1", self.groebner_1),
+            ("groebner_2", self.groebner_2),
+            ("groebner_3", self.groebner_3),
+        ]
+    }
+
+    /// Whether every attack is resisted
+    pub fn is_secure(&self) -> bool {
+        self.named().iter().all(|(_, margin)| *margin >= 0.0)
+    }
+
+    /// Names of the attacks not resisted, in field order
+    pub fn failures(&self) -> Vec<&'static str> {
+        self.named()
+            .into_iter()
+            .filter(|(_, margin)| *margin < 0.0)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// Why [`validate_security`] rejects parameters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SecurityError {
+    /// `t` is below 2, `rate` is not `t - 1`, or `r_f` is odd or zero
+    Shape,
+    /// Some attack is not resisted, see [`Margins::failures`]
+    Insecure(Margins),
+}
+
+impl fmt::Display for SecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shape => f.write_str("invalid width, rate or number of full rounds"),
+            Self::Insecure(margins) => write!(
+                f,
+                "round numbers do not resist: {}",
+                margins.failures().join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SecurityError {}
+
+/// The margins of `r_f` full and `r_p` partial rounds of a width-`t`
+/// permutation absorbing `rate` words, over a field of `field_bits` bits,
+/// if they resist every attack at `security` bits
+pub fn validate_security(
+    t: usize,
+    rate: usize,
+    r_f: usize,
+    r_p: usize,
+    field_bits: u32,
+    security: u32,
+) -> Result<Margins, SecurityError> {
+    if t < 2 || rate + 1 != t || r_f < 2 || !r_f.is_multiple_of(2) {
+        return Err(SecurityError::Shape);
+    }
+    let margins = Margins::new(field_bits, t, r_f, r_p, security);
+    if margins.is_secure() {
+        Ok(margins)
+    } else {
+        Err(SecurityError::Insecure(margins))
+    }
+}
+
+/// Specs generated for a security level
+pub trait CheckedSpec: Sized {
+    /// [`PrecomputedSpec::new`], for round numbers passing
+    /// [`validate_security`] only
+    fn new_checked(r_f: usize, r_p: usize, security: u32) -> Result<Self, SecurityError>;
+
+    /// The spec with the [`round_numbers`] of its field and width at
+    /// `security` bits
+    ///
+    /// # Panics
+    ///
+    /// If [`round_numbers`] does, for a security level out of range
+    fn with_security(security: u32) -> Self;
+}
+
+impl<F, const T: usize, const RATE: usize> CheckedSpec for PrecomputedSpec<F, T, RATE>
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn new_checked(r_f: usize, r_p: usize, security: u32) -> Result<Self, SecurityError> {
+        validate_security(T, RATE, r_f, r_p, F::NUM_BITS, security)?;
+        Ok(PrecomputedSpec::new(r_f, r_p))
+    }
+    fn with_security(security: u32) -> Self {
+        let (r_f, r_p) = round_numbers(F::NUM_BITS, T, security);
+        PrecomputedSpec::new(r_f, r_p)
+    }
+}
+
+/// Whether `r_f` full and `r_p` partial rounds resist the attacks considered
+/// by the paper, without any margin
+fn is_secure(field_bits: u32, t: usize, r_f: usize, r_p: usize, security: u32) -> bool {
+    Margins::new(field_bits, t, r_f, r_p, security).is_secure()
+}
+
+/// `(r_f, r_p)` including the security margin for a width-`t` permutation
+/// over a field of `field_bits` bits
+///
+/// # Panics
+///
+/// If no round numbers below 100 full and 500 partial rounds reach
+/// `security`
+pub fn round_numbers(field_bits: u32, t: usize, security: u32) -> (usize, usize) {
+    let mut best: Option<(usize, usize, usize)> = None;
+    for r_p in 1..500 {
+        for r_f in (4..100).step_by(2) {
+            if is_secure(field_bits, t, r_f, r_p, security) {
+                let (r_f, r_p) = (r_f + 2, (r_p as f64 * 1.075).ceil() as usize);
+                let cost = t * r_f + r_p;
+                if !matches!(best, Some((best_cost, _, _)) if best_cost <= cost) {
+                    best = Some((cost, r_f, r_p));
+                }
+                // more full rounds only cost more
+                break;
+            }
+        }
+    }
+    let (_, r_f, r_p) = best.expect("security level out of range");
+    (r_f, r_p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_numbers() {
+        // bn256 scalar field
+        for t in 2..=5 {
+            assert_eq!(round_numbers(254, t, 128), (8, 56));
+        }
+        for t in 6..=8 {
+            assert_eq!(round_numbers(254, t, 128), (8, 57));
+        }
+        assert_eq!(round_numbers(254, 3, 80), (8, 34));
+        // pasta fields
+        assert_eq!(round_numbers(255, 3, 128), (8, 56));
+    }
+
+    #[test]
+    fn test_margins() {
+        let margins = Margins::new(254, 3, 8, 56, 128);
+        assert!(margins.is_secure());
+        // 6 full rounds against statistical attacks, and 58 - 8 partial
+        // rounds against interpolation
+        assert_eq!(margins.statistical, 2.0);
+        assert_eq!(margins.interpolation, 6.0);
+        assert_eq!(margins.groebner_1, 44.0);
+        assert_eq!(margins.groebner_3, 149.0);
+        assert!(!Margins::new(254, 3, 4, 56, 128).is_secure());
+        assert_eq!(Margins::new(254, 3, 8, 40, 128).interpolation, -10.0);
+        assert_eq!(
+            Margins::new(254, 3, 8, 40, 128).failures(),
+            vec!["interpolation"]
+        );
+    }
+
+    #[test]
+    fn test_validate_security() {
+        assert_eq!(
+            validate_security(3, 2, 8, 56, 254, 128),
+            Ok(Margins::new(254, 3, 8, 56, 128))
+        );
+        let err = validate_security(3, 2, 8, 4, 254, 128).unwrap_err();
+        assert!(matches!(err, SecurityError::Insecure(_)));
+        assert_eq!(
+            err.to_string(),
+            "round numbers do not resist: interpolation, groebner_1, groebner_2, groebner_3"
+        );
+        for (t, rate, r_f) in [(1, 0, 8), (3, 3, 8), (3, 2, 7), (3, 2, 0)] {
+            assert_eq!(
+                validate_security(t, rate, r_f, 56, 254, 128),
+                Err(SecurityError::Shape)
+            );
+        }
+    }
+
+    #[test]
+    fn test_new_checked() {
+        use halo2curves::bn256::Fr;
+
+        let spec = PrecomputedSpec::<Fr, 3, 2>::new_checked(8, 56, 128).unwrap();
+        assert_eq!(spec.r_f(), 8);
+        assert!(PrecomputedSpec::<Fr, 3, 2>::new_checked(8, 4, 128).is_err());
+        assert!(PrecomputedSpec::<Fr, 3, 2>::new_checked(4, 56, 128).is_err());
+    }
+
+    #[test]
+    fn test_with_security() {
+        use halo2curves::{bn256::Fr, pasta::Fp};
+
+        let spec = PrecomputedSpec::<Fr, 3, 2>::with_security(128);
+        assert_eq!(spec.r_f(), 8);
+        assert_eq!(spec.r_p(), 56);
+        assert_eq!(PrecomputedSpec::<Fr, 6, 5>::with_security(128).r_p(), 57);
+        assert_eq!(PrecomputedSpec::<Fp, 3, 2>::with_security(80).r_p(), 34);
+        // the same constants as the round numbers spelled out
+        assert_eq!(spec, PrecomputedSpec::new(8, 56));
+    }
+}
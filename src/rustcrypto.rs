@@ -0,0 +1,154 @@
+//! RustCrypto [`digest`] trait implementations over the byte-absorbing mode
+//! of [`PoseidonHash`], so the hash can be used wherever a generic
+//! `D: Digest` is expected. The 32-byte output is the big-endian encoding of
+//! the squeezed element.
+use digest::{
+    consts::U32, FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update,
+};
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    cached::CachedSpec, encoding::to_be_bytes, poseidon_hash::PoseidonHash,
+    precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+/// [`PoseidonHash`] with the round numbers fixed by the type, as required by
+/// `Default`. The spec comes from [`CachedSpec::cached`], so generic code
+/// creating a digest per message does not regenerate the constants
+#[derive(Clone, Debug)]
+pub struct PoseidonDigest<
+    C,
+    F,
+    const T: usize,
+    const RATE: usize,
+    const R_F: usize,
+    const R_P: usize,
+> where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    spec: &'static PrecomputedSpec<F, T, RATE>,
+    hasher: PoseidonHash<C, F, T, RATE>,
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize>
+    PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn squeeze_bytes(&mut self, out: &mut Output<Self>) {
+        let bytes = to_be_bytes(&self.hasher.squeeze());
+        assert_eq!(bytes.len(), out.len(), "field elements must be 32 bytes");
+        out.copy_from_slice(&bytes);
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> Default
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn default() -> Self {
+        let spec = PrecomputedSpec::cached(R_F, R_P);
+        Self {
+            hasher: PoseidonHash::new(spec.clone()),
+            spec,
+        }
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> HashMarker
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> Update
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update_bytes(data);
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> OutputSizeUser
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    type OutputSize = U32;
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> FixedOutput
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn finalize_into(mut self, out: &mut Output<Self>) {
+        self.squeeze_bytes(out);
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> Reset
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn reset(&mut self) {
+        self.hasher = PoseidonHash::new(self.spec.clone());
+    }
+}
+
+impl<C, F, const T: usize, const RATE: usize, const R_F: usize, const R_P: usize> FixedOutputReset
+    for PoseidonDigest<C, F, T, RATE, R_F, R_P>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        self.squeeze_bytes(out);
+        Reset::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest;
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+
+    type Bn256Digest = PoseidonDigest<G1Affine, Fr, 4, 3, 8, 56>;
+
+    fn generic_digest<D: Digest>(data: &[u8]) -> Vec<u8> {
+        D::digest(data).to_vec()
+    }
+
+    #[test]
+    fn test_digest() {
+        let data = b"poseidon";
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 4, 3>::new(PrecomputedSpec::new(8, 56));
+        hasher.update_bytes(data);
+        let expected = to_be_bytes(&hasher.squeeze());
+
+        assert_eq!(generic_digest::<Bn256Digest>(data), expected);
+
+        let mut digest = Bn256Digest::new();
+        Digest::update(&mut digest, &data[..3]);
+        Digest::update(&mut digest, &data[3..]);
+        assert_eq!(digest.finalize_reset().to_vec(), expected);
+        Digest::update(&mut digest, data);
+        assert_eq!(digest.finalize().to_vec(), expected);
+    }
+}
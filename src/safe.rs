@@ -0,0 +1,248 @@
+//! The SAFE sponge API (Sponge API for Field Elements) over the Poseidon
+//! permutation.
+//!
+//! A [`SafeSponge`] is started with an IO pattern, the sequence of absorb
+//! and squeeze calls the protocol will make, and a domain separator. Both
+//! are hashed into the tag of the SAFE specification: consecutive calls of
+//! the same kind are aggregated, every call is encoded as a 32-bit
+//! big-endian word, `0x80000000 + len` for an absorb and `len` for a
+//! squeeze, the domain separator is appended and the first 16 bytes of the
+//! SHA3-256 digest, read as a little-endian integer, give the tag. The tag is
+//! the initial capacity word, so sponges with different patterns or domains
+//! start from different states.
+//!
+//! Unlike [`PoseidonHash`](crate::poseidon_hash::PoseidonHash) there is no
+//! padding: the pattern fixes every length. Absorbed elements are added to
+//! the rate words `1..=RATE` in order and the state is permuted only when
+//! the rate is full or an output is needed. Every call is checked against
+//! the pattern, and a call that deviates from it panics, as does
+//! [`SafeSponge::finish`] before the pattern is complete.
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+use sha3::{Digest, Sha3_256};
+
+use crate::{poseidon_hash::State, precomputed::PrecomputedSpec};
+
+/// One call of an IO pattern, with its number of elements
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoCall {
+    Absorb(u32),
+    Squeeze(u32),
+}
+
+impl IoCall {
+    fn word(&self) -> u32 {
+        match self {
+            IoCall::Absorb(len) => 0x8000_0000 | len,
+            IoCall::Squeeze(len) => *len,
+        }
+    }
+}
+
+/// The SAFE tag of `pattern` and `domain`
+///
+/// # Panics
+///
+/// If a call has no elements or more than `2^31 - 1`
+pub fn tag(pattern: &[IoCall], domain: &[u8]) -> u128 {
+    let mut words: Vec<IoCall> = Vec::new();
+    for call in pattern {
+        let (IoCall::Absorb(len) | IoCall::Squeeze(len)) = *call;
+        assert!(len > 0 && len < 0x8000_0000, "invalid call length");
+        match (words.last_mut(), call) {
+            (Some(IoCall::Absorb(acc)), IoCall::Absorb(len)) => *acc += len,
+            (Some(IoCall::Squeeze(acc)), IoCall::Squeeze(len)) => *acc += len,
+            _ => words.push(*call),
+        }
+    }
+    let mut hasher = Sha3_256::new();
+    for call in &words {
+        hasher.update(call.word().to_be_bytes());
+    }
+    hasher.update(domain);
+    let digest = hasher.finalize();
+    u128::from_le_bytes(digest[..16].try_into().unwrap())
+}
+
+/// A sponge that only accepts the calls of its IO pattern
+#[derive(Clone, Debug)]
+pub struct SafeSponge<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize> {
+    spec: PrecomputedSpec<F, T, RATE>,
+    state: State<F, T, RATE>,
+    pattern: Vec<IoCall>,
+    /// Index of the next call in `pattern`
+    call: usize,
+    absorb_pos: usize,
+    squeeze_pos: usize,
+}
+
+impl<F: PrimeField + FromUniformBytes<64>, const T: usize, const RATE: usize>
+    SafeSponge<F, T, RATE>
+{
+    /// `START`: a sponge with the tag of `pattern` and `domain` in the
+    /// capacity
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>, pattern: &[IoCall], domain: &[u8]) -> Self {
+        let mut inner = [F::ZERO; T];
+        inner[0] = F::from_u128(tag(pattern, domain));
+        Self {
+            spec,
+            state: State::new(inner),
+            pattern: pattern.to_vec(),
+            call: 0,
+            absorb_pos: 0,
+            squeeze_pos: 0,
+        }
+    }
+
+    fn next_call(&mut self, call: IoCall) {
+        assert_eq!(
+            self.pattern.get(self.call),
+            Some(&call),
+            "call {} deviates from the IO pattern",
+            self.call
+        );
+        self.call += 1;
+    }
+
+    fn permute(&mut self) {
+        // a full block of zeros adds nothing and no padding
+        self.state.permutation(&self.spec, &[F::ZERO; RATE]);
+    }
+
+    /// `ABSORB`
+    pub fn absorb(&mut self, elements: &[F]) {
+        self.next_call(IoCall::Absorb(elements.len() as u32));
+        for element in elements {
+            if self.absorb_pos == RATE {
+                self.permute();
+                self.absorb_pos = 0;
+            }
+            self.state.inner[1 + self.absorb_pos] += element;
+            self.absorb_pos += 1;
+        }
+        // the next squeeze permutes first
+        self.squeeze_pos = RATE;
+    }
+
+    /// `SQUEEZE`
+    pub fn squeeze(&mut self, len: usize) -> Vec<F> {
+        self.next_call(IoCall::Squeeze(len as u32));
+        (0..len)
+            .map(|_| {
+                if self.squeeze_pos == RATE {
+                    self.permute();
+                    self.squeeze_pos = 0;
+                    self.absorb_pos = 0;
+                }
+                self.squeeze_pos += 1;
+                self.state.inner[self.squeeze_pos]
+            })
+            .collect()
+    }
+
+    /// `FINISH`: checks that the whole pattern was used and erases the state
+    pub fn finish(mut self) {
+        assert_eq!(self.call, self.pattern.len(), "IO pattern not completed");
+        self.state.inner = [F::ZERO; T];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::reference::Reference;
+
+    const PATTERN: [IoCall; 3] = [IoCall::Absorb(2), IoCall::Absorb(1), IoCall::Squeeze(1)];
+
+    fn sponge(pattern: &[IoCall], domain: &[u8]) -> SafeSponge<Fr, 3, 2> {
+        SafeSponge::new(PrecomputedSpec::new(8, 56), pattern, domain)
+    }
+
+    #[test]
+    fn test_tag() {
+        // 80000003 00000001 "poseidon-circuit", from Python's hashlib
+        let expected = 0xf60c3a3cc59e7b21f9fa8c259cb2d7f7;
+        assert_eq!(tag(&PATTERN, b"poseidon-circuit"), expected);
+        assert_eq!(
+            tag(
+                &[IoCall::Absorb(3), IoCall::Squeeze(1)],
+                b"poseidon-circuit"
+            ),
+            expected
+        );
+        assert_ne!(
+            tag(&[IoCall::Absorb(3), IoCall::Squeeze(1)], b"other"),
+            expected
+        );
+        assert_ne!(
+            tag(
+                &[IoCall::Absorb(4), IoCall::Squeeze(1)],
+                b"poseidon-circuit"
+            ),
+            expected
+        );
+    }
+
+    /// Permutations happen when the rate is full and before the first output
+    #[test]
+    fn test_sponge() {
+        let reference = Reference::<Fr, 3>::new(8, 56);
+        let mut state = [
+            Fr::from_u128(tag(&PATTERN, b"domain")),
+            Fr::from(1),
+            Fr::from(2),
+        ];
+        reference.permute(&mut state);
+        state[1] += Fr::from(3);
+        reference.permute(&mut state);
+
+        let mut safe = sponge(&PATTERN, b"domain");
+        safe.absorb(&[Fr::from(1), Fr::from(2)]);
+        safe.absorb(&[Fr::from(3)]);
+        assert_eq!(safe.squeeze(1), vec![state[1]]);
+        safe.finish();
+
+        // two outputs from one permutation, the third from the next
+        let pattern = [IoCall::Absorb(1), IoCall::Squeeze(3)];
+        let mut safe = sponge(&pattern, b"domain");
+        safe.absorb(&[Fr::from(1)]);
+        let outputs = safe.squeeze(3);
+        let mut state = [
+            Fr::from_u128(tag(&pattern, b"domain")),
+            Fr::from(1),
+            Fr::from(0),
+        ];
+        reference.permute(&mut state);
+        assert_eq!(outputs[..2], state[1..]);
+        reference.permute(&mut state);
+        assert_eq!(outputs[2], state[1]);
+    }
+
+    #[test]
+    fn test_domain_separation() {
+        let output = |domain: &[u8]| {
+            let mut safe = sponge(&PATTERN, domain);
+            safe.absorb(&[Fr::from(1), Fr::from(2)]);
+            safe.absorb(&[Fr::from(3)]);
+            safe.squeeze(1)
+        };
+        assert_ne!(output(b"a"), output(b"b"));
+    }
+
+    #[test]
+    #[should_panic(expected = "call 1 deviates from the IO pattern")]
+    fn test_rejects_deviation() {
+        let mut safe = sponge(&PATTERN, b"");
+        safe.absorb(&[Fr::from(1), Fr::from(2)]);
+        safe.squeeze(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "IO pattern not completed")]
+    fn test_rejects_incomplete_pattern() {
+        let mut safe = sponge(&PATTERN, b"");
+        safe.absorb(&[Fr::from(1), Fr::from(2)]);
+        safe.finish();
+    }
+}
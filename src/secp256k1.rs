@@ -0,0 +1,111 @@
+//! Parameters for the two fields of secp256k1.
+//!
+//! Both moduli are 256-bit primes with `gcd(5, p - 1) = 1`, so `x^5` is a
+//! permutation of either field. [`round_numbers`] picks eight full and 56
+//! partial rounds at width 3 and 128-bit security, and the Grain-generated
+//! matrices pass every [`MdsCheck`]; the tests below check both.
+//!
+//! [`ScalarHash`] hashes over the scalar field, the field a circuit proving
+//! statements about secp256k1 signatures works in natively. The base field
+//! is the scalar field of secq256k1, which halo2curves does not provide, so
+//! [`base_spec`] only comes with the spec: the native permutation and
+//! [`hash_fixed`] take any field.
+//!
+//! [`round_numbers`]: crate::rounds::round_numbers
+//! [`MdsCheck`]: crate::audit::MdsCheck
+//! [`hash_fixed`]: crate::fixed::hash_fixed
+use halo2curves::secp256k1::{Fp, Fq, Secp256k1Affine};
+
+use crate::{
+    cached::CachedSpec, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+};
+
+pub const R_F: usize = 8;
+pub const R_P: usize = 56;
+
+/// Sponge over the secp256k1 scalar field, absorbing points of the curve
+pub type ScalarHash = PoseidonHash<Secp256k1Affine, Fq, 3, 2>;
+
+/// The spec over the secp256k1 base field
+pub fn base_spec() -> &'static PrecomputedSpec<Fp, 3, 2> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+/// The spec over the secp256k1 scalar field
+pub fn scalar_spec() -> &'static PrecomputedSpec<Fq, 3, 2> {
+    PrecomputedSpec::cached(R_F, R_P)
+}
+
+/// A fresh [`ScalarHash`]
+pub fn scalar_hash() -> ScalarHash {
+    ScalarHash::new(scalar_spec().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::group::{
+        ff::{FromUniformBytes, PrimeField},
+        prime::PrimeCurveAffine,
+        Curve,
+    };
+
+    use super::*;
+    use crate::{
+        audit::Audit,
+        poseidon_hash::{initial_state, State},
+        reference::Reference,
+        rounds::round_numbers,
+    };
+
+    /// `(p - 1) mod 5`, from the little-endian representation of `-1`
+    fn order_mod_5<F: PrimeField>() -> u32 {
+        (-F::ONE)
+            .to_repr()
+            .as_ref()
+            .iter()
+            .rev()
+            .fold(0, |acc, byte| (acc * 256 + *byte as u32) % 5)
+    }
+
+    fn check_field<F: PrimeField + FromUniformBytes<64>>(spec: &PrecomputedSpec<F, 3, 2>) {
+        assert_ne!(order_mod_5::<F>(), 0);
+        assert_eq!(round_numbers(F::NUM_BITS, 3, 128), (R_F, R_P));
+        let audit = Audit::new(spec, 128);
+        assert!(audit.passes(), "{}", audit);
+
+        let reference = Reference::<F, 3>::new(R_F, R_P);
+        let mut state = State::<F, 3, 2>::new(initial_state());
+        let mut expected = state.inner;
+        state.permutation(spec, &[F::from(1), F::from(2)]);
+        expected[1] += F::from(1);
+        expected[2] += F::from(2);
+        reference.permute(&mut expected);
+        assert_eq!(state.inner, expected);
+    }
+
+    #[test]
+    fn test_fields() {
+        check_field(base_spec());
+        check_field(scalar_spec());
+    }
+
+    #[test]
+    fn test_scalar_hash() {
+        let point = (Secp256k1Affine::generator() * Fq::from(3)).to_affine();
+        let mut hasher = scalar_hash();
+        hasher.update(&[Fq::from(1), Fq::from(2)]);
+        hasher.absorb_point(&point);
+        let digest = hasher.squeeze();
+
+        let mut again = scalar_hash();
+        again.update(&[Fq::from(1), Fq::from(2)]);
+        again.absorb_point(&point);
+        assert_eq!(again.squeeze(), digest);
+
+        let mut other = scalar_hash();
+        other.update(&[Fq::from(2), Fq::from(1)]);
+        other.absorb_point(&point);
+        assert_ne!(other.squeeze(), digest);
+    }
+}
@@ -0,0 +1,283 @@
+//! Native side of a Poseidon-based shuffle argument.
+//!
+//! Every element is committed together with its own blinding factor as
+//! `c = H(x, r)`. The challenge `gamma` is derived by hashing all input and
+//! output commitments, and the grand products `prod_i (gamma - x_i)` and
+//! `prod_j (gamma - y_j)` agree exactly when the outputs are a permutation of
+//! the inputs (except with probability `n / |F|`). A circuit verifying the
+//! shuffle opens the commitments and recomputes both products.
+use std::marker::PhantomData;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait};
+
+/// Public part of a shuffle: per-element commitments of both sides
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShuffleCommitment<F: PrimeField> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field_vec"))]
+    pub input_commitments: Vec<F>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field_vec"))]
+    pub output_commitments: Vec<F>,
+}
+
+/// Values a shuffle-verifying circuit needs besides the openings
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShuffleWitness<F: PrimeField> {
+    /// `outputs[j] = inputs[permutation[j]]`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field_vec"))]
+    pub outputs: Vec<F>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field"))]
+    pub challenge: F,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field"))]
+    pub input_product: F,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::field"))]
+    pub output_product: F,
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshSerialize for ShuffleCommitment<F> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        crate::encoding::borsh::write_fields(&self.input_commitments, writer)?;
+        crate::encoding::borsh::write_fields(&self.output_commitments, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshDeserialize for ShuffleCommitment<F> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(Self {
+            input_commitments: crate::encoding::borsh::read_fields(reader)?,
+            output_commitments: crate::encoding::borsh::read_fields(reader)?,
+        })
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshSerialize for ShuffleWitness<F> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        crate::encoding::borsh::write_fields(&self.outputs, writer)?;
+        crate::encoding::borsh::write_field(&self.challenge, writer)?;
+        crate::encoding::borsh::write_field(&self.input_product, writer)?;
+        crate::encoding::borsh::write_field(&self.output_product, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<F: PrimeField> borsh::BorshDeserialize for ShuffleWitness<F> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(Self {
+            outputs: crate::encoding::borsh::read_fields(reader)?,
+            challenge: crate::encoding::borsh::read_field(reader)?,
+            input_product: crate::encoding::borsh::read_field(reader)?,
+            output_product: crate::encoding::borsh::read_field(reader)?,
+        })
+    }
+}
+
+pub struct Shuffle<C, F, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    spec: PrecomputedSpec<F, T, RATE>,
+    _marker: PhantomData<C>,
+}
+
+impl<C, F, const T: usize, const RATE: usize> Shuffle<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        Self {
+            spec,
+            _marker: PhantomData,
+        }
+    }
+
+    fn hash(&self, elements: &[F]) -> F {
+        let mut hasher = PoseidonHash::<C, F, T, RATE>::new(self.spec.clone());
+        hasher.update(elements);
+        hasher.squeeze()
+    }
+
+    /// Commits to a single element: `H(value, blinding)`
+    pub fn commit_element(&self, value: F, blinding: F) -> F {
+        self.hash(&[value, blinding])
+    }
+
+    /// Derives `gamma` from all input and output commitments
+    pub fn challenge(&self, commitment: &ShuffleCommitment<F>) -> F {
+        let elements = commitment
+            .input_commitments
+            .iter()
+            .chain(commitment.output_commitments.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        self.hash(&elements)
+    }
+
+    /// Shuffles `inputs` by `permutation` and commits to both sides.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `permutation` is not a permutation of `0..inputs.len()` or if
+    /// the number of blinding factors does not match the number of inputs.
+    pub fn commit(
+        &self,
+        inputs: &[F],
+        permutation: &[usize],
+        input_blindings: &[F],
+        output_blindings: &[F],
+    ) -> (ShuffleCommitment<F>, ShuffleWitness<F>) {
+        assert!(is_permutation(permutation, inputs.len()));
+        assert_eq!(input_blindings.len(), inputs.len());
+        assert_eq!(output_blindings.len(), inputs.len());
+
+        let outputs = permutation.iter().map(|&i| inputs[i]).collect::<Vec<_>>();
+        let commitment = ShuffleCommitment {
+            input_commitments: self.commit_all(inputs, input_blindings),
+            output_commitments: self.commit_all(&outputs, output_blindings),
+        };
+
+        let challenge = self.challenge(&commitment);
+        let witness = ShuffleWitness {
+            input_product: grand_product(inputs, challenge),
+            output_product: grand_product(&outputs, challenge),
+            outputs,
+            challenge,
+        };
+        (commitment, witness)
+    }
+
+    /// Checks the openings of `commitment` and the grand-product equality,
+    /// i.e. everything a shuffle-verifying circuit enforces
+    pub fn verify(
+        &self,
+        commitment: &ShuffleCommitment<F>,
+        inputs: &[F],
+        input_blindings: &[F],
+        outputs: &[F],
+        output_blindings: &[F],
+    ) -> bool {
+        if inputs.len() != outputs.len()
+            || input_blindings.len() != inputs.len()
+            || output_blindings.len() != outputs.len()
+        {
+            return false;
+        }
+        if commitment.input_commitments != self.commit_all(inputs, input_blindings)
+            || commitment.output_commitments != self.commit_all(outputs, output_blindings)
+        {
+            return false;
+        }
+
+        let challenge = self.challenge(commitment);
+        grand_product(inputs, challenge) == grand_product(outputs, challenge)
+    }
+
+    fn commit_all(&self, values: &[F], blindings: &[F]) -> Vec<F> {
+        values
+            .iter()
+            .zip(blindings.iter())
+            .map(|(value, blinding)| self.commit_element(*value, *blinding))
+            .collect()
+    }
+}
+
+/// `prod_i (gamma - values[i])`
+pub fn grand_product<F: PrimeField>(values: &[F], gamma: F) -> F {
+    values
+        .iter()
+        .fold(F::ONE, |acc, value| acc * (gamma - *value))
+}
+
+fn is_permutation(permutation: &[usize], len: usize) -> bool {
+    let mut seen = vec![false; len];
+    permutation.len() == len
+        && permutation
+            .iter()
+            .all(|&i| i < len && !std::mem::replace(&mut seen[i], true))
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+
+    const T: usize = 4;
+    const RATE: usize = 3;
+    const R_F: usize = 8;
+    const R_P: usize = 56;
+
+    fn setup() -> (Shuffle<G1Affine, Fr, T, RATE>, Vec<Fr>, Vec<Fr>, Vec<Fr>) {
+        let shuffle = Shuffle::new(PrecomputedSpec::new(R_F, R_P));
+        let inputs = (0..5).map(|i| Fr::from(i + 10)).collect();
+        let input_blindings = (0..5).map(|i| Fr::from(i + 100)).collect();
+        let output_blindings = (0..5).map(|i| Fr::from(i + 200)).collect();
+        (shuffle, inputs, input_blindings, output_blindings)
+    }
+
+    #[test]
+    fn test_shuffle_verifies() {
+        let (shuffle, inputs, input_blindings, output_blindings) = setup();
+        let (commitment, witness) = shuffle.commit(
+            &inputs,
+            &[3, 0, 4, 1, 2],
+            &input_blindings,
+            &output_blindings,
+        );
+        assert_eq!(witness.input_product, witness.output_product);
+        assert!(shuffle.verify(
+            &commitment,
+            &inputs,
+            &input_blindings,
+            &witness.outputs,
+            &output_blindings
+        ));
+    }
+
+    #[test]
+    fn test_shuffle_rejects_tampered_output() {
+        let (shuffle, inputs, input_blindings, output_blindings) = setup();
+        let (_, witness) = shuffle.commit(
+            &inputs,
+            &[3, 0, 4, 1, 2],
+            &input_blindings,
+            &output_blindings,
+        );
+
+        let mut outputs = witness.outputs;
+        outputs[0] = Fr::from(11);
+        let commitment = ShuffleCommitment {
+            input_commitments: shuffle.commit_all(&inputs, &input_blindings),
+            output_commitments: shuffle.commit_all(&outputs, &output_blindings),
+        };
+        assert!(!shuffle.verify(
+            &commitment,
+            &inputs,
+            &input_blindings,
+            &outputs,
+            &output_blindings
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shuffle_rejects_non_permutation() {
+        let (shuffle, inputs, input_blindings, output_blindings) = setup();
+        shuffle.commit(
+            &inputs,
+            &[0, 0, 1, 2, 3],
+            &input_blindings,
+            &output_blindings,
+        );
+    }
+}
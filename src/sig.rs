@@ -0,0 +1,305 @@
+//! Schnorr signatures over a curve `C` with a Poseidon challenge.
+//!
+//! A signature of `msg` under the public key `pk = sk * G` is a point `R`
+//! and a scalar `s` with `s * G = R + e * pk`, where the challenge `e` is the
+//! digest of a [`PoseidonHash`] over the scalar field of `C` that absorbed
+//! `R` and `pk` with [`PoseidonHash::absorb_point`], then `msg`.
+//!
+//! The challenge is the only hash of verification. [`SchnorrChallengeChip`]
+//! assigns it in a circuit over the scalar field from the limbs of `R` and
+//! `pk` and the message. It does not verify a signature: the crate has no
+//! curve chip, so the group equation `s * G = R + e * pk` is left to a
+//! non-native curve chip of the caller that holds these limbs.
+use std::marker::PhantomData;
+
+use halo2_proofs::{arithmetic::CurveAffine, plonk::Error};
+use halo2curves::{
+    group::{
+        ff::{FromUniformBytes, PrimeField},
+        Curve,
+    },
+    Coordinates,
+};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    encoding,
+    main_gate::{AssignedValue, MainGateConfig, RegionCtx},
+    poseidon_circuit::PoseidonChip,
+    poseidon_hash::PoseidonHash,
+    precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+    transcript::BITS,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature<C: CurveAffine> {
+    pub r: C,
+    pub s: C::Scalar,
+}
+
+/// The public key of `secret`
+pub fn public_key<C: CurveAffine>(secret: &C::Scalar) -> C {
+    (C::generator() * secret).to_affine()
+}
+
+/// The elements [`PoseidonHash::absorb_point`] buffers for `point` with the
+/// default limb width, for circuits to witness
+pub fn point_elements<C: CurveAffine<ScalarExt = F>, F: PrimeField>(point: &C) -> Vec<F> {
+    match Option::<Coordinates<C>>::from(point.coordinates()) {
+        Some(coordinates) => {
+            let mut elements = encoding::to_limbs(coordinates.x(), BITS);
+            elements.extend(encoding::to_limbs::<_, F>(coordinates.y(), BITS));
+            elements.push(F::ZERO);
+            elements
+        }
+        None => {
+            let limbs = (C::Base::NUM_BITS as usize).div_ceil(BITS);
+            let mut elements = vec![F::ZERO; 2 * limbs];
+            elements.push(F::ONE);
+            elements
+        }
+    }
+}
+
+/// The challenge of the nonce point `r` and `public_key` for `msg`
+pub fn challenge<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    r: &C,
+    public_key: &C,
+    msg: &[F],
+) -> F
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut hasher = PoseidonHash::<C, F, T, RATE>::new(spec.clone());
+    hasher.absorb_point(r);
+    hasher.absorb_point(public_key);
+    hasher.update(msg);
+    hasher.squeeze()
+}
+
+/// The signature of `msg` under `secret`, with a nonce drawn from `rng`
+pub fn sign<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    secret: &F,
+    msg: &[F],
+    rng: impl RngCore + CryptoRng,
+) -> Signature<C>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let nonce = F::random(rng);
+    let r = public_key::<C>(&nonce);
+    let e = challenge(spec, &r, &public_key(secret), msg);
+    Signature {
+        r,
+        s: nonce + e * secret,
+    }
+}
+
+/// Whether `signature` signs `msg` under `public_key`. The identity is not a
+/// valid public key.
+pub fn verify<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    public_key: &C,
+    msg: &[F],
+    signature: &Signature<C>,
+) -> bool
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    if bool::from(public_key.is_identity()) {
+        return false;
+    }
+    let e = challenge(spec, &signature.r, public_key, msg);
+    (C::generator() * signature.s - *public_key * e).to_affine() == signature.r
+}
+
+/// [`challenge`] over the main gate, the hashing part of a signature
+/// verification gadget without the group equation
+pub struct SchnorrChallengeChip<
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField,
+    const T: usize,
+    const RATE: usize,
+> {
+    config: MainGateConfig<T>,
+    spec: PrecomputedSpec<F, T, RATE>,
+    _marker: PhantomData<C>,
+}
+
+impl<C, F, const T: usize, const RATE: usize> SchnorrChallengeChip<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(config: MainGateConfig<T>, spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        Self {
+            config,
+            spec,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The challenge of the assigned [`point_elements`] of the nonce point
+    /// `r` and of `public_key` for the assigned `msg`, equal to the one of
+    /// [`challenge`]. The caller constrains the elements to the limbs of its
+    /// points and checks `s * G = R + e * pk`.
+    ///
+    /// # Panics
+    ///
+    /// If `r` or `public_key` do not have as many elements as
+    /// [`point_elements`]
+    pub fn challenge(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        r: &[AssignedValue<F>],
+        public_key: &[AssignedValue<F>],
+        msg: &[AssignedValue<F>],
+    ) -> Result<AssignedValue<F>, Error> {
+        let elements = 2 * (C::Base::NUM_BITS as usize).div_ceil(BITS) + 1;
+        assert_eq!(r.len(), elements, "nonce point of another width");
+        assert_eq!(public_key.len(), elements, "public key of another width");
+        let mut chip = PoseidonChip::new(self.config.clone(), self.spec.clone());
+        chip.update_assigned(r);
+        chip.update_assigned(public_key);
+        chip.update_assigned(msg);
+        chip.squeeze(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::{ff::Field, prime::PrimeCurveAffine},
+    };
+
+    use super::*;
+    use crate::{
+        rng::PoseidonRng,
+        test_fixture::{spec, FixtureConfig, RATE, T},
+    };
+
+    fn rng(seed: &[u8]) -> PoseidonRng<G1Affine, Fr, T, RATE> {
+        let mut hasher = PoseidonHash::new(spec());
+        hasher.update_bytes(seed);
+        PoseidonRng::new(hasher)
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let secret = Fr::from(1234);
+        let pk = public_key::<G1Affine>(&secret);
+        let msg = [Fr::from(1), Fr::from(2)];
+        let signature = sign::<G1Affine, _, T, RATE>(&spec(), &secret, &msg, rng(b"nonce"));
+        assert!(verify(&spec(), &pk, &msg, &signature));
+
+        assert!(!verify(&spec(), &pk, &msg[..1], &signature));
+        assert!(!verify(
+            &spec(),
+            &public_key(&Fr::from(1235)),
+            &msg,
+            &signature
+        ));
+        let forged = Signature {
+            s: signature.s + Fr::ONE,
+            ..signature
+        };
+        assert!(!verify(&spec(), &pk, &msg, &forged));
+        // the identity key accepts `s * G = R`, so it is rejected outright
+        let trivial = Signature {
+            r: public_key(&Fr::from(5)),
+            s: Fr::from(5),
+        };
+        assert!(!verify(&spec(), &G1Affine::identity(), &msg, &trivial));
+    }
+
+    #[test]
+    fn test_point_elements() {
+        let points = [G1Affine::generator(), G1Affine::identity()];
+        for point in points {
+            let mut hasher = PoseidonHash::<G1Affine, Fr, T, RATE>::new(spec());
+            hasher.absorb_point(&point);
+            let mut expected = PoseidonHash::<G1Affine, Fr, T, RATE>::new(spec());
+            expected.update(&point_elements(&point));
+            assert_eq!(hasher.squeeze(), expected.squeeze());
+        }
+    }
+
+    struct SchnorrCircuit {
+        r: G1Affine,
+        public_key: G1Affine,
+        msg: Vec<Fr>,
+    }
+
+    impl Circuit<Fr> for SchnorrCircuit {
+        type Config = FixtureConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                r: G1Affine::identity(),
+                public_key: G1Affine::identity(),
+                msg: vec![Fr::ZERO; self.msg.len()],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FixtureConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip =
+                SchnorrChallengeChip::<G1Affine, _, T, RATE>::new(config.main_gate.clone(), spec());
+            let challenge = layouter.assign_region(
+                || "schnorr",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let r = config.witness(ctx, point_elements(&self.r))?;
+                    let public_key = config.witness(ctx, point_elements(&self.public_key))?;
+                    let msg = config.witness(ctx, self.msg.iter().copied())?;
+                    chip.challenge(ctx, &r, &public_key, &msg)
+                },
+            )?;
+            layouter.constrain_instance(challenge.cell(), config.instance, 0)
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        let secret = Fr::from(1234);
+        let msg = vec![Fr::from(1), Fr::from(2), Fr::from(3)];
+        let signature = sign::<G1Affine, _, T, RATE>(&spec(), &secret, &msg, rng(b"nonce"));
+        let public_key = public_key(&secret);
+        let expected = challenge(&spec(), &signature.r, &public_key, &msg);
+        let circuit = SchnorrCircuit {
+            r: signature.r,
+            public_key,
+            msg,
+        };
+        let prover = MockProver::run(12, &circuit, vec![vec![expected]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the challenge is bound to the public key
+        let circuit = SchnorrCircuit {
+            public_key: signature.r,
+            ..circuit
+        };
+        let prover = MockProver::run(12, &circuit, vec![vec![expected]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
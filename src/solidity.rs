@@ -0,0 +1,245 @@
+//! Solidity code generation for recomputing [`PoseidonHash`] digests on-chain.
+//!
+//! [`generate_library`] emits a library with the spec's optimized round
+//! constants unrolled into a straight-line permutation and a `hash` function
+//! reproducing the native sponge: the same capacity initialization, padding
+//! and absorption schedule. The state lives in local variables, so the EVM
+//! stack limits the width to `T <= 7`. Bytecode size grows with the round count
+//! and `T^2`.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+use std::fmt::Write;
+
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    encoding::to_hex, poseidon_hash::initial_state, precomputed::PrecomputedSpec,
+    spec::PoseidonSpec,
+};
+
+struct Emitter<'a, F: PrimeField> {
+    out: &'a mut String,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<'a, F: PrimeField> Emitter<'a, F> {
+    fn line(&mut self, line: &str) {
+        writeln!(self.out, "        {}", line).unwrap();
+    }
+
+    fn sum(terms: Vec<String>) -> String {
+        terms
+            .into_iter()
+            .reduce(|acc, term| format!("addmod({}, {}, P)", acc, term))
+            .unwrap()
+    }
+
+    fn add_constants(&mut self, constants: &[F]) {
+        for (i, constant) in constants.iter().enumerate() {
+            if !bool::from(constant.is_zero()) {
+                self.line(&format!("s{i} = addmod(s{i}, {}, P);", to_hex(constant)));
+            }
+        }
+    }
+
+    fn sbox_full(&mut self, constants: &[F]) {
+        for i in 0..constants.len() {
+            self.line(&format!("s{i} = pow5(s{i});"));
+        }
+        self.add_constants(constants);
+    }
+
+    fn sbox_part(&mut self, constant: &F) {
+        self.line(&format!("s0 = addmod(pow5(s0), {}, P);", to_hex(constant)));
+    }
+
+    fn mds<const T: usize>(&mut self, mds: &[[F; T]; T]) {
+        for (i, row) in mds.iter().enumerate() {
+            let terms = row
+                .iter()
+                .enumerate()
+                .map(|(j, m)| format!("mulmod(s{j}, {}, P)", to_hex(m)))
+                .collect();
+            self.line(&format!("t{i} = {};", Self::sum(terms)));
+        }
+        self.assign_temporaries(T);
+    }
+
+    fn sparse_mds<const T: usize, const RATE: usize>(
+        &mut self,
+        (row, col_hat): (&[F; T], &[F; RATE]),
+    ) {
+        let terms = row
+            .iter()
+            .enumerate()
+            .map(|(j, m)| format!("mulmod(s{j}, {}, P)", to_hex(m)))
+            .collect();
+        self.line(&format!("t0 = {};", Self::sum(terms)));
+        for (i, coeff) in col_hat.iter().enumerate() {
+            let i = i + 1;
+            self.line(&format!(
+                "t{i} = addmod(mulmod(s0, {}, P), s{i}, P);",
+                to_hex(coeff)
+            ));
+        }
+        self.assign_temporaries(T);
+    }
+
+    fn assign_temporaries(&mut self, width: usize) {
+        let assignments = (0..width)
+            .map(|i| format!("s{i} = t{i};"))
+            .collect::<Vec<_>>();
+        self.line(&assignments.join(" "));
+    }
+}
+
+/// Generates a Solidity library named `name` whose
+/// `hash(uint256[] memory inputs)` equals the native
+/// [`PoseidonHash`](crate::poseidon_hash::PoseidonHash) digest of `inputs`
+/// under `spec`
+pub fn generate_library<F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    name: &str,
+) -> String
+where
+    F: PrimeField + FromUniformBytes<64>,
+{
+    assert!(RATE == T - 1);
+    let mut out = String::new();
+    let initial_state = initial_state::<F, T>();
+
+    writeln!(out, "// SPDX-License-Identifier: MIT").unwrap();
+    writeln!(
+        out,
+        "// Generated by poseidon_circuit for T = {}, R_F = {}, R_P = {}; do not edit.",
+        T,
+        spec.full_rounds(),
+        spec.partial_rounds()
+    )
+    .unwrap();
+    writeln!(out, "pragma solidity ^0.8.0;\n").unwrap();
+    writeln!(out, "library {} {{", name).unwrap();
+    writeln!(out, "    uint256 internal constant P = {};", F::MODULUS).unwrap();
+    writeln!(out, "    uint256 internal constant RATE = {};\n", RATE).unwrap();
+
+    out.push_str(
+        "    function pow5(uint256 x) private pure returns (uint256) {
+        uint256 x2 = mulmod(x, x, P);
+        return mulmod(mulmod(x2, x2, P), x, P);
+    }
+
+",
+    );
+
+    writeln!(
+        out,
+        "    function hash(uint256[] memory inputs) internal pure returns (uint256) {{"
+    )
+    .unwrap();
+    writeln!(out, "        uint256[{}] memory state;", T).unwrap();
+    for (i, word) in initial_state.iter().enumerate() {
+        if !bool::from(word.is_zero()) {
+            writeln!(out, "        state[{}] = {};", i, to_hex(word)).unwrap();
+        }
+    }
+    out.push_str(
+        "        uint256 n = inputs.length;
+        // Full chunks, then a final chunk shorter than RATE padded with a one
+        for (uint256 k = 0; k <= n / RATE; k++) {
+            uint256 len = n - k * RATE;
+            if (len > RATE) len = RATE;
+            for (uint256 j = 0; j < len; j++) {
+                uint256 input = inputs[k * RATE + j];
+                require(input < P, \"input not in field\");
+                state[j + 1] = addmod(state[j + 1], input, P);
+            }
+            if (len < RATE) state[len + 1] = addmod(state[len + 1], 1, P);
+            state = permute(state);
+        }
+        return state[1];
+    }
+
+",
+    );
+
+    writeln!(
+        out,
+        "    function permute(uint256[{t}] memory state) internal pure returns (uint256[{t}] memory) {{",
+        t = T
+    )
+    .unwrap();
+    for i in 0..T {
+        writeln!(out, "        uint256 s{i} = state[{i}];").unwrap();
+    }
+    let temporaries = (0..T).map(|i| format!("t{i}")).collect::<Vec<_>>();
+    writeln!(
+        out,
+        "        uint256 {};",
+        temporaries.join(";\n        uint256 ")
+    )
+    .unwrap();
+
+    let r_f = spec.full_rounds() / 2;
+    let mds = spec.mds();
+    let pre_sparse_mds = spec.pre_sparse_mds();
+    let mut emitter = Emitter::<F> {
+        out: &mut out,
+        _marker: std::marker::PhantomData,
+    };
+
+    // First half of the full rounds
+    let constants = spec.start_constants();
+    emitter.add_constants(&constants[0]);
+    for constants in constants.iter().skip(1).take(r_f - 1) {
+        emitter.sbox_full(constants);
+        emitter.mds(&mds);
+    }
+    emitter.sbox_full(constants.last().unwrap());
+    emitter.mds(&pre_sparse_mds);
+
+    // Partial rounds
+    for (round, constant) in spec.partial_constants().iter().enumerate() {
+        emitter.sbox_part(constant);
+        emitter.sparse_mds(spec.sparse_mds(round));
+    }
+
+    // Second half of the full rounds
+    for constants in spec.end_constants().iter() {
+        emitter.sbox_full(constants);
+        emitter.mds(&mds);
+    }
+    emitter.sbox_full(&[F::ZERO; T]);
+    emitter.mds(&mds);
+
+    for i in 0..T {
+        writeln!(out, "        state[{i}] = s{i};").unwrap();
+    }
+    writeln!(out, "        return state;\n    }}\n}}").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr;
+
+    use super::*;
+
+    #[test]
+    fn test_generate_library() {
+        const T: usize = 4;
+        const RATE: usize = 3;
+        const R_F: usize = 8;
+        const R_P: usize = 56;
+        let spec = PrecomputedSpec::<Fr, T, RATE>::new(R_F, R_P);
+        let source = generate_library(&spec, "PoseidonT4");
+
+        assert!(source.contains("library PoseidonT4 {"));
+        assert!(source.contains(&format!("uint256 internal constant P = {};", Fr::MODULUS)));
+        assert!(source.contains(
+            "state[0] = 0x0000000000000000000000000000000000000000000000010000000000000000;"
+        ));
+        // one S-box per state element in every full round, one per partial round
+        assert_eq!(source.matches("pow5(s").count(), R_F * T + R_P);
+        assert_eq!(source.matches('{').count(), source.matches('}').count());
+    }
+}
@@ -0,0 +1,58 @@
+//! Internal view of the permutation parameters.
+//!
+//! Code that walks the permutation reads the constants and matrices through
+//! [`PoseidonSpec`] rather than the fields of
+//! [`PrecomputedSpec`](crate::precomputed::PrecomputedSpec), which implements
+//! it, so that round structure and storage stay separate.
+use halo2curves::group::ff::PrimeField;
+
+/// Round counts, optimized round constants and MDS matrices of a permutation
+/// with `T` state elements, `RATE = T - 1` of them absorbing
+pub(crate) trait PoseidonSpec<F: PrimeField, const T: usize, const RATE: usize> {
+    /// Total number of full rounds
+    fn full_rounds(&self) -> usize;
+
+    /// Number of partial rounds
+    fn partial_rounds(&self) -> usize;
+
+    /// Constants of the first half of the full rounds; the first entry is
+    /// added before any S-box, so there are `full_rounds() / 2 + 1` of them
+    fn start_constants(&self) -> &[[F; T]];
+
+    /// One constant per partial round, added to the first state element
+    fn partial_constants(&self) -> &[F];
+
+    /// Constants of the second half of the full rounds, except the last one,
+    /// which has none
+    fn end_constants(&self) -> &[[F; T]];
+
+    /// MDS matrix of the full rounds
+    fn mds(&self) -> [[F; T]; T];
+
+    /// Matrix replacing the MDS of the last first-half full round
+    fn pre_sparse_mds(&self) -> [[F; T]; T];
+
+    /// First row and remaining first column of the sparse matrix applied after
+    /// partial round `round`
+    fn sparse_mds(&self, round: usize) -> (&[F; T], &[F; RATE]);
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::precomputed::PrecomputedSpec;
+
+    #[test]
+    fn test_round_structure() {
+        let spec = PrecomputedSpec::<Fr, 4, 3>::new(8, 56);
+        assert_eq!(spec.full_rounds(), 8);
+        assert_eq!(spec.partial_rounds(), 56);
+        assert_eq!(spec.start_constants().len(), 5);
+        assert_eq!(spec.partial_constants().len(), 56);
+        assert_eq!(spec.end_constants().len(), 3);
+        // the factoring only changes the rows below the first
+        assert_eq!(spec.pre_sparse_mds()[0], spec.mds()[0]);
+    }
+}
@@ -0,0 +1,97 @@
+//! `std::io` adapters for the byte-absorbing mode of [`PoseidonHash`], so
+//! streams can be hashed with `io::copy`.
+use std::io::{self, Read, Write};
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{poseidon_hash::PoseidonHash, ro_types::ROTrait};
+
+/// Every write goes to [`PoseidonHash::update_bytes`], which permutes as
+/// blocks fill up, so memory stays bounded by the width of the sponge.
+/// `flush` does not end the byte string; the next squeeze does
+impl<C, F, const T: usize, const RATE: usize> Write for PoseidonHash<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Passes reads through from `inner` while hashing every byte read
+pub struct HashingReader<R, C, F, const T: usize, const RATE: usize>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    inner: R,
+    hasher: PoseidonHash<C, F, T, RATE>,
+}
+
+impl<R, C, F, const T: usize, const RATE: usize> HashingReader<R, C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(inner: R, hasher: PoseidonHash<C, F, T, RATE>) -> Self {
+        Self { inner, hasher }
+    }
+
+    /// Returns the inner reader and the digest of everything read so far
+    pub fn finalize(mut self) -> (R, F) {
+        let digest = self.hasher.squeeze();
+        (self.inner, digest)
+    }
+}
+
+impl<R: Read, C, F, const T: usize, const RATE: usize> Read for HashingReader<R, C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update_bytes(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::precomputed::PrecomputedSpec;
+
+    type PH = PoseidonHash<G1Affine, Fr, 4, 3>;
+
+    fn hasher() -> PH {
+        PH::new(PrecomputedSpec::new(8, 56))
+    }
+
+    #[test]
+    fn test_io_copy() {
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        let mut expected = hasher();
+        expected.update_bytes(&data);
+        let expected = expected.squeeze();
+
+        let mut writer = hasher();
+        io::copy(&mut &data[..], &mut writer).unwrap();
+        assert_eq!(writer.squeeze(), expected);
+
+        let mut reader = HashingReader::new(&data[..], hasher());
+        let mut sink = Vec::new();
+        io::copy(&mut reader, &mut sink).unwrap();
+        let (_, digest) = reader.finalize();
+        assert_eq!(sink, data);
+        assert_eq!(digest, expected);
+    }
+}
@@ -3,11 +3,11 @@ use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner},
     plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
 };
-use poseidon::Spec;
 
 use crate::{
     main_gate::{MainGate, MainGateConfig, RegionCtx},
     poseidon_circuit::PoseidonChip,
+    precomputed::PrecomputedSpec,
 };
 
 const T: usize = 4;
@@ -53,7 +53,7 @@ impl<F: PrimeField + FromUniformBytes<64>> Circuit<F> for TestCircuit<F> {
         config: Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        let spec = Spec::<F, T, RATE>::new(R_F, R_P);
+        let spec = PrecomputedSpec::<F, T, RATE>::new(R_F, R_P);
         let mut pchip = PoseidonChip::new(config.pconfig, spec);
         pchip.update(self.inputs.clone());
         let output = layouter.assign_region(
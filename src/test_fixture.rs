@@ -0,0 +1,58 @@
+//! The configuration, spec and witness helper shared by the test circuits of
+//! the chips built on [`MainGate`].
+use ff::PrimeField;
+use halo2_proofs::{
+    circuit::Value,
+    plonk::{Column, ConstraintSystem, Error, Instance},
+};
+
+use crate::{
+    main_gate::{AssignedValue, MainGate, MainGateConfig, RegionCtx},
+    precomputed::PrecomputedSpec,
+};
+
+pub(crate) const T: usize = 3;
+pub(crate) const RATE: usize = 2;
+
+/// The spec with 8 full and 56 partial rounds the test circuits hash with
+pub(crate) fn spec<F: PrimeField>() -> PrecomputedSpec<F, T, RATE> {
+    PrecomputedSpec::new(8, 56)
+}
+
+/// A main gate and the instance column the circuit's outputs are exposed in
+#[derive(Clone, Debug)]
+pub(crate) struct FixtureConfig {
+    pub(crate) main_gate: MainGateConfig<T>,
+    pub(crate) instance: Column<Instance>,
+}
+
+impl FixtureConfig {
+    pub(crate) fn configure<F: PrimeField>(meta: &mut ConstraintSystem<F>) -> Self {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let mut adv_cols = [(); T + 2].map(|_| meta.advice_column()).into_iter();
+        let mut fix_cols = [(); 2 * T + 4].map(|_| meta.fixed_column()).into_iter();
+        let main_gate = MainGate::configure(meta, &mut adv_cols, &mut fix_cols);
+        Self {
+            main_gate,
+            instance,
+        }
+    }
+
+    /// Assigns `values` to the first state column, one row each
+    pub(crate) fn witness<F: PrimeField>(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        values: impl IntoIterator<Item = F>,
+    ) -> Result<Vec<AssignedValue<F>>, Error> {
+        values
+            .into_iter()
+            .map(|value| {
+                let cell =
+                    ctx.assign_advice(|| "witness", self.main_gate.state[0], Value::known(value));
+                ctx.next();
+                cell
+            })
+            .collect()
+    }
+}
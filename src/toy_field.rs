@@ -0,0 +1,305 @@
+//! A 17-bit prime field for exhaustive structural tests.
+//!
+//! Over `p = 2^16 + 1` every field element can be enumerated, so properties
+//! that the KATs and proptests only sample, such as the S-box being a
+//! permutation of the field or the optimized permutation agreeing with the
+//! reference one, are checked for every input. `x^5` is a bijection since
+//! `gcd(5, p - 1) = 1`, and the Grain-generated Cauchy matrices are valid for
+//! the widths used below.
+use std::{
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use ff::{Field, FromUniformBytes, PrimeField};
+use rand_core::RngCore;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+const P: u64 = 65537;
+
+/// Element of `GF(2^16 + 1)`, always reduced
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Toy(u64);
+
+impl Toy {
+    const fn new(value: u64) -> Self {
+        Self(value % P)
+    }
+
+    fn power(&self, mut exp: u64) -> Self {
+        let (mut base, mut acc) = (*self, Self::ONE);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                acc *= base;
+            }
+            base = base.square();
+            exp >>= 1;
+        }
+        acc
+    }
+
+    /// Every element of the field
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        (0..P).map(Self)
+    }
+}
+
+impl From<u64> for Toy {
+    fn from(value: u64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Neg for Toy {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(P - self.0)
+    }
+}
+
+/// `$trait` and `$assign` for values and references from `$op` on the
+/// reduced representatives
+macro_rules! impl_op {
+    ($trait:ident, $method:ident, $assign:ident, $assign_method:ident, $op:expr) => {
+        impl $trait for Toy {
+            type Output = Self;
+            fn $method(self, rhs: Self) -> Self {
+                Self::new($op(self.0, rhs.0))
+            }
+        }
+        impl<'a> $trait<&'a Toy> for Toy {
+            type Output = Self;
+            fn $method(self, rhs: &'a Self) -> Self {
+                self.$method(*rhs)
+            }
+        }
+        impl $assign for Toy {
+            fn $assign_method(&mut self, rhs: Self) {
+                *self = self.$method(rhs);
+            }
+        }
+        impl<'a> $assign<&'a Toy> for Toy {
+            fn $assign_method(&mut self, rhs: &'a Self) {
+                *self = self.$method(*rhs);
+            }
+        }
+    };
+}
+
+impl_op!(Add, add, AddAssign, add_assign, |a, b| a + b);
+impl_op!(Sub, sub, SubAssign, sub_assign, |a, b| a + P - b);
+impl_op!(Mul, mul, MulAssign, mul_assign, |a, b| a * b);
+
+impl Sum for Toy {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, v| acc + v)
+    }
+}
+
+impl<'a> Sum<&'a Toy> for Toy {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl Product for Toy {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |acc, v| acc * v)
+    }
+}
+
+impl<'a> Product<&'a Toy> for Toy {
+    fn product<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().product()
+    }
+}
+
+impl ConditionallySelectable for Toy {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self(u64::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for Toy {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl Field for Toy {
+    const ZERO: Self = Self(0);
+    const ONE: Self = Self(1);
+
+    fn random(mut rng: impl RngCore) -> Self {
+        Self::new(rng.next_u64())
+    }
+
+    fn square(&self) -> Self {
+        *self * self
+    }
+
+    fn double(&self) -> Self {
+        *self + self
+    }
+
+    fn invert(&self) -> CtOption<Self> {
+        CtOption::new(self.power(P - 2), Choice::from((self.0 != 0) as u8))
+    }
+
+    /// By search, the field being small
+    fn sqrt_ratio(num: &Self, div: &Self) -> (Choice, Self) {
+        if div.0 == 0 {
+            return (Choice::from((num.0 == 0) as u8), Self::ZERO);
+        }
+        let ratio = *num * div.invert().unwrap();
+        let root = |v: Self| Self::all().find(|x| x.square() == v);
+        match root(ratio) {
+            Some(root) => (Choice::from(1), root),
+            None => (Choice::from(0), root(Self::ROOT_OF_UNITY * ratio).unwrap()),
+        }
+    }
+}
+
+impl PrimeField for Toy {
+    type Repr = [u8; 4];
+
+    fn from_repr(repr: Self::Repr) -> CtOption<Self> {
+        let value = u32::from_le_bytes(repr) as u64;
+        CtOption::new(Self(value), Choice::from((value < P) as u8))
+    }
+
+    fn to_repr(&self) -> Self::Repr {
+        (self.0 as u32).to_le_bytes()
+    }
+
+    fn is_odd(&self) -> Choice {
+        Choice::from((self.0 & 1) as u8)
+    }
+
+    const MODULUS: &'static str = "0x10001";
+    const NUM_BITS: u32 = 17;
+    const CAPACITY: u32 = 16;
+    const TWO_INV: Self = Self(32769);
+    // 3 generates the multiplicative group of order 2^16, so it is also the
+    // root of unity of order 2^S, and DELTA = 3^(2^S) = 1
+    const MULTIPLICATIVE_GENERATOR: Self = Self(3);
+    const S: u32 = 16;
+    const ROOT_OF_UNITY: Self = Self(3);
+    const ROOT_OF_UNITY_INV: Self = Self(21846);
+    const DELTA: Self = Self(1);
+}
+
+impl FromUniformBytes<64> for Toy {
+    fn from_uniform_bytes(bytes: &[u8; 64]) -> Self {
+        bytes
+            .iter()
+            .rev()
+            .fold(Self::ZERO, |acc, byte| acc * Self(256) + Self(*byte as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        audit::determinant, poseidon_hash::State, precomputed::PrecomputedSpec,
+        reference::Reference, spec::PoseidonSpec,
+    };
+
+    #[test]
+    fn test_field_constants() {
+        assert_eq!(Toy::TWO_INV.double(), Toy::ONE);
+        assert_eq!(Toy::ROOT_OF_UNITY * Toy::ROOT_OF_UNITY_INV, Toy::ONE);
+        assert_eq!(Toy::ROOT_OF_UNITY.power(1 << 15), -Toy::ONE);
+        assert_eq!(Toy::from_repr(Toy(P - 1).to_repr()).unwrap(), -Toy::ONE);
+        assert!(bool::from(Toy::from_repr(Toy::ZERO.to_repr()).is_some()));
+        assert!(bool::from(
+            Toy::from_repr((P as u32).to_le_bytes()).is_none()
+        ));
+        for x in Toy::all().skip(1) {
+            assert_eq!(x * x.invert().unwrap(), Toy::ONE);
+        }
+    }
+
+    #[test]
+    fn test_sbox_is_a_permutation() {
+        let images = Toy::all()
+            .map(|x| x.square().square() * x)
+            .collect::<HashSet<_>>();
+        assert_eq!(images.len() as u64, P);
+    }
+
+    /// Every square submatrix of the MDS matrix is invertible, the MDS
+    /// property, and so are the matrices of the sparse factorization
+    fn check_matrices<const T: usize, const RATE: usize>() {
+        let spec = PrecomputedSpec::<Toy, T, RATE>::new(8, 56);
+        let mds = spec.mds();
+        for rows in 1..1usize << T {
+            for cols in (1..1usize << T).filter(|cols| cols.count_ones() == rows.count_ones()) {
+                let submatrix = (0..T)
+                    .filter(|i| (rows >> i) & 1 == 1)
+                    .map(|i| {
+                        (0..T)
+                            .filter(|j| (cols >> j) & 1 == 1)
+                            .map(|j| mds[i][j])
+                            .collect()
+                    })
+                    .collect();
+                assert_ne!(determinant(submatrix), Toy::ZERO, "t = {}", T);
+            }
+        }
+
+        let pre_sparse = spec.pre_sparse_mds();
+        assert_ne!(
+            determinant(pre_sparse.iter().map(|row| row.to_vec()).collect()),
+            Toy::ZERO
+        );
+        for round in 0..spec.partial_rounds() {
+            let (row, col_hat) = spec.sparse_mds(round);
+            // [row; col_hat | I]
+            let matrix = (0..T)
+                .map(|i| match i {
+                    0 => row.to_vec(),
+                    i => (0..T)
+                        .map(|j| match j {
+                            0 => col_hat[i - 1],
+                            j if j == i => Toy::ONE,
+                            _ => Toy::ZERO,
+                        })
+                        .collect(),
+                })
+                .collect();
+            assert_ne!(determinant(matrix), Toy::ZERO, "t = {}", T);
+        }
+    }
+
+    #[test]
+    fn test_matrices_are_invertible() {
+        check_matrices::<2, 1>();
+        check_matrices::<3, 2>();
+        check_matrices::<4, 3>();
+        check_matrices::<5, 4>();
+    }
+
+    /// Absorbing every element of the field as the first input matches the
+    /// reference permutation and gives pairwise distinct states
+    #[test]
+    fn test_permutation_exhaustive() {
+        let (r_f, r_p) = (8, 56);
+        let spec = PrecomputedSpec::<Toy, 3, 2>::new(r_f, r_p);
+        let reference = Reference::<Toy, 3>::new(r_f, r_p);
+
+        let mut outputs = HashSet::new();
+        for x in Toy::all() {
+            let mut optimized = State::<Toy, 3, 2>::new([Toy::ZERO; 3]);
+            optimized.permutation(&spec, &[x]);
+            let mut expected = [Toy::ZERO, x, Toy::ONE];
+            reference.permute(&mut expected);
+            assert_eq!(optimized.inner, expected, "input {:?}", x);
+            outputs.insert(expected);
+        }
+        assert_eq!(outputs.len() as u64, P);
+    }
+}
@@ -0,0 +1,100 @@
+//! Round-by-round traces of the native permutation, for finding where two
+//! implementations start to disagree.
+//!
+//! With the `debug-trace` feature every [`PoseidonHash`] records its state at
+//! the end of each round; [`PoseidonHash::take_trace`] returns the recorded
+//! states and [`diff`] locates the first round at which two traces differ.
+//!
+//! [`PoseidonHash`]: crate::poseidon_hash::PoseidonHash
+//! [`PoseidonHash::take_trace`]: crate::poseidon_hash::PoseidonHash::take_trace
+use std::fmt;
+
+use halo2curves::group::ff::PrimeField;
+
+use crate::encoding;
+
+/// States at the end of consecutive rounds
+pub type Trace<F, const T: usize> = Vec<[F; T]>;
+
+/// First round at which two traces differ, `None` for a trace that ended
+/// before it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence<F: PrimeField, const T: usize> {
+    /// Index into the traces; with `r_f + r_p` rounds per permutation, the
+    /// permutation is `round / (r_f + r_p)`
+    pub round: usize,
+    pub left: Option<[F; T]>,
+    pub right: Option<[F; T]>,
+}
+
+/// Returns the first round at which `left` and `right` differ, including the
+/// round after the shorter trace ended
+pub fn diff<F: PrimeField, const T: usize>(
+    left: &[[F; T]],
+    right: &[[F; T]],
+) -> Option<Divergence<F, T>> {
+    (0..left.len().max(right.len()))
+        .map(|round| Divergence {
+            round,
+            left: left.get(round).copied(),
+            right: right.get(round).copied(),
+        })
+        .find(|divergence| divergence.left != divergence.right)
+}
+
+impl<F: PrimeField, const T: usize> fmt::Display for Divergence<F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "traces diverge at round {}", self.round)?;
+        for (side, state) in [("left", &self.left), ("right", &self.right)] {
+            match state {
+                Some(state) => {
+                    let state = state.iter().map(encoding::to_hex).collect::<Vec<_>>();
+                    writeln!(f, "  {}: [{}]", side, state.join(", "))?;
+                }
+                None => writeln!(f, "  {}: ended", side)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    use super::*;
+    use crate::{poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait};
+
+    fn trace(spec: PrecomputedSpec<Fr, 3, 2>, inputs: &[Fr]) -> (Fr, Trace<Fr, 3>) {
+        let mut hasher = PoseidonHash::<G1Affine, Fr, 3, 2>::new(spec);
+        hasher.update(inputs);
+        let output = hasher.squeeze();
+        (output, hasher.take_trace())
+    }
+
+    #[test]
+    fn test_trace() {
+        let inputs = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let (output, trace_a) = trace(PrecomputedSpec::new(8, 56), &inputs);
+        // two permutations
+        assert_eq!(trace_a.len(), 2 * 64);
+        assert_eq!(trace_a.last().unwrap()[1], output);
+        assert_eq!(diff(&trace_a, &trace_a), None);
+
+        let (_, trace_b) = trace(
+            PrecomputedSpec::new(8, 56),
+            &[Fr::from(1), Fr::from(2), Fr::from(4)],
+        );
+        assert_eq!(diff(&trace_a, &trace_b).unwrap().round, 64);
+
+        let (_, trace_c) = trace(PrecomputedSpec::new(8, 56), &inputs[..1]);
+        let divergence = diff(&trace_a, &trace_c).unwrap();
+        assert_eq!(divergence.round, 0);
+        assert!(divergence
+            .to_string()
+            .starts_with("traces diverge at round 0\n"));
+
+        let divergence = diff(&trace_a, &trace_a[..70]).unwrap();
+        assert_eq!((divergence.round, divergence.right), (70, None));
+    }
+}
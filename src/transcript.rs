@@ -0,0 +1,573 @@
+//! Fiat-Shamir transcript over [`PoseidonHash`], usable both as a halo2
+//! transcript and, with the `snark-verifier` feature, through snark-verifier's
+//! native-loader transcript traits. Its halo2 challenges are [`Challenge255`]
+//! by default or, choosing the encoding parameter, [`PoseidonChallenge`];
+//! both give the squeezed scalar as the challenge.
+//!
+//! Implementing the traits does not make the challenges those of
+//! snark-verifier's own native Poseidon transcript: that agreement is only
+//! claimed once `test_snark_verifier_challenges_agree` has passed against the
+//! pinned snark-verifier tag, which has not happened yet.
+//!
+//! Scalars are absorbed as they are. Points have coordinates in the base
+//! field, so each coordinate is absorbed as one scalar, its integer reduced
+//! modulo the scalar field. The identity point has no affine coordinates and
+//! is rejected.
+//!
+//! Items are written as their canonical `Repr` bytes. Reading follows the
+//! same schedule the in-circuit verifier has to replicate: read the bytes,
+//! reject non-canonical scalars and points that are not on the curve, absorb
+//! the decoded value, and only then hand it to the caller.
+use std::{
+    io::{self, Read, Write},
+    marker::PhantomData,
+};
+
+use halo2_proofs::{
+    arithmetic::CurveAffine,
+    transcript::{Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptWrite},
+};
+use halo2curves::{
+    group::ff::{FromUniformBytes, PrimeField},
+    Coordinates,
+};
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod record;
+
+use record::{Op, Recording};
+
+/// Width of the limbs base field elements are split into, like
+/// snark-verifier's halo2 loader for bn256
+pub const BITS: usize = 68;
+
+/// Transcript over `stream` giving challenges encoded as `E`, see
+/// [`PoseidonChallenge`]
+pub struct PoseidonTranscript<C, F, S, const T: usize, const RATE: usize, E = Challenge255<C>>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    hasher: PoseidonHash<C, F, T, RATE>,
+    stream: S,
+    recording: Option<Recording<F>>,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<C, F, S, E, const T: usize, const RATE: usize> PoseidonTranscript<C, F, S, T, RATE, E>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(spec: PrecomputedSpec<F, T, RATE>, stream: S) -> Self {
+        Self {
+            hasher: PoseidonHash::new(spec),
+            stream,
+            recording: None,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Records the sponge operations from now on, see [`record`]
+    pub fn start_recording(&mut self) {
+        self.recording.get_or_insert_with(Recording::default);
+    }
+
+    /// Takes the operations recorded since [`Self::start_recording`], which
+    /// stops recording
+    pub fn take_recording(&mut self) -> Option<Recording<F>> {
+        self.recording.take()
+    }
+
+    fn record(&mut self, op: Op<F>) {
+        if let Some(recording) = &mut self.recording {
+            recording.push(op);
+        }
+    }
+
+    /// Absorbs a domain separator or other label as a byte string, see
+    /// [`PoseidonHash::update_bytes`]
+    pub fn common_label(&mut self, label: &[u8]) {
+        self.hasher.update_bytes(label);
+        self.record(Op::Label(label.to_vec()));
+    }
+
+    fn absorb_scalar(&mut self, scalar: F) {
+        self.hasher.update(&[scalar]);
+        self.record(Op::Absorb(vec![scalar]));
+    }
+
+    fn absorb_point(&mut self, point: &C) -> io::Result<()> {
+        let coordinates = Option::<Coordinates<C>>::from(point.coordinates()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cannot absorb the point at infinity",
+            )
+        })?;
+        let encoded = [*coordinates.x(), *coordinates.y()]
+            .into_iter()
+            .map(base_to_scalar::<C::Base, F>)
+            .collect::<Vec<_>>();
+        self.hasher.update(&encoded);
+        self.record(Op::Absorb(encoded));
+        Ok(())
+    }
+
+    fn squeeze(&mut self) -> F {
+        let challenge = self.hasher.squeeze();
+        self.record(Op::Squeeze(challenge));
+        challenge
+    }
+}
+
+impl<C, F, R, E, const T: usize, const RATE: usize> PoseidonTranscript<C, F, R, T, RATE, E>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    R: Read,
+{
+    fn read_and_absorb_scalar(&mut self) -> io::Result<F> {
+        let mut repr = F::Repr::default();
+        self.stream.read_exact(repr.as_mut())?;
+        let scalar = Option::from(F::from_repr(repr))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid scalar encoding"))?;
+        self.absorb_scalar(scalar);
+        Ok(scalar)
+    }
+
+    fn read_and_absorb_point(&mut self) -> io::Result<C> {
+        let mut repr = C::Repr::default();
+        self.stream.read_exact(repr.as_mut())?;
+        let point = Option::from(C::from_bytes(&repr))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid point encoding"))?;
+        self.absorb_point(&point)?;
+        Ok(point)
+    }
+}
+
+impl<C, F, W, E, const T: usize, const RATE: usize> PoseidonTranscript<C, F, W, T, RATE, E>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    W: Write,
+{
+    fn absorb_and_write_scalar(&mut self, scalar: F) -> io::Result<()> {
+        self.absorb_scalar(scalar);
+        self.stream.write_all(scalar.to_repr().as_ref())
+    }
+
+    fn absorb_and_write_point(&mut self, point: C) -> io::Result<()> {
+        self.absorb_point(&point)?;
+        self.stream.write_all(point.to_bytes().as_ref())
+    }
+}
+
+/// The integer of `value` reduced modulo the order of `F`
+pub fn base_to_scalar<B: PrimeField, F: PrimeField>(value: B) -> F {
+    encoding::from_be_bytes_reduced(&encoding::to_be_bytes(&value))
+}
+
+impl<C, F, S, const T: usize, const RATE: usize> Transcript<C, Challenge255<C>>
+    for PoseidonTranscript<C, F, S, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        // Zero-extended, so the challenge scalar is the squeezed element itself
+        let mut bytes = [0u8; 64];
+        let repr = self.squeeze().to_repr();
+        bytes[..repr.as_ref().len()].copy_from_slice(repr.as_ref());
+        Challenge255::new(&bytes)
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.absorb_point(&point)
+    }
+
+    fn common_scalar(&mut self, scalar: F) -> io::Result<()> {
+        self.absorb_scalar(scalar);
+        Ok(())
+    }
+}
+
+/// Challenge holding the squeezed scalar itself, with no truncation
+///
+/// A [`Challenge255`] is reduced from 64 bytes, which the transcript fills
+/// with the zero-extended squeezed scalar, so both encodings give the same
+/// challenge scalar; this one skips the bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoseidonChallenge<C: CurveAffine>(C::Scalar);
+
+impl<C: CurveAffine> EncodedChallenge<C> for PoseidonChallenge<C> {
+    type Input = C::Scalar;
+
+    fn new(challenge_input: &C::Scalar) -> Self {
+        Self(*challenge_input)
+    }
+
+    fn get_scalar(&self) -> C::Scalar {
+        self.0
+    }
+}
+
+impl<C, F, S, const T: usize, const RATE: usize> Transcript<C, PoseidonChallenge<C>>
+    for PoseidonTranscript<C, F, S, T, RATE, PoseidonChallenge<C>>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> PoseidonChallenge<C> {
+        PoseidonChallenge::new(&self.squeeze())
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.absorb_point(&point)
+    }
+
+    fn common_scalar(&mut self, scalar: F) -> io::Result<()> {
+        self.absorb_scalar(scalar);
+        Ok(())
+    }
+}
+
+impl<C, F, R, E, const T: usize, const RATE: usize> TranscriptRead<C, E>
+    for PoseidonTranscript<C, F, R, T, RATE, E>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    E: EncodedChallenge<C>,
+    Self: Transcript<C, E>,
+    R: Read,
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        self.read_and_absorb_point()
+    }
+
+    fn read_scalar(&mut self) -> io::Result<F> {
+        self.read_and_absorb_scalar()
+    }
+}
+
+impl<C, F, W, E, const T: usize, const RATE: usize> TranscriptWrite<C, E>
+    for PoseidonTranscript<C, F, W, T, RATE, E>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+    E: EncodedChallenge<C>,
+    Self: Transcript<C, E>,
+    W: Write,
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.absorb_and_write_point(point)
+    }
+
+    fn write_scalar(&mut self, scalar: F) -> io::Result<()> {
+        self.absorb_and_write_scalar(scalar)
+    }
+}
+
+#[cfg(feature = "snark-verifier")]
+mod snark_verifier_impls {
+    use snark_verifier::{
+        loader::native::{NativeLoader, LOADER},
+        util::transcript::{Transcript, TranscriptRead, TranscriptWrite},
+        Error,
+    };
+
+    use super::*;
+
+    fn transcript_error(err: io::Error) -> Error {
+        Error::Transcript(err.kind(), err.to_string())
+    }
+
+    impl<C, F, S, E, const T: usize, const RATE: usize> Transcript<C, NativeLoader>
+        for PoseidonTranscript<C, F, S, T, RATE, E>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        F: PrimeField + FromUniformBytes<64>,
+    {
+        fn loader(&self) -> &NativeLoader {
+            &LOADER
+        }
+
+        fn squeeze_challenge(&mut self) -> F {
+            self.squeeze()
+        }
+
+        fn common_ec_point(&mut self, ec_point: &C) -> Result<(), Error> {
+            self.absorb_point(ec_point).map_err(transcript_error)
+        }
+
+        fn common_scalar(&mut self, scalar: &F) -> Result<(), Error> {
+            self.absorb_scalar(*scalar);
+            Ok(())
+        }
+    }
+
+    impl<C, F, R, E, const T: usize, const RATE: usize> TranscriptRead<C, NativeLoader>
+        for PoseidonTranscript<C, F, R, T, RATE, E>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        F: PrimeField + FromUniformBytes<64>,
+        R: Read,
+    {
+        fn read_scalar(&mut self) -> Result<F, Error> {
+            self.read_and_absorb_scalar().map_err(transcript_error)
+        }
+
+        fn read_ec_point(&mut self) -> Result<C, Error> {
+            self.read_and_absorb_point().map_err(transcript_error)
+        }
+    }
+
+    impl<C, F, W, E, const T: usize, const RATE: usize> TranscriptWrite<C>
+        for PoseidonTranscript<C, F, W, T, RATE, E>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        F: PrimeField + FromUniformBytes<64>,
+        W: Write,
+    {
+        fn write_scalar(&mut self, scalar: F) -> Result<(), Error> {
+            self.absorb_and_write_scalar(scalar)
+                .map_err(transcript_error)
+        }
+
+        fn write_ec_point(&mut self, ec_point: C) -> Result<(), Error> {
+            self.absorb_and_write_point(ec_point)
+                .map_err(transcript_error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        plonk::{create_proof, keygen_pk, keygen_vk, verify_proof},
+        poly::kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverGWC, VerifierGWC},
+            strategy::SingleStrategy,
+        },
+    };
+    use halo2curves::{
+        bn256::{Bn256, Fq, Fr, G1Affine},
+        group::{ff::Field, prime::PrimeCurveAffine},
+    };
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::test_circuit::TestCircuit;
+
+    const T: usize = 4;
+    const RATE: usize = 3;
+    const R_F: usize = 8;
+    const R_P: usize = 56;
+
+    #[test]
+    fn test_base_to_scalar() {
+        let small = Fq::from(u64::MAX) * Fq::from(1 << 20) + Fq::from(5);
+        assert_eq!(
+            base_to_scalar::<Fq, Fr>(small),
+            Fr::from(u64::MAX) * Fr::from(1 << 20) + Fr::from(5)
+        );
+        // p - 1 is above r, so it wraps to p - 1 - r
+        let p_minus_r = base_to_scalar::<Fq, Fr>(-Fq::ONE) + Fr::ONE;
+        assert_eq!(
+            encoding::to_hex(&p_minus_r),
+            "0x000000000000000000000000000000006f4d8248eeb859fbf83e9682e87cfd46"
+        );
+    }
+
+    fn spec() -> PrecomputedSpec<Fr, T, RATE> {
+        PrecomputedSpec::new(R_F, R_P)
+    }
+
+    #[test]
+    fn test_halo2_transcript_roundtrip() {
+        let point = G1Affine::generator();
+        let mut writer = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), Vec::new());
+        writer.write_scalar(Fr::from(3)).unwrap();
+        writer.write_point(point).unwrap();
+        let challenge = Transcript::<_, Challenge255<_>>::squeeze_challenge(&mut writer);
+        assert!(writer.write_point(G1Affine::identity()).is_err());
+        let proof = writer.into_inner();
+        assert_eq!(proof.len(), 64);
+
+        let mut reader = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), &proof[..]);
+        assert_eq!(reader.read_scalar().unwrap(), Fr::from(3));
+        assert_eq!(reader.read_point().unwrap(), point);
+        let read_challenge = Transcript::<_, Challenge255<_>>::squeeze_challenge(&mut reader);
+        assert_eq!(read_challenge.get_scalar(), challenge.get_scalar());
+
+        let coordinates = point.coordinates().unwrap();
+        let mut hasher = PoseidonHash::<G1Affine, Fr, T, RATE>::new(spec());
+        hasher.update(&[Fr::from(3)]);
+        hasher.update(&[
+            base_to_scalar::<Fq, Fr>(*coordinates.x()),
+            base_to_scalar::<Fq, Fr>(*coordinates.y()),
+        ]);
+        assert_eq!(challenge.get_scalar(), hasher.squeeze());
+    }
+
+    /// A KZG proof of [`TestCircuit`] made and checked with this transcript in
+    /// place of Blake2b
+    #[test]
+    fn test_kzg_proof() {
+        const K: u32 = 10;
+        let params = ParamsKZG::<Bn256>::setup(K, OsRng);
+        let inputs = (0..5).map(Fr::from).collect::<Vec<_>>();
+        let mut hasher = PoseidonHash::<G1Affine, Fr, T, RATE>::new(spec());
+        hasher.update(&inputs);
+        let public_inputs: &[&[Fr]] = &[&[hasher.squeeze()]];
+        let circuit = TestCircuit::new(inputs);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let pk = keygen_pk(&params, vk, &circuit).unwrap();
+
+        let mut transcript =
+            PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), Vec::new());
+        create_proof::<KZGCommitmentScheme<_>, ProverGWC<'_, _>, _, _, _, _>(
+            &params,
+            &pk,
+            &[circuit],
+            &[public_inputs],
+            OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        let mut proof = transcript.into_inner();
+
+        let verify = |proof: &[u8]| {
+            let mut transcript = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), proof);
+            verify_proof::<KZGCommitmentScheme<_>, VerifierGWC<'_, _>, _, _, _>(
+                &params,
+                pk.get_vk(),
+                SingleStrategy::new(&params),
+                &[public_inputs],
+                &mut transcript,
+            )
+        };
+        assert!(verify(&proof).is_ok());
+        let last = proof.len() - 1;
+        proof[last] ^= 1;
+        assert!(verify(&proof).is_err());
+    }
+
+    #[test]
+    fn test_poseidon_challenge() {
+        let mut default = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), Vec::new());
+        let mut plain = PoseidonTranscript::<G1Affine, Fr, _, T, RATE, PoseidonChallenge<_>>::new(
+            spec(),
+            Vec::new(),
+        );
+        for i in 0..3 {
+            default.write_scalar(Fr::from(i)).unwrap();
+            plain.write_scalar(Fr::from(i)).unwrap();
+            let challenge = Transcript::<_, Challenge255<_>>::squeeze_challenge(&mut default);
+            assert_eq!(
+                plain.squeeze_challenge().get_scalar(),
+                challenge.get_scalar()
+            );
+        }
+        assert_eq!(plain.into_inner(), default.into_inner());
+    }
+
+    #[test]
+    fn test_reject_invalid_encodings() {
+        let invalid = [0xffu8; 32];
+        let mut reader = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), &invalid[..]);
+        assert!(reader.read_scalar().is_err());
+        let mut reader = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), &invalid[..]);
+        assert!(reader.read_point().is_err());
+        let mut reader = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), &invalid[..8]);
+        assert_eq!(
+            reader.read_scalar().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[cfg(feature = "snark-verifier")]
+    #[test]
+    fn test_snark_verifier_transcript_roundtrip() {
+        use snark_verifier::util::transcript::{TranscriptRead as _, TranscriptWrite as _};
+
+        let point = G1Affine::generator();
+        let mut writer = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), Vec::new());
+        snark_verifier::util::transcript::TranscriptWrite::write_scalar(&mut writer, Fr::from(3))
+            .unwrap();
+        writer.write_ec_point(point).unwrap();
+        let challenge: Fr =
+            snark_verifier::util::transcript::Transcript::squeeze_challenge(&mut writer);
+        let proof = writer.into_inner();
+
+        let mut reader = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), &proof[..]);
+        assert_eq!(
+            snark_verifier::util::transcript::TranscriptRead::read_scalar(&mut reader).unwrap(),
+            Fr::from(3)
+        );
+        assert_eq!(reader.read_ec_point().unwrap(), point);
+        assert_eq!(
+            snark_verifier::util::transcript::Transcript::squeeze_challenge(&mut reader),
+            challenge
+        );
+    }
+    /// Challenges agree with snark-verifier's own native Poseidon transcript
+    /// reading the same proof, squeeze after squeeze. Not yet run against the
+    /// pinned tag
+    #[cfg(feature = "snark-verifier")]
+    #[test]
+    fn test_snark_verifier_challenges_agree() {
+        use snark_verifier::{
+            loader::native::NativeLoader,
+            system::halo2::transcript::halo2::PoseidonTranscript as NativeTranscript,
+            util::transcript as sv,
+        };
+
+        let point = |i: u64| G1Affine::from(G1Affine::generator() * Fr::from(i));
+        let mut writer = PoseidonTranscript::<G1Affine, Fr, _, T, RATE>::new(spec(), Vec::new());
+        let mut challenges = Vec::new();
+        for i in 1..4 {
+            sv::TranscriptWrite::write_scalar(&mut writer, Fr::from(i)).unwrap();
+            sv::TranscriptWrite::write_ec_point(&mut writer, point(i)).unwrap();
+            challenges.push(sv::Transcript::squeeze_challenge(&mut writer));
+        }
+        challenges.push(sv::Transcript::squeeze_challenge(&mut writer));
+        let proof = writer.into_inner();
+
+        let mut native =
+            NativeTranscript::<G1Affine, NativeLoader, _, T, RATE, R_F, R_P>::new(&proof[..]);
+        for i in 1..4 {
+            assert_eq!(
+                sv::TranscriptRead::read_scalar(&mut native).unwrap(),
+                Fr::from(i)
+            );
+            assert_eq!(
+                sv::TranscriptRead::read_ec_point(&mut native).unwrap(),
+                point(i)
+            );
+            assert_eq!(
+                sv::Transcript::squeeze_challenge(&mut native),
+                challenges[i as usize - 1],
+                "challenge {}",
+                i
+            );
+        }
+        assert_eq!(
+            sv::Transcript::squeeze_challenge(&mut native),
+            challenges[3]
+        );
+    }
+}
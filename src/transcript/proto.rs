@@ -0,0 +1,164 @@
+//! Protobuf messages for transcript items, mirroring `proto/transcript.proto`.
+//!
+//! Provers that contribute to a transcript from different machines can send
+//! [`TranscriptItem`]s, or a whole [`TranscriptLog`], over the network with
+//! prost's (length-delimited) encoding. [`replay`] feeds them into a
+//! [`PoseidonTranscript`] in order, so every party squeezes the same
+//! challenges.
+use std::io;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use super::PoseidonTranscript;
+use crate::encoding;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TranscriptItem {
+    #[prost(oneof = "transcript_item::Item", tags = "1, 2, 3, 4")]
+    pub item: Option<transcript_item::Item>,
+}
+
+pub mod transcript_item {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Item {
+        #[prost(bytes, tag = "1")]
+        Scalar(Vec<u8>),
+        #[prost(bytes, tag = "2")]
+        Point(Vec<u8>),
+        #[prost(bytes, tag = "3")]
+        Label(Vec<u8>),
+        #[prost(message, tag = "4")]
+        Squeeze(super::Squeeze),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Squeeze {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TranscriptLog {
+    #[prost(message, repeated, tag = "1")]
+    pub items: Vec<TranscriptItem>,
+}
+
+impl TranscriptItem {
+    fn new(item: transcript_item::Item) -> Self {
+        Self { item: Some(item) }
+    }
+
+    pub fn scalar<F: PrimeField>(scalar: &F) -> Self {
+        Self::new(transcript_item::Item::Scalar(encoding::to_be_bytes(scalar)))
+    }
+
+    pub fn point<C: CurveAffine>(point: &C) -> Self {
+        Self::new(transcript_item::Item::Point(
+            point.to_bytes().as_ref().to_vec(),
+        ))
+    }
+
+    pub fn label(label: &[u8]) -> Self {
+        Self::new(transcript_item::Item::Label(label.to_vec()))
+    }
+
+    pub fn squeeze() -> Self {
+        Self::new(transcript_item::Item::Squeeze(Squeeze {}))
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Absorbs `items` into `transcript` in order and returns the challenges
+/// squeezed by their `Squeeze` items. Malformed scalars and points are
+/// rejected with [`io::ErrorKind::InvalidData`], as when reading a proof.
+pub fn replay<C, F, S, E, const T: usize, const RATE: usize>(
+    transcript: &mut PoseidonTranscript<C, F, S, T, RATE, E>,
+    items: &[TranscriptItem],
+) -> io::Result<Vec<F>>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut challenges = Vec::new();
+    for item in items {
+        match item
+            .item
+            .as_ref()
+            .ok_or_else(|| invalid_data("empty item"))?
+        {
+            transcript_item::Item::Scalar(bytes) => {
+                let scalar = encoding::from_be_bytes(bytes)
+                    .ok_or_else(|| invalid_data("invalid scalar encoding"))?;
+                transcript.absorb_scalar(scalar);
+            }
+            transcript_item::Item::Point(bytes) => {
+                let mut repr = C::Repr::default();
+                if bytes.len() != repr.as_ref().len() {
+                    return Err(invalid_data("invalid point encoding"));
+                }
+                repr.as_mut().copy_from_slice(bytes);
+                let point = Option::<C>::from(C::from_bytes(&repr))
+                    .ok_or_else(|| invalid_data("invalid point encoding"))?;
+                transcript.absorb_point(&point)?;
+            }
+            transcript_item::Item::Label(label) => transcript.common_label(label),
+            transcript_item::Item::Squeeze(_) => challenges.push(transcript.squeeze()),
+        }
+    }
+    Ok(challenges)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::transcript::{Challenge255, EncodedChallenge, Transcript, TranscriptWrite};
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::prime::PrimeCurveAffine,
+    };
+    use prost::Message;
+
+    use super::{transcript_item::Item, *};
+    use crate::precomputed::PrecomputedSpec;
+
+    fn transcript<S>(stream: S) -> PoseidonTranscript<G1Affine, Fr, S, 4, 3> {
+        PoseidonTranscript::new(PrecomputedSpec::new(8, 56), stream)
+    }
+
+    #[test]
+    fn test_replay_matches_transcript() {
+        let point = G1Affine::generator();
+        let mut writer = transcript(Vec::new());
+        writer.common_label(b"round 1");
+        writer.write_scalar(Fr::from(3)).unwrap();
+        writer.write_point(point).unwrap();
+        let challenge = Transcript::<_, Challenge255<_>>::squeeze_challenge(&mut writer);
+
+        let log = TranscriptLog {
+            items: vec![
+                TranscriptItem::label(b"round 1"),
+                TranscriptItem::scalar(&Fr::from(3)),
+                TranscriptItem::point(&point),
+                TranscriptItem::squeeze(),
+            ],
+        };
+        let decoded = TranscriptLog::decode(log.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, log);
+
+        let challenges = replay(&mut transcript(()), &decoded.items).unwrap();
+        assert_eq!(challenges, vec![challenge.get_scalar()]);
+    }
+
+    #[test]
+    fn test_replay_rejects_invalid_items() {
+        let invalid = [
+            TranscriptItem::new(Item::Scalar(vec![0xff; 32])),
+            TranscriptItem::new(Item::Point(vec![0x01; 31])),
+            TranscriptItem { item: None },
+        ];
+        for item in invalid {
+            assert!(replay(&mut transcript(()), &[item]).is_err());
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Recordings of the sponge operations of a [`PoseidonTranscript`], for
+//! reproducing "prover and verifier disagree" reports.
+//!
+//! After [`PoseidonTranscript::start_recording`], every label, absorbed
+//! element and squeezed challenge is appended to a [`Recording`]. When a
+//! prover and a verifier end up with different challenges,
+//! [`dump_on_mismatch`] writes both recordings to a directory and returns
+//! the first operation at which they differ. A recording is plain text, one
+//! operation per line:
+//!
+//! ```text
+//! label 0x6e6f7661
+//! absorb 0x..01, 0x..02
+//! squeeze 0x..
+//! ```
+//!
+//! [`Recording::replay`] runs the operations of a loaded recording through a
+//! fresh [`PoseidonHash`] one [`Recording::step`] at a time and reports the
+//! first squeeze that does not reproduce, so a breakpoint on `step` at that
+//! index stops right before the faulty permutation.
+//!
+//! [`PoseidonTranscript`]: super::PoseidonTranscript
+//! [`PoseidonTranscript::start_recording`]: super::PoseidonTranscript::start_recording
+use std::{fmt, fs, io, path::Path};
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2curves::group::ff::{FromUniformBytes, PrimeField};
+
+use crate::{
+    encoding, poseidon_hash::PoseidonHash, precomputed::PrecomputedSpec, ro_types::ROTrait,
+};
+
+/// One operation on the sponge of a transcript
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<F: PrimeField> {
+    /// A byte string absorbed with [`PoseidonHash::update_bytes`]
+    Label(Vec<u8>),
+    /// Elements absorbed with [`PoseidonHash::update`]: a scalar, or the
+    /// coordinates of a point
+    Absorb(Vec<F>),
+    /// A squeezed challenge
+    Squeeze(F),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording<F: PrimeField> {
+    pub ops: Vec<Op<F>>,
+}
+
+impl<F: PrimeField> Recording<F> {
+    pub(crate) fn push(&mut self, op: Op<F>) {
+        self.ops.push(op);
+    }
+
+    /// Index of the first operation that differs, including the first one
+    /// missing from the shorter recording
+    pub fn first_divergence(&self, other: &Self) -> Option<usize> {
+        (0..self.ops.len().max(other.ops.len())).find(|i| self.ops.get(*i) != other.ops.get(*i))
+    }
+
+    /// Parses the text format of [`fmt::Display`]
+    pub fn parse(text: &str) -> Option<Self> {
+        let ops = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (kind, values) = line.split_once(' ').unwrap_or((line, ""));
+                match kind {
+                    "label" => encoding::bytes_from_hex(values).map(Op::Label),
+                    "absorb" => values
+                        .split(", ")
+                        .filter(|value| !value.is_empty())
+                        .map(encoding::from_hex)
+                        .collect::<Option<_>>()
+                        .map(Op::Absorb),
+                    "squeeze" => encoding::from_hex(values).map(Op::Squeeze),
+                    _ => None,
+                }
+            })
+            .collect::<Option<_>>()?;
+        Some(Self { ops })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed recording"))
+    }
+}
+
+impl<F: PrimeField + FromUniformBytes<64>> Recording<F> {
+    /// Applies operation `index` to `hasher`, returning whether a squeeze
+    /// reproduced the recorded challenge
+    #[inline(never)]
+    pub fn step<C, const T: usize, const RATE: usize>(
+        &self,
+        hasher: &mut PoseidonHash<C, F, T, RATE>,
+        index: usize,
+    ) -> bool
+    where
+        C: CurveAffine<ScalarExt = F>,
+    {
+        match &self.ops[index] {
+            Op::Label(label) => hasher.update_bytes(label),
+            Op::Absorb(elements) => hasher.update(elements),
+            Op::Squeeze(challenge) => return hasher.squeeze() == *challenge,
+        }
+        true
+    }
+
+    /// Replays the recording on a fresh sponge, returning the index of the
+    /// first squeeze that gives another challenge
+    pub fn replay<C, const T: usize, const RATE: usize>(
+        &self,
+        spec: PrecomputedSpec<F, T, RATE>,
+    ) -> Result<(), usize>
+    where
+        C: CurveAffine<ScalarExt = F>,
+    {
+        let mut hasher = PoseidonHash::<C, F, T, RATE>::new(spec);
+        match (0..self.ops.len()).find(|index| !self.step(&mut hasher, *index)) {
+            Some(index) => Err(index),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F: PrimeField> fmt::Display for Recording<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            match op {
+                Op::Label(label) => writeln!(f, "label {}", encoding::bytes_to_hex(label))?,
+                Op::Absorb(elements) => {
+                    let elements = elements.iter().map(encoding::to_hex).collect::<Vec<_>>();
+                    writeln!(f, "absorb {}", elements.join(", "))?
+                }
+                Op::Squeeze(challenge) => writeln!(f, "squeeze {}", encoding::to_hex(challenge))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares the recordings of a prover and a verifier. If they differ, both
+/// are written to `dir` as `prover.transcript` and `verifier.transcript` and
+/// the index of the first differing operation is returned.
+pub fn dump_on_mismatch<F: PrimeField>(
+    prover: &Recording<F>,
+    verifier: &Recording<F>,
+    dir: &Path,
+) -> io::Result<Option<usize>> {
+    let divergence = prover.first_divergence(verifier);
+    if divergence.is_some() {
+        fs::create_dir_all(dir)?;
+        prover.save(&dir.join("prover.transcript"))?;
+        verifier.save(&dir.join("verifier.transcript"))?;
+    }
+    Ok(divergence)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::transcript::{Challenge255, Transcript, TranscriptRead, TranscriptWrite};
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::prime::PrimeCurveAffine,
+    };
+
+    use super::*;
+    use crate::transcript::PoseidonTranscript;
+
+    type Tr<S> = PoseidonTranscript<G1Affine, Fr, S, 4, 3>;
+
+    fn spec() -> PrecomputedSpec<Fr, 4, 3> {
+        PrecomputedSpec::new(8, 56)
+    }
+
+    /// Records a prover writing a scalar and a point, and a verifier reading
+    /// them back from `corrupt(proof)`
+    fn record(corrupt: impl Fn(&mut Vec<u8>)) -> (Recording<Fr>, Recording<Fr>) {
+        let mut prover = Tr::new(spec(), Vec::new());
+        prover.start_recording();
+        prover.common_label(b"nova");
+        prover.write_scalar(Fr::from(3)).unwrap();
+        prover.write_point(G1Affine::generator()).unwrap();
+        Transcript::<_, Challenge255<_>>::squeeze_challenge(&mut prover);
+        let prover_recording = prover.take_recording().unwrap();
+        let mut proof = prover.into_inner();
+        corrupt(&mut proof);
+
+        let mut verifier = Tr::new(spec(), &proof[..]);
+        verifier.start_recording();
+        verifier.common_label(b"nova");
+        verifier.read_scalar().unwrap();
+        verifier.read_point().unwrap();
+        Transcript::<_, Challenge255<_>>::squeeze_challenge(&mut verifier);
+        (prover_recording, verifier.take_recording().unwrap())
+    }
+
+    #[test]
+    fn test_recording() {
+        let (prover, verifier) = record(|_| ());
+        assert_eq!(prover, verifier);
+        assert_eq!(prover.ops.len(), 4);
+        assert_eq!(prover.ops[0], Op::Label(b"nova".to_vec()));
+        assert_eq!(prover.ops[1], Op::Absorb(vec![Fr::from(3)]));
+        assert!(matches!(&prover.ops[2], Op::Absorb(coordinates) if coordinates.len() == 2));
+        assert_eq!(prover.replay::<G1Affine, 4, 3>(spec()), Ok(()));
+
+        let text = prover.to_string();
+        assert!(text.starts_with("label 0x6e6f7661\nabsorb 0x"));
+        assert_eq!(Recording::parse(&text), Some(prover));
+        assert_eq!(Recording::<Fr>::parse("squeeze 0x01"), None);
+        assert_eq!(Recording::<Fr>::parse("permute"), None);
+    }
+
+    #[test]
+    fn test_mismatch_reproduction() {
+        // a different scalar: the verifier diverges at its absorb
+        let (prover, verifier) = record(|proof| proof[0] ^= 1);
+        assert_eq!(prover.first_divergence(&verifier), Some(1));
+
+        let dir = std::env::temp_dir().join(format!("poseidon-record-{}", std::process::id()));
+        assert_eq!(dump_on_mismatch(&prover, &verifier, &dir).unwrap(), Some(1));
+        let loaded = Recording::<Fr>::load(&dir.join("verifier.transcript")).unwrap();
+        assert_eq!(loaded, verifier);
+        assert_eq!(
+            dump_on_mismatch(&prover, &prover, &dir.join("unused")).unwrap(),
+            None
+        );
+        assert!(!dir.join("unused").exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        // a recorded challenge that the sponge does not reproduce
+        let mut tampered = prover.clone();
+        tampered.ops[3] = Op::Squeeze(Fr::from(1));
+        assert_eq!(tampered.replay::<G1Affine, 4, 3>(spec()), Err(3));
+    }
+}
@@ -0,0 +1,419 @@
+//! A verifiable random function over a curve `C`, with every hash a
+//! Poseidon digest over the scalar field.
+//!
+//! The output for the input `alpha` under the key `pk = sk * G` is derived
+//! from `Gamma = sk * H`, where `H` is [`hash_to_point`] of `pk` and
+//! `alpha`. The proof is `Gamma` with a Chaum–Pedersen proof that `Gamma`
+//! and `pk` have the same discrete logarithm in the bases `H` and `G`:
+//! `(c, s)` with `c` the challenge of `U = s * G - c * pk` and
+//! `V = s * H - c * Gamma`, as in ECVRF (RFC 9381).
+//!
+//! Every digest is the one of a [`PoseidonHash`] that absorbed a label,
+//! packed with [`pack_bytes`] like every label of the crate, then points with
+//! [`PoseidonHash::absorb_point`]:
+//!
+//! - `H`: the label `poseidon-vrf-point`, `pk`, then `alpha`; the successive
+//!   squeezes of the sponge taken with [`PoseidonHash::squeeze_base`] are
+//!   candidate abscissas, and the first one on the curve gives `H` with its
+//!   even ordinate. This takes two squeezes on average and is not constant
+//!   time, which leaks nothing secret as `pk` and `alpha` are public.
+//!   [`hash_to_curve`](crate::hash_to_curve::hash_to_curve) is not used as
+//!   it hashes elements of the base field, while `alpha` and the digests
+//!   here are over the scalar field.
+//! - `c`: the label `poseidon-vrf-challenge`, `pk`, `H`, `Gamma`, `U`, `V`
+//! - the output: the label `poseidon-vrf-output`, then `Gamma`
+//!
+//! [`VrfHashChip`] assigns the challenge and the output in a circuit from the
+//! [`point_elements`] of the points. It does not verify a proof: like
+//! [`sig`](crate::sig), the crate has no curve chip, so `U`, `V`, their
+//! group equations and `H` being [`hash_to_point`] of `pk` and `alpha` are
+//! left to a non-native curve chip of the caller.
+//!
+//! [`point_elements`]: crate::sig::point_elements
+use std::marker::PhantomData;
+
+use halo2_proofs::{arithmetic::CurveAffine, circuit::Chip, plonk::Error};
+use halo2curves::group::{
+    ff::{Field, FromUniformBytes, PrimeField},
+    Curve,
+};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    encoding::pack_bytes,
+    main_gate::{AssignedValue, MainGate, MainGateConfig, RegionCtx},
+    poseidon_circuit::PoseidonChip,
+    poseidon_hash::PoseidonHash,
+    precomputed::PrecomputedSpec,
+    ro_types::ROTrait,
+    sig::public_key,
+    transcript::BITS,
+};
+
+const POINT: &[u8] = b"poseidon-vrf-point";
+const CHALLENGE: &[u8] = b"poseidon-vrf-challenge";
+const OUTPUT: &[u8] = b"poseidon-vrf-output";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Proof<C: CurveAffine> {
+    pub gamma: C,
+    pub c: C::Scalar,
+    pub s: C::Scalar,
+}
+
+fn labelled<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    label: &[u8],
+) -> PoseidonHash<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut hasher = PoseidonHash::new(spec.clone());
+    hasher.update(&pack_bytes(label));
+    hasher
+}
+
+/// The point `H` of `public_key` and `alpha`, whose discrete logarithm is
+/// unknown
+pub fn hash_to_point<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    public_key: &C,
+    alpha: &[F],
+) -> C
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut hasher = labelled(spec, POINT);
+    hasher.absorb_point(public_key);
+    hasher.update(alpha);
+    loop {
+        let x = hasher.squeeze_base();
+        let y2 = x.square() * x + C::a() * x + C::b();
+        if let Some(y) = Option::<C::Base>::from(y2.sqrt()) {
+            let y = if bool::from(y.is_odd()) { -y } else { y };
+            return C::from_xy(x, y).unwrap();
+        }
+    }
+}
+
+/// The challenge of a proof for `H`, `Gamma`, `U` and `V`
+pub fn challenge<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    public_key: &C,
+    points: [&C; 4],
+) -> F
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut hasher = labelled(spec, CHALLENGE);
+    hasher.absorb_point(public_key);
+    for point in points {
+        hasher.absorb_point(point);
+    }
+    hasher.squeeze()
+}
+
+/// The output of `gamma`
+pub fn output<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    gamma: &C,
+) -> F
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let mut hasher = labelled(spec, OUTPUT);
+    hasher.absorb_point(gamma);
+    hasher.squeeze()
+}
+
+/// The output for `alpha` under `secret` and its proof, with a nonce drawn
+/// from `rng`
+pub fn prove<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    secret: &F,
+    alpha: &[F],
+    rng: impl RngCore + CryptoRng,
+) -> (F, Proof<C>)
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    let pk = public_key::<C>(secret);
+    let h = hash_to_point(spec, &pk, alpha);
+    let gamma = (h * secret).to_affine();
+    let nonce = F::random(rng);
+    let u = public_key::<C>(&nonce);
+    let v = (h * nonce).to_affine();
+    let c = challenge(spec, &pk, [&h, &gamma, &u, &v]);
+    let proof = Proof {
+        gamma,
+        c,
+        s: nonce + c * secret,
+    };
+    (output(spec, &gamma), proof)
+}
+
+/// The output for `alpha` under `public_key` if `proof` proves it, `None`
+/// otherwise. The identity is not a valid public key.
+pub fn verify<C, F, const T: usize, const RATE: usize>(
+    spec: &PrecomputedSpec<F, T, RATE>,
+    public_key: &C,
+    alpha: &[F],
+    proof: &Proof<C>,
+) -> Option<F>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    if bool::from(public_key.is_identity()) {
+        return None;
+    }
+    let h = hash_to_point(spec, public_key, alpha);
+    let u = (C::generator() * proof.s - *public_key * proof.c).to_affine();
+    let v = (h * proof.s - proof.gamma * proof.c).to_affine();
+    let c = challenge(spec, public_key, [&h, &proof.gamma, &u, &v]);
+    (c == proof.c).then(|| output(spec, &proof.gamma))
+}
+
+/// [`challenge`] and [`output`] over the main gate, the hashing part of a
+/// proof verification gadget without the group equations and `H`
+pub struct VrfHashChip<
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField,
+    const T: usize,
+    const RATE: usize,
+> {
+    main_gate: MainGate<F, T>,
+    spec: PrecomputedSpec<F, T, RATE>,
+    _marker: PhantomData<C>,
+}
+
+impl<C, F, const T: usize, const RATE: usize> VrfHashChip<C, F, T, RATE>
+where
+    C: CurveAffine<ScalarExt = F>,
+    F: PrimeField + FromUniformBytes<64>,
+{
+    pub fn new(config: MainGateConfig<T>, spec: PrecomputedSpec<F, T, RATE>) -> Self {
+        Self {
+            main_gate: MainGate::new(config),
+            spec,
+            _marker: PhantomData,
+        }
+    }
+
+    /// A sponge that absorbed `label` as constants, then `points`, each of
+    /// as many elements as [`point_elements`](crate::sig::point_elements)
+    fn labelled(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        label: &[u8],
+        points: &[&[AssignedValue<F>]],
+    ) -> Result<PoseidonChip<F, T, RATE>, Error> {
+        let elements = 2 * (C::Base::NUM_BITS as usize).div_ceil(BITS) + 1;
+        let mut chip = PoseidonChip::new(self.main_gate.config().clone(), self.spec.clone());
+        for word in pack_bytes::<F>(label) {
+            let word = self.main_gate.assign_constant(ctx, word)?;
+            chip.update_assigned(&[word]);
+        }
+        for point in points {
+            assert_eq!(point.len(), elements, "point of another width");
+            chip.update_assigned(point);
+        }
+        Ok(chip)
+    }
+
+    /// The challenge of the assigned
+    /// [`point_elements`](crate::sig::point_elements) of `public_key`,
+    /// `H`, `Gamma`, `U` and `V`, equal to the one of [`challenge`]. The
+    /// caller constrains the elements to the limbs of its points, checks
+    /// that `H` is [`hash_to_point`] of the key and the input, and that
+    /// `U` and `V` are the points of the proof.
+    ///
+    /// # Panics
+    ///
+    /// If a point does not have as many elements as
+    /// [`point_elements`](crate::sig::point_elements)
+    pub fn challenge(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        public_key: &[AssignedValue<F>],
+        points: [&[AssignedValue<F>]; 4],
+    ) -> Result<AssignedValue<F>, Error> {
+        let mut all = vec![public_key];
+        all.extend(points);
+        self.labelled(ctx, CHALLENGE, &all)?.squeeze(ctx)
+    }
+
+    /// The output of the assigned
+    /// [`point_elements`](crate::sig::point_elements) of `gamma`, equal to
+    /// the one of [`output`]
+    ///
+    /// # Panics
+    ///
+    /// If `gamma` does not have as many elements as
+    /// [`point_elements`](crate::sig::point_elements)
+    pub fn output(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        gamma: &[AssignedValue<F>],
+    ) -> Result<AssignedValue<F>, Error> {
+        self.labelled(ctx, OUTPUT, &[gamma])?.squeeze(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2curves::{
+        bn256::{Fr, G1Affine},
+        group::{prime::PrimeCurveAffine, Group},
+    };
+
+    use super::*;
+    use crate::{
+        rng::PoseidonRng,
+        sig::point_elements,
+        test_fixture::{spec, FixtureConfig, RATE, T},
+    };
+
+    fn rng(seed: &[u8]) -> PoseidonRng<G1Affine, Fr, T, RATE> {
+        let mut hasher = PoseidonHash::new(spec());
+        hasher.update_bytes(seed);
+        PoseidonRng::new(hasher)
+    }
+
+    fn prove_with(secret: u64, alpha: &[Fr], seed: &[u8]) -> (Fr, Proof<G1Affine>) {
+        prove(&spec(), &Fr::from(secret), alpha, rng(seed))
+    }
+
+    #[test]
+    fn test_prove_verify() {
+        let alpha = [Fr::from(1), Fr::from(2)];
+        let pk = public_key::<G1Affine>(&Fr::from(1234));
+        let (beta, proof) = prove_with(1234, &alpha, b"nonce");
+        assert_eq!(verify(&spec(), &pk, &alpha, &proof), Some(beta));
+
+        // the output is unique: another nonce gives another proof of it
+        let (again, other) = prove_with(1234, &alpha, b"other nonce");
+        assert_eq!(again, beta);
+        assert_ne!(other, proof);
+        assert_ne!(prove_with(1234, &alpha[..1], b"nonce").0, beta);
+        assert_ne!(prove_with(1235, &alpha, b"nonce").0, beta);
+
+        assert_eq!(verify(&spec(), &pk, &alpha[..1], &proof), None);
+        let pk_other = public_key(&Fr::from(1235));
+        assert_eq!(verify(&spec(), &pk_other, &alpha, &proof), None);
+        let forged = Proof {
+            gamma: (proof.gamma + G1Affine::generator()).to_affine(),
+            ..proof
+        };
+        assert_eq!(verify(&spec(), &pk, &alpha, &forged), None);
+        let forged = Proof {
+            s: proof.s + Fr::ONE,
+            ..proof
+        };
+        assert_eq!(verify(&spec(), &pk, &alpha, &forged), None);
+        assert_eq!(verify(&spec(), &G1Affine::identity(), &alpha, &proof), None);
+    }
+
+    #[test]
+    fn test_label() {
+        // a label is absorbed like any byte string
+        let mut labelled = labelled::<G1Affine, _, T, RATE>(&spec(), POINT);
+        let mut expected = PoseidonHash::<G1Affine, Fr, T, RATE>::new(spec());
+        expected.update_bytes(POINT);
+        assert_eq!(labelled.squeeze(), expected.squeeze());
+    }
+
+    #[test]
+    fn test_hash_to_point() {
+        let pk = public_key::<G1Affine>(&Fr::from(1234));
+        let h = hash_to_point(&spec(), &pk, &[Fr::from(1)]);
+        assert!(bool::from(h.is_on_curve()));
+        assert!(!bool::from(h.coordinates().unwrap().y().is_odd()));
+        assert_ne!(h, hash_to_point(&spec(), &pk, &[Fr::from(2)]));
+        assert_ne!(h, G1Affine::generator());
+        assert!(!bool::from(h.to_curve().is_identity()));
+    }
+
+    struct VrfCircuit {
+        public_key: G1Affine,
+        points: [G1Affine; 4],
+    }
+
+    impl Circuit<Fr> for VrfCircuit {
+        type Config = FixtureConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                public_key: G1Affine::identity(),
+                points: [G1Affine::identity(); 4],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FixtureConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = VrfHashChip::<G1Affine, _, T, RATE>::new(config.main_gate.clone(), spec());
+            let cells = layouter.assign_region(
+                || "vrf",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let public_key = config.witness(ctx, point_elements(&self.public_key))?;
+                    let points = self
+                        .points
+                        .iter()
+                        .map(|point| config.witness(ctx, point_elements(point)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let [h, gamma, u, v] = [0, 1, 2, 3].map(|i| points[i].as_slice());
+                    let c = chip.challenge(ctx, &public_key, [h, gamma, u, v])?;
+                    let beta = chip.output(ctx, gamma)?;
+                    Ok([c, beta])
+                },
+            )?;
+            for (row, cell) in cells.iter().enumerate() {
+                layouter.constrain_instance(cell.cell(), config.instance, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        let alpha = [Fr::from(1), Fr::from(2)];
+        let public_key = public_key::<G1Affine>(&Fr::from(1234));
+        let (beta, proof) = prove_with(1234, &alpha, b"nonce");
+        let h = hash_to_point(&spec(), &public_key, &alpha);
+        let u = (G1Affine::generator() * proof.s - public_key * proof.c).to_affine();
+        let v = (h * proof.s - proof.gamma * proof.c).to_affine();
+        let circuit = VrfCircuit {
+            public_key,
+            points: [h, proof.gamma, u, v],
+        };
+        let prover = MockProver::run(13, &circuit, vec![vec![proof.c, beta]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the challenge is bound to the order of the points
+        let circuit = VrfCircuit {
+            public_key,
+            points: [h, proof.gamma, v, u],
+        };
+        let prover = MockProver::run(13, &circuit, vec![vec![proof.c, beta]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}